@@ -6,6 +6,48 @@ fn main() {
         "set_property",
         "get_property",
         "set_video_margin_ratio",
+        "get_profiles",
+        "apply_profile",
+        "reload_config",
+        "get_capabilities",
+        "start_render",
+        "resize_render",
+        "screenshot",
+        "save_screenshot",
+        "get_thumbnail",
+        "generate_storyboard",
+        "cancel_storyboard",
+        "probe",
+        "list_tracks",
+        "select_track",
+        "add_external_track",
+        "remove_track",
+        "list_editions",
+        "select_edition",
+        "list_chapters",
+        "next_chapter",
+        "prev_chapter",
+        "seek_chapter",
+        "get_playlist",
+        "playlist_append",
+        "playlist_insert_at",
+        "playlist_insert_next",
+        "playlist_move",
+        "playlist_remove",
+        "playlist_clear",
+        "playlist_play_index",
+        "playlist_play_entry",
+        "playlist_export",
+        "playlist_import",
+        "get_queue",
+        "set_repeat",
+        "set_shuffle",
+        "queue_next",
+        "queue_previous",
+        "load_directory",
+        "get_resume_point",
+        "list_resume_points",
+        "clear_resume_point",
     ])
     .android_path("android")
     .ios_path("ios")
@@ -1,9 +1,8 @@
 import { useEffect } from 'react'
 import { init, observeProperties, MpvConfig, destroy, MpvObservableProperty, listenEvents } from 'tauri-plugin-libmpv-api'
-import usePlayerStore, { MpvPlaylistItem } from '../store'
+import usePlayerStore from '../store'
 
 const OBSERVED_PROPERTIES = [
-  ['playlist', 'node'],
   ['filename', 'string', 'none'],
   ['pause', 'flag'],
   ['eof-reached', 'flag', 'none'],
@@ -51,6 +50,9 @@ const useMpv = () => {
   useEffect(() => {
     const unlistenPromise = listenEvents(
       (mpvEvent) => {
+        if (mpvEvent.event === 'playlist-changed') {
+          updatePlayerState('playlist', mpvEvent.entries)
+        }
         if (mpvEvent.event == 'property-change' && mpvEvent.name !== 'time-pos') {
           console.log(mpvEvent)
         } else if (mpvEvent.event !== 'property-change') {
@@ -68,9 +70,6 @@ const useMpv = () => {
       OBSERVED_PROPERTIES,
       ({ name, data }) => {
         switch (name) {
-          case 'playlist':
-            updatePlayerState('playlist', data as MpvPlaylistItem[])
-            break
           case 'filename':
             updatePlayerState('filename', data)
             break
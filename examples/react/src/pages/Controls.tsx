@@ -4,7 +4,7 @@ import formatTime from '../utils/formatTime'
 import { useState } from 'react'
 import usePlayerStore from '../store'
 import { getCurrentWindow } from '@tauri-apps/api/window'
-import { loadFile, seek, stop, play, pause, playlistPrev, playlistNext, playlistPlay } from '../utils/commands'
+import { loadFile, loadFolder, seek, stop, play, pause, playlistPrev, playlistNext, playlistPlay } from '../utils/commands'
 
 const Controls = () => {
 
@@ -26,7 +26,7 @@ const Controls = () => {
     })
 
     if (file) {
-      await loadFile(file)
+      await (folder ? loadFolder(file) : loadFile(file))
     }
   }
 
@@ -103,7 +103,7 @@ const Controls = () => {
           {
             playlist.map((item, index) => (
               <div
-                key={index}
+                key={item.id}
                 className={`playlist-item ${item.current ? 'active' : ''}`}
                 onClick={() => {
                   playlistPlay(index)
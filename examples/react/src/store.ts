@@ -1,5 +1,6 @@
 
 import { create, StoreApi, UseBoundStore } from 'zustand'
+import type { MpvPlaylistEntry } from 'tauri-plugin-libmpv-api'
 
 type WithSelectors<S> = S extends { getState: () => infer T }
   ? S & { use: { [K in keyof T]: () => T[K] } }
@@ -23,19 +24,10 @@ export const createSelectors = <S extends UseBoundStore<StoreApi<object>>>(
   return store
 }
 
-export interface MpvPlaylistItem {
-  filename: string;
-  playing?: boolean;
-  current?: boolean;
-  title?: string;
-  id: number;
-  'playlist-path'?: string;
-}
-
 export interface PlayerStoreState {
   isInitalized: boolean;
   isPaused: boolean;
-  playlist: MpvPlaylistItem[];
+  playlist: MpvPlaylistEntry[];
   filename: string | null;
   eofReached: boolean;
   timePos: number;
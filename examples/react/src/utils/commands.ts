@@ -1,12 +1,21 @@
-import { command, setProperty } from 'tauri-plugin-libmpv-api'
+import { command, loadDirectory, playlistPlayIndex, setProperty } from 'tauri-plugin-libmpv-api'
 
 export const loadFile = async (file: string) => {
   await command('loadfile', [file])
   await play()
 }
 
+export const loadFolder = async (folder: string) => {
+  await command('stop')
+  const files = await loadDirectory(folder, { recursive: true })
+  if (files.length > 0) {
+    await playlistPlay(0)
+    await play()
+  }
+}
+
 export const playlistPlay = async (index: number) => {
-  await command('playlist-play-index', [index])
+  await playlistPlayIndex(index)
 }
 
 export const playlistNext = async () => {
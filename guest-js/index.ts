@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/core'
+import { Channel, invoke } from '@tauri-apps/api/core'
 import { getCurrentWindow } from '@tauri-apps/api/window'
 import { listen, UnlistenFn } from '@tauri-apps/api/event'
 
@@ -10,6 +10,23 @@ import type {
   MpvFormatToType,
   MpvFormat,
   MpvObservableProperty,
+  MpvProfile,
+  MpvCapabilities,
+  MpvScreenshotOptions,
+  MpvStoryboard,
+  MpvStoryboardOptions,
+  MpvMediaInfo,
+  MpvTrack,
+  MpvTrackType,
+  MpvExternalTrackOptions,
+  MpvChapter,
+  MpvEdition,
+  MpvPlaylistEntry,
+  MpvQueueState,
+  MpvRepeatMode,
+  MpvMediaFile,
+  MpvLoadDirectoryOptions,
+  MpvResumePoint,
 } from './types'
 
 export * from './types'
@@ -373,3 +390,1134 @@ export async function setVideoMarginRatio(ratio: VideoMarginRatio, windowLabel?:
     windowLabel,
   })
 }
+
+/**
+ * Get the profiles defined in the loaded mpv config.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvProfile[]>} A promise that resolves with the profile list.
+ *
+ * @example
+ * ```typescript
+ * import { getProfiles } from 'tauri-plugin-libmpv-api';
+ *
+ * const profiles = await getProfiles();
+ * console.log(profiles.map(profile => profile.name));
+ * ```
+ */
+export async function getProfiles(windowLabel?: string): Promise<MpvProfile[]> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvProfile[]>('plugin:libmpv|get_profiles', {
+    windowLabel,
+  })
+}
+
+/**
+ * Apply an mpv profile.
+ * @param {string} name - Profile name
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @throws {Error} Throws error when the profile doesn't exist
+ *
+ * @example
+ * ```typescript
+ * import { applyProfile } from 'tauri-plugin-libmpv-api';
+ *
+ * await applyProfile('anime');
+ * ```
+ */
+export async function applyProfile(name: string, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<void>('plugin:libmpv|apply_profile', {
+    name,
+    windowLabel,
+  })
+}
+
+/**
+ * Reload `mpv.conf` and `input.conf` from the config directory set with `MpvConfig.configDir`.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @throws {Error} Throws error when the instance has no config directory
+ *
+ * @example
+ * ```typescript
+ * import { reloadConfig } from 'tauri-plugin-libmpv-api';
+ *
+ * await reloadConfig();
+ * ```
+ */
+export async function reloadConfig(windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<void>('plugin:libmpv|reload_config', {
+    windowLabel,
+  })
+}
+
+/**
+ * Get the optional features supported by the loaded backend.
+ * Loads `libmpv-wrapper` or libmpv if no instance has been created yet.
+ *
+ * @example
+ * ```typescript
+ * import { getCapabilities } from 'tauri-plugin-libmpv-api';
+ *
+ * const { abiVersion, hooks } = await getCapabilities();
+ * ```
+ */
+export async function getCapabilities(): Promise<MpvCapabilities> {
+  return await invoke<MpvCapabilities>('plugin:libmpv|get_capabilities')
+}
+
+/**
+ * Draw the video of a player created with `renderMode: 'software'` onto a canvas.
+ * Frames are rendered at the displayed size of the canvas and follow it when it is resized.
+ * @param {HTMLCanvasElement} canvas - The canvas to draw into
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<() => void>} A function to call to stop following the canvas size.
+ * @throws {Error} Throws error when the backend has no render API
+ *
+ * @example
+ * ```typescript
+ * import { init, renderToCanvas } from 'tauri-plugin-libmpv-api';
+ *
+ * await init({ renderMode: 'software' });
+ * const stop = await renderToCanvas(document.querySelector('canvas')!);
+ * ```
+ */
+export async function renderToCanvas(canvas: HTMLCanvasElement, windowLabel?: string): Promise<() => void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  const context = canvas.getContext('2d')
+  if (!context) {
+    throw new Error('Failed to get a 2D context for the canvas')
+  }
+
+  const size = () => ({
+    width: Math.max(1, Math.round(canvas.clientWidth * window.devicePixelRatio)),
+    height: Math.max(1, Math.round(canvas.clientHeight * window.devicePixelRatio)),
+  })
+
+  // Every frame is its width and height as little-endian u32s, followed by RGBA pixels.
+  const onFrame = new Channel<ArrayBuffer>()
+  onFrame.onmessage = (frame) => {
+    const header = new DataView(frame, 0, 8)
+    const width = header.getUint32(0, true)
+    const height = header.getUint32(4, true)
+    if (canvas.width !== width || canvas.height !== height) {
+      canvas.width = width
+      canvas.height = height
+    }
+    const pixels = new Uint8ClampedArray(frame, 8, width * height * 4)
+    context.putImageData(new ImageData(pixels, width, height), 0, 0)
+  }
+
+  await invoke<void>('plugin:libmpv|start_render', {
+    ...size(),
+    onFrame,
+    windowLabel,
+  })
+
+  const observer = new ResizeObserver(() => {
+    invoke<void>('plugin:libmpv|resize_render', {
+      ...size(),
+      windowLabel,
+    }).catch(console.error)
+  })
+  observer.observe(canvas)
+
+  return () => observer.disconnect()
+}
+
+/**
+ * Take a screenshot and return the encoded image as base64.
+ * @param {MpvScreenshotOptions} [options] - What to capture and the image format
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<string>} The PNG or JPEG image, base64-encoded.
+ * @throws {Error} Throws error when nothing is playing
+ *
+ * @example
+ * ```typescript
+ * import { screenshot } from 'tauri-plugin-libmpv-api';
+ *
+ * const image = await screenshot({ mode: 'video', format: 'jpeg' });
+ * img.src = `data:image/jpeg;base64,${image}`;
+ * ```
+ */
+export async function screenshot(options?: MpvScreenshotOptions, windowLabel?: string): Promise<string> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<string>('plugin:libmpv|screenshot', {
+    options: options ?? {},
+    windowLabel,
+  })
+}
+
+/**
+ * Save a screenshot into `plugins.libmpv.screenshotDir` and send a `screenshot-saved` event.
+ * @param {MpvScreenshotOptions} [options] - What to capture, the image format and the file name template
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<string>} The path of the written image.
+ * @throws {Error} Throws error when nothing is playing
+ *
+ * @example
+ * ```typescript
+ * import { saveScreenshot } from 'tauri-plugin-libmpv-api';
+ *
+ * const path = await saveScreenshot({ template: '{name}-{unix}' });
+ * ```
+ */
+export async function saveScreenshot(options?: MpvScreenshotOptions, windowLabel?: string): Promise<string> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<string>('plugin:libmpv|save_screenshot', {
+    options: options ?? {},
+    windowLabel,
+  })
+}
+
+/**
+ * Render a thumbnail of `path` at `time`, using a hidden mpv instance.
+ * @param {string} path - File path or URL
+ * @param {number} time - Position in seconds
+ * @param {number} width - Image width in pixels. The height keeps the aspect ratio.
+ * @param {string} [windowLabel] - Window asking for the thumbnail, defaults to current window
+ * @returns {Promise<string>} The JPEG image, base64-encoded.
+ * @throws {Error} Throws error when the file can't be decoded, or a newer request for the same window and file cancelled this one
+ *
+ * @example
+ * ```typescript
+ * import { getThumbnail } from 'tauri-plugin-libmpv-api';
+ *
+ * const image = await getThumbnail('/path/to/video.mp4', 42, 160);
+ * preview.src = `data:image/jpeg;base64,${image}`;
+ * ```
+ */
+export async function getThumbnail(path: string, time: number, width: number, windowLabel?: string): Promise<string> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<string>('plugin:libmpv|get_thumbnail', {
+    path,
+    time,
+    width,
+    windowLabel,
+  })
+}
+
+/**
+ * Generate the storyboard of a local file, or return it from the cache.
+ *
+ * Progress is sent as `storyboard-progress` events to the window.
+ * @param {string} path - File path
+ * @param {MpvStoryboardOptions} [options] - Interval, sheet layout and thumbnail width
+ * @param {string} [windowLabel] - Window receiving the progress events, defaults to current window
+ * @returns {Promise<MpvStoryboard>} The sprite sheets and the WebVTT file.
+ * @throws {Error} Throws error when the file can't be decoded or the job was cancelled
+ *
+ * @example
+ * ```typescript
+ * import { generateStoryboard } from 'tauri-plugin-libmpv-api';
+ * import { convertFileSrc } from '@tauri-apps/api/core';
+ *
+ * const storyboard = await generateStoryboard('/path/to/video.mp4', { interval: 5 });
+ * track.src = convertFileSrc(storyboard.vtt);
+ * ```
+ */
+export async function generateStoryboard(
+  path: string,
+  options?: MpvStoryboardOptions,
+  windowLabel?: string,
+): Promise<MpvStoryboard> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvStoryboard>('plugin:libmpv|generate_storyboard', {
+    path,
+    options: options ?? {},
+    windowLabel,
+  })
+}
+
+/**
+ * Cancel generating the storyboard of a file.
+ * @param {string} path - The path passed to `generateStoryboard`
+ * @param {string} [windowLabel] - Window that started the job, defaults to current window
+ * @returns {Promise<boolean>} Whether a job was cancelled.
+ *
+ * @example
+ * ```typescript
+ * import { cancelStoryboard } from 'tauri-plugin-libmpv-api';
+ *
+ * await cancelStoryboard('/path/to/video.mp4');
+ * ```
+ */
+export async function cancelStoryboard(path: string, windowLabel?: string): Promise<boolean> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<boolean>('plugin:libmpv|cancel_storyboard', {
+    path,
+    windowLabel,
+  })
+}
+
+/**
+ * Read duration, tracks, chapters, tags and container of a file without playing it.
+ *
+ * The file is opened by a hidden mpv instance, so many files can be probed in parallel.
+ * @param {string} path - File path or URL
+ * @param {string} [windowLabel] - Window asking, defaults to current window
+ * @returns {Promise<MpvMediaInfo>} What mpv found out about the file.
+ * @throws {Error} Throws error when the file can't be opened or opening takes longer than `plugins.libmpv.probe.timeout`
+ *
+ * @example
+ * ```typescript
+ * import { probe } from 'tauri-plugin-libmpv-api';
+ *
+ * const info = await probe('/path/to/video.mkv');
+ * const video = info.tracks.find((track) => track.type === 'video');
+ * console.log(info.duration, video?.width, video?.height);
+ * ```
+ */
+export async function probe(path: string, windowLabel?: string): Promise<MpvMediaInfo> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvMediaInfo>('plugin:libmpv|probe', {
+    path,
+    windowLabel,
+  })
+}
+
+/**
+ * List the video, audio and subtitle tracks of the playing file.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvTrack[]>} The tracks, in mpv's order.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { listTracks } from 'tauri-plugin-libmpv-api';
+ *
+ * const subtitles = (await listTracks()).filter((track) => track.type === 'sub');
+ * ```
+ */
+export async function listTracks(windowLabel?: string): Promise<MpvTrack[]> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvTrack[]>('plugin:libmpv|list_tracks', {
+    windowLabel,
+  })
+}
+
+/**
+ * Select a track, or disable a track type with `null`.
+ * @param {MpvTrackType} type - `'video'`, `'audio'` or `'sub'`
+ * @param {number | null} id - Track ID from `listTracks`, or `null` for none
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the track is selected.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { selectTrack } from 'tauri-plugin-libmpv-api';
+ *
+ * await selectTrack('sub', 2);
+ * await selectTrack('sub', null);
+ * ```
+ */
+export async function selectTrack(type: MpvTrackType, id: number | null, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|select_track', {
+    kind: type,
+    id,
+    windowLabel,
+  })
+}
+
+/**
+ * Load an external subtitle, audio or video file as a track.
+ * @param {MpvTrackType} type - `'video'`, `'audio'` or `'sub'`
+ * @param {string} path - File path or URL
+ * @param {MpvExternalTrackOptions} [options] - Whether to select it, and its title and language
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the file is loaded.
+ * @throws {Error} Throws error when the file can't be loaded
+ *
+ * @example
+ * ```typescript
+ * import { addExternalTrack } from 'tauri-plugin-libmpv-api';
+ *
+ * await addExternalTrack('sub', '/path/to/video.de.srt', { lang: 'de' });
+ * ```
+ */
+export async function addExternalTrack(
+  type: MpvTrackType,
+  path: string,
+  options?: MpvExternalTrackOptions,
+  windowLabel?: string,
+): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|add_external_track', {
+    kind: type,
+    path,
+    options: options ?? {},
+    windowLabel,
+  })
+}
+
+/**
+ * Remove an external track.
+ * @param {MpvTrackType} type - `'video'`, `'audio'` or `'sub'`
+ * @param {number} id - Track ID from `listTracks`
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the track is removed.
+ * @throws {Error} Throws error when the track does not exist or isn't external
+ *
+ * @example
+ * ```typescript
+ * import { removeTrack } from 'tauri-plugin-libmpv-api';
+ *
+ * await removeTrack('sub', 3);
+ * ```
+ */
+export async function removeTrack(type: MpvTrackType, id: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|remove_track', {
+    kind: type,
+    id,
+    windowLabel,
+  })
+}
+
+/**
+ * List the editions of the playing file, like the cuts of a Matroska file.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvEdition[]>} The editions, empty for files without any.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { listEditions } from 'tauri-plugin-libmpv-api';
+ *
+ * const editions = await listEditions();
+ * ```
+ */
+export async function listEditions(windowLabel?: string): Promise<MpvEdition[]> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvEdition[]>('plugin:libmpv|list_editions', {
+    windowLabel,
+  })
+}
+
+/**
+ * Switch to another edition. mpv reloads the file.
+ * @param {number} id - Edition ID from `listEditions`
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the edition is set.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { selectEdition } from 'tauri-plugin-libmpv-api';
+ *
+ * await selectEdition(1);
+ * ```
+ */
+export async function selectEdition(id: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|select_edition', {
+    id,
+    windowLabel,
+  })
+}
+
+/**
+ * List the chapters of the playing file, each ending where the next starts.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvChapter[]>} The chapters, empty for files without any.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { listChapters } from 'tauri-plugin-libmpv-api';
+ *
+ * const chapters = await listChapters();
+ * ```
+ */
+export async function listChapters(windowLabel?: string): Promise<MpvChapter[]> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvChapter[]>('plugin:libmpv|list_chapters', {
+    windowLabel,
+  })
+}
+
+/**
+ * Seek to the start of the next chapter.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the seek is requested.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { nextChapter } from 'tauri-plugin-libmpv-api';
+ *
+ * await nextChapter();
+ * ```
+ */
+export async function nextChapter(windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|next_chapter', {
+    windowLabel,
+  })
+}
+
+/**
+ * Seek to the start of the previous chapter.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the seek is requested.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { prevChapter } from 'tauri-plugin-libmpv-api';
+ *
+ * await prevChapter();
+ * ```
+ */
+export async function prevChapter(windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|prev_chapter', {
+    windowLabel,
+  })
+}
+
+/**
+ * Seek to the start of a chapter.
+ * @param {number} index - Chapter index from `listChapters`
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the seek is requested.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { seekChapter } from 'tauri-plugin-libmpv-api';
+ *
+ * await seekChapter(2);
+ * ```
+ */
+export async function seekChapter(index: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|seek_chapter', {
+    index,
+    windowLabel,
+  })
+}
+
+/**
+ * List the entries of the playlist.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvPlaylistEntry[]>} The entries, in playlist order.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { getPlaylist } from 'tauri-plugin-libmpv-api';
+ *
+ * const entries = await getPlaylist();
+ * ```
+ */
+export async function getPlaylist(windowLabel?: string): Promise<MpvPlaylistEntry[]> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvPlaylistEntry[]>('plugin:libmpv|get_playlist', {
+    windowLabel,
+  })
+}
+
+/**
+ * Add a file at the end of the playlist, without starting playback.
+ * @param {string} path - File path or URL
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the playlist is updated.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistAppend } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistAppend('/path/to/video.mp4');
+ * ```
+ */
+export async function playlistAppend(path: string, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_append', {
+    path,
+    windowLabel,
+  })
+}
+
+/**
+ * Add a file to the playlist at an index.
+ * @param {string} path - File path or URL
+ * @param {number} index - Index the new entry ends up at
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the playlist is updated.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistInsertAt } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistInsertAt('/path/to/video.mp4', 0);
+ * ```
+ */
+export async function playlistInsertAt(path: string, index: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_insert_at', {
+    path,
+    index,
+    windowLabel,
+  })
+}
+
+/**
+ * Add a file to the playlist right after the current entry.
+ * @param {string} path - File path or URL
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the playlist is updated.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistInsertNext } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistInsertNext('/path/to/video.mp4');
+ * ```
+ */
+export async function playlistInsertNext(path: string, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_insert_next', {
+    path,
+    windowLabel,
+  })
+}
+
+/**
+ * Move a playlist entry.
+ * @param {number} from - Index of the entry to move
+ * @param {number} to - Index the entry ends up at
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the playlist is updated.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistMove } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistMove(3, 0);
+ * ```
+ */
+export async function playlistMove(from: number, to: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_move', {
+    from,
+    to,
+    windowLabel,
+  })
+}
+
+/**
+ * Remove a playlist entry, stopping playback if it is playing.
+ * @param {number} index - Index of the entry to remove
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the playlist is updated.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistRemove } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistRemove(2);
+ * ```
+ */
+export async function playlistRemove(index: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_remove', {
+    index,
+    windowLabel,
+  })
+}
+
+/**
+ * Remove every playlist entry except the current one, like mpv's `playlist-clear`.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the playlist is updated.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistClear } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistClear();
+ * ```
+ */
+export async function playlistClear(windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_clear', {
+    windowLabel,
+  })
+}
+
+/**
+ * Start playing the playlist entry at an index.
+ * @param {number} index - Index of the entry to play
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when playback is requested.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistPlayIndex } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistPlayIndex(0);
+ * ```
+ */
+export async function playlistPlayIndex(index: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_play_index', {
+    index,
+    windowLabel,
+  })
+}
+
+/**
+ * Start playing the playlist entry with an ID, wherever it was moved.
+ * @param {number} id - Entry ID from `getPlaylist`
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when playback is requested.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { playlistPlayEntry } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistPlayEntry(42);
+ * ```
+ */
+export async function playlistPlayEntry(id: number, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_play_entry', {
+    id,
+    windowLabel,
+  })
+}
+
+/**
+ * Save the playlist to an M3U/M3U8, PLS or XSPF file, chosen by the file extension.
+ * @param {string} path - Path of the playlist file to write
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the file is written.
+ * @throws {Error} Throws error when the mpv instance does not exist or the extension is unknown
+ *
+ * @example
+ * ```typescript
+ * import { playlistExport } from 'tauri-plugin-libmpv-api';
+ *
+ * await playlistExport('/path/to/queue.m3u8');
+ * ```
+ */
+export async function playlistExport(path: string, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|playlist_export', {
+    path,
+    windowLabel,
+  })
+}
+
+/**
+ * Add the entries of an M3U/M3U8, PLS or XSPF file to the playlist.
+ * Relative paths in the file are resolved against the file.
+ * @param {string} path - Path of the playlist file to read
+ * @param {boolean} [replace=false] - Replace the playlist and play the first entry instead of appending
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<number>} The number of added entries.
+ * @throws {Error} Throws error when the mpv instance does not exist or the file can't be read or has no entries
+ *
+ * @example
+ * ```typescript
+ * import { playlistImport } from 'tauri-plugin-libmpv-api';
+ *
+ * const count = await playlistImport('/path/to/queue.xspf', true);
+ * ```
+ */
+export async function playlistImport(
+  path: string,
+  replace: boolean = false,
+  windowLabel?: string,
+): Promise<number> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<number>('plugin:libmpv|playlist_import', {
+    path,
+    replace,
+    windowLabel,
+  })
+}
+
+/**
+ * Get the play order of the queue engine, which picks the entry to play when one ends.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvQueueState>} The repeat mode, shuffle state, play order and history.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { getQueue } from 'tauri-plugin-libmpv-api';
+ *
+ * const { order, history } = await getQueue();
+ * ```
+ */
+export async function getQueue(windowLabel?: string): Promise<MpvQueueState> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvQueueState>('plugin:libmpv|get_queue', {
+    windowLabel,
+  })
+}
+
+/**
+ * Set what happens when an entry of the queue ends.
+ * @param {MpvRepeatMode} repeat - `'off'` stops after the last entry, `'one'` plays the entry again and `'all'` starts over
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the mode is set.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { setRepeat } from 'tauri-plugin-libmpv-api';
+ *
+ * await setRepeat('all');
+ * ```
+ */
+export async function setRepeat(repeat: MpvRepeatMode, windowLabel?: string): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|set_repeat', {
+    repeat,
+    windowLabel,
+  })
+}
+
+/**
+ * Turn shuffling on or off, without reordering the playlist.
+ * The current entry comes first in the shuffled order.
+ * @param {boolean} enabled - Whether to shuffle
+ * @param {number} [seed] - The same seed gives the same order for the same entries, random if omitted
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<void>} A promise that resolves when the order is set.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { setShuffle } from 'tauri-plugin-libmpv-api';
+ *
+ * await setShuffle(true, 42);
+ * ```
+ */
+export async function setShuffle(
+  enabled: boolean,
+  seed?: number,
+  windowLabel?: string,
+): Promise<void> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  await invoke('plugin:libmpv|set_shuffle', {
+    enabled,
+    seed,
+    windowLabel,
+  })
+}
+
+/**
+ * Play the next entry of the queue, going forward through entries gone back from first.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<boolean>} `false` if there is no next entry.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { queueNext } from 'tauri-plugin-libmpv-api';
+ *
+ * await queueNext();
+ * ```
+ */
+export async function queueNext(windowLabel?: string): Promise<boolean> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<boolean>('plugin:libmpv|queue_next', {
+    windowLabel,
+  })
+}
+
+/**
+ * Play the previous entry of the queue, going back through the entries played before.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<boolean>} `false` if there is no previous entry.
+ * @throws {Error} Throws error when the mpv instance does not exist
+ *
+ * @example
+ * ```typescript
+ * import { queuePrevious } from 'tauri-plugin-libmpv-api';
+ *
+ * await queuePrevious();
+ * ```
+ */
+export async function queuePrevious(windowLabel?: string): Promise<boolean> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<boolean>('plugin:libmpv|queue_previous', {
+    windowLabel,
+  })
+}
+
+/**
+ * Append the media files of a directory to the playlist, in natural order ("ep2" before "ep10").
+ * Subtitle files named like a video or audio file are attached to it as external tracks,
+ * hidden files and cover art are skipped.
+ * @param {string} path - Directory path
+ * @param {MpvLoadDirectoryOptions} [options] - Whether to include subdirectories and which kinds of files to add
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvMediaFile[]>} The added files.
+ * @throws {Error} Throws error when the mpv instance does not exist or the directory can't be read
+ *
+ * @example
+ * ```typescript
+ * import { loadDirectory } from 'tauri-plugin-libmpv-api';
+ *
+ * const files = await loadDirectory('/path/to/season-1', { recursive: true });
+ * ```
+ */
+export async function loadDirectory(
+  path: string,
+  options: MpvLoadDirectoryOptions = {},
+  windowLabel?: string,
+): Promise<MpvMediaFile[]> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvMediaFile[]>('plugin:libmpv|load_directory', {
+    path,
+    recursive: options.recursive ?? false,
+    filter: options.filter ?? [],
+    windowLabel,
+  })
+}
+
+/**
+ * Get where playback of a file stopped last, to offer resuming from there.
+ * Positions are only saved while `plugins.libmpv.resume.enabled` is set.
+ * @param {string} path - File path or URL
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvResumePoint | null>} The saved position, or null if there is none or the file was played to its end.
+ * @throws {Error} Throws error when the resume positions can't be read
+ *
+ * @example
+ * ```typescript
+ * import { command, getResumePoint } from 'tauri-plugin-libmpv-api';
+ *
+ * const point = await getResumePoint('/path/to/video.mkv');
+ * if (point) {
+ *   await command('loadfile', ['/path/to/video.mkv', 'replace', -1, `start=${point.position}`]);
+ * }
+ * ```
+ */
+export async function getResumePoint(
+  path: string,
+  windowLabel?: string,
+): Promise<MpvResumePoint | null> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvResumePoint | null>('plugin:libmpv|get_resume_point', {
+    path,
+    windowLabel,
+  })
+}
+
+/**
+ * List every saved resume position, most recently played first.
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<MpvResumePoint[]>} The saved positions.
+ * @throws {Error} Throws error when the resume positions can't be read
+ *
+ * @example
+ * ```typescript
+ * import { listResumePoints } from 'tauri-plugin-libmpv-api';
+ *
+ * const continueWatching = (await listResumePoints()).slice(0, 10);
+ * ```
+ */
+export async function listResumePoints(windowLabel?: string): Promise<MpvResumePoint[]> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<MpvResumePoint[]>('plugin:libmpv|list_resume_points', {
+    windowLabel,
+  })
+}
+
+/**
+ * Forget where playback of a file stopped.
+ * @param {string} path - File path or URL
+ * @param {string} [windowLabel] - Target window label, defaults to current window
+ * @returns {Promise<boolean>} Whether a position was saved.
+ * @throws {Error} Throws error when the resume positions can't be written
+ *
+ * @example
+ * ```typescript
+ * import { clearResumePoint } from 'tauri-plugin-libmpv-api';
+ *
+ * await clearResumePoint('/path/to/video.mkv');
+ * ```
+ */
+export async function clearResumePoint(path: string, windowLabel?: string): Promise<boolean> {
+
+  if (!windowLabel) {
+    windowLabel = getCurrentWindow().label
+  }
+
+  return await invoke<boolean>('plugin:libmpv|clear_resume_point', {
+    path,
+    windowLabel,
+  })
+}
@@ -26,9 +26,61 @@ export type MpvPropertyData<T extends MpvObservableProperty> =
   ? MpvFormatToType[TFormat]
   : never;
 
+export type MpvClosePolicy = 'quit' | 'quit-watch-later';
+
+export type MpvPreset =
+  | 'audio-only'
+  | 'low-latency'
+  | 'high-quality'
+  | 'thumbnailer'
+  | 'background-music';
+
 export interface MpvConfig {
   initialOptions?: Record<string, string | boolean | number>;
   observedProperties?: readonly MpvObservableProperty[];
+  /** How the mpv core is shut down on window close and app exit. Defaults to `'quit'`. */
+  closePolicy?: MpvClosePolicy;
+  /**
+   * A built-in preset, or one registered with the Rust `Builder`.
+   * Its options are applied first, so `initialOptions` override them.
+   */
+  preset?: MpvPreset | (string & {});
+  /**
+   * Directory to load `mpv.conf`, `input.conf` and profiles from.
+   * `'app-config'` and `'resource'` use the `mpv` folder inside the app config or resource directory.
+   */
+  configDir?: MpvConfigDir;
+  /**
+   * `'window'` embeds mpv in the native window behind a transparent webview.
+   * `'software'` renders frames on the CPU for `renderToCanvas`, which also works on Wayland
+   * and without a window. Defaults to `'window'`.
+   */
+  renderMode?: MpvRenderMode;
+}
+
+export type MpvRenderMode = 'window' | 'software';
+
+export type MpvConfigDir = 'app-config' | 'resource' | { path: string };
+
+export interface MpvProfileOption {
+  key: string;
+  value: string;
+}
+
+export interface MpvCapabilities {
+  abiVersion: string | null;
+  asyncCommands: boolean;
+  observeProperties: boolean;
+  hooks: boolean;
+  renderApi: boolean;
+}
+
+export interface MpvProfile {
+  name: string;
+  'profile-desc'?: string;
+  'profile-cond'?: string;
+  'profile-restore'?: string;
+  options: MpvProfileOption[];
 }
 
 export type MpvEventType =
@@ -49,7 +101,13 @@ export type MpvEventType =
   | 'playback-restart'
   | 'property-change'
   | 'queue-overflow'
-  | 'hook';
+  | 'hook'
+  | 'crashed'
+  | 'screenshot-saved'
+  | 'storyboard-progress'
+  | 'tracks-changed'
+  | 'chapter-changed'
+  | 'playlist-changed';
 
 interface MpvEventBase<E extends MpvEventType> {
   event: E;
@@ -128,6 +186,44 @@ export interface MpvHookEvent extends MpvEventBase<'hook'> {
   hook_id: number;
 }
 
+/** Sent by the `process` and `mpv` backends when the process of a player exits unexpectedly. */
+export interface MpvCrashedEvent extends MpvEventBase<'crashed'> {
+  exitCode: number | null;
+  /** The signal that ended the host on Unix. */
+  signal: number | null;
+  /** Whether the player is started again with the same options. */
+  restarting: boolean;
+}
+
+/** Sent after `saveScreenshot` has written an image. */
+export interface MpvScreenshotSavedEvent extends MpvEventBase<'screenshot-saved'> {
+  path: string;
+}
+
+/** Sent while `generateStoryboard` renders thumbnails. */
+export interface MpvStoryboardProgressEvent extends MpvEventBase<'storyboard-progress'> {
+  /** The path passed to `generateStoryboard`. */
+  path: string;
+  done: number;
+  total: number;
+}
+
+/** Sent when the track list of a player changes. */
+export interface MpvTracksChangedEvent extends MpvEventBase<'tracks-changed'> {
+  tracks: MpvTrack[];
+}
+
+export interface MpvChapterChangedEvent extends MpvEventBase<'chapter-changed'> {
+  /** `null` before the first chapter and when no file is loaded. */
+  index: number | null;
+  title: string | null;
+  chapter: MpvChapter | null;
+}
+
+export interface MpvPlaylistChangedEvent extends MpvEventBase<'playlist-changed'> {
+  entries: MpvPlaylistEntry[];
+}
+
 export type MpvEvent =
   | MpvShutdownEvent
   | MpvLogMessageEvent
@@ -146,7 +242,174 @@ export type MpvEvent =
   | MpvPlaybackRestartEvent
   | MpvEventFromProperties<MpvObservableProperty>
   | MpvQueueOverflowEvent
-  | MpvHookEvent;
+  | MpvHookEvent
+  | MpvCrashedEvent
+  | MpvScreenshotSavedEvent
+  | MpvStoryboardProgressEvent
+  | MpvTracksChangedEvent
+  | MpvChapterChangedEvent
+  | MpvPlaylistChangedEvent;
+
+export interface MpvScreenshotOptions {
+  /**
+   * `'subtitles'` is the video frame with subtitles, `'video'` the frame only and
+   * `'window'` the scaled window content including the OSD. Defaults to `'subtitles'`.
+   */
+  mode?: 'subtitles' | 'video' | 'window';
+  /** Defaults to `'png'`. */
+  format?: 'png' | 'jpeg';
+  /**
+   * File name template for `saveScreenshot`, without extension. `{name}`, `{pos}`, `{window}`
+   * and `{unix}` are replaced with the media file name, the playback position, the window label
+   * and the current Unix time. Defaults to `plugins.libmpv.screenshotTemplate` or `'{name}-{pos}'`.
+   */
+  template?: string;
+}
+
+export interface MpvStoryboardOptions {
+  /** Seconds between two thumbnails. Defaults to `10`. */
+  interval?: number;
+  /** Thumbnails per row of a sprite sheet. Defaults to `10`. */
+  columns?: number;
+  /** Rows per sprite sheet. Long videos get several sheets. Defaults to `10`. */
+  rows?: number;
+  /** Width of one thumbnail in pixels. The height keeps the aspect ratio. Defaults to `160`. */
+  width?: number;
+}
+
+export interface MpvStoryboard {
+  /** JPEG sprite sheets, in order. */
+  sheets: string[];
+  /** WebVTT file whose cues point to `sheet-N.jpg#xywh=x,y,w,h`, relative to it. */
+  vtt: string;
+  tileWidth: number;
+  tileHeight: number;
+  /** Number of thumbnails over all sheets. */
+  count: number;
+}
+
+export type MpvTrackType = 'video' | 'audio' | 'sub';
+
+/** An entry of mpv's `track-list`. */
+export interface MpvTrack {
+  /** The ID selected through `vid`, `aid` or `sid`. */
+  id: number;
+  type: MpvTrackType;
+  title: string | null;
+  lang: string | null;
+  codec: string | null;
+  default: boolean;
+  forced: boolean;
+  selected: boolean;
+  external: boolean;
+  /** The file an external track was loaded from. */
+  externalFilename: string | null;
+  /** Bits per second, as reported by the demuxer. */
+  bitrate: number | null;
+  channels: number | null;
+  samplerate: number | null;
+  width: number | null;
+  height: number | null;
+  fps: number | null;
+}
+
+export interface MpvExternalTrackOptions {
+  /** Select the track once it is added. Defaults to `true`. */
+  select?: boolean;
+  title?: string;
+  lang?: string;
+}
+
+/** An entry of mpv's `edition-list`. */
+export interface MpvEdition {
+  /** The ID selected through `edition`. */
+  id: number;
+  title: string | null;
+  default: boolean;
+  /** Whether it is `current-edition`. */
+  selected: boolean;
+}
+
+/** An entry of mpv's `chapter-list`. */
+export interface MpvChapter {
+  index: number;
+  title: string | null;
+  /** Start in seconds. */
+  start: number;
+  /** Start of the next chapter, or the duration for the last one if known. */
+  end: number | null;
+}
+
+export interface MpvPlaylistEntry {
+  /** Stable while the entry is in the playlist, unlike its index. */
+  id: number;
+  index: number;
+  filename: string;
+  title: string | null;
+  /** The entry playback is on or moving to. */
+  current: boolean;
+  /** The entry that is actually playing. */
+  playing: boolean;
+}
+
+/** What a media file contains, by its extension. Archives are opened by mpv as playlists of their contents. */
+export type MpvMediaKind = 'video' | 'audio' | 'image' | 'subtitle' | 'archive';
+
+/** A file `loadDirectory` added to the playlist. */
+export interface MpvMediaFile {
+  path: string;
+  kind: MpvMediaKind;
+  /** Subtitle files with the same name, attached as external tracks. */
+  subtitles: string[];
+}
+
+export interface MpvLoadDirectoryOptions {
+  /** Include subdirectories. Defaults to `false`. */
+  recursive?: boolean;
+  /** The kinds of files to add. Defaults to `['video', 'audio']`. */
+  filter?: MpvMediaKind[];
+}
+
+/** What happens when an entry of the queue ends: stop after the last one, play it again or start over. */
+export type MpvRepeatMode = 'off' | 'one' | 'all';
+
+/** The play order the queue engine follows on top of the playlist. */
+export interface MpvQueueState {
+  repeat: MpvRepeatMode;
+  shuffle: boolean;
+  /** Seed of the shuffled order, which is the same for the same seed and entries. */
+  seed: number | null;
+  /** Playlist entry IDs in play order. */
+  order: number[];
+  /** Playlist entry IDs played so far, oldest first, including the current one. */
+  history: number[];
+}
+
+/** Where playback of a file stopped, saved by the plugin to resume from later. */
+export interface MpvResumePoint {
+  /** The canonical path, or the URL. */
+  path: string;
+  /** Position in seconds. */
+  position: number;
+  /** Duration in seconds. */
+  duration: number;
+  /** When the file was last played, as a Unix time in seconds. */
+  lastPlayed: number;
+}
+
+/** What `probe` found out about a file without playing it. */
+export interface MpvMediaInfo {
+  /** Duration in seconds. Unknown for some streams. */
+  duration: number | null;
+  /** The demuxer's name for the container, like `mp4` or `matroska,webm`. */
+  fileFormat: string | null;
+  /** Size in bytes. */
+  fileSize: number | null;
+  /** Tags of the file, like `title` and `artist`. */
+  metadata: Record<string, string>;
+  tracks: MpvTrack[];
+  chapters: MpvChapter[];
+}
 
 export interface VideoMarginRatio {
   left?: number;
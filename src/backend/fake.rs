@@ -0,0 +1,345 @@
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::{BackendCapabilities, BackendInstance, EventSink, FrameSink, MpvBackend, VideoFrame};
+use crate::{Error, Result};
+
+/// A command received by a [`FakeBackend`] instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedCommand {
+    pub window_label: String,
+    pub name: String,
+    pub args: Vec<serde_json::Value>,
+}
+
+/// An in-memory backend for tests that need no libmpv and no window.
+///
+/// Clones share their state, so a clone can be registered with
+/// [`Builder::backend`](crate::Builder::backend) and the original used to
+/// script responses and inspect what the plugin did.
+///
+/// ```rust
+/// use tauri_plugin_libmpv::{FakeBackend, MpvBackend};
+///
+/// let fake = FakeBackend::new();
+/// fake.set_default_property("duration", 60.0);
+///
+/// let instance = fake
+///     .create("main", &Default::default(), &Default::default(), std::sync::Arc::new(|_| {}))
+///     .unwrap();
+/// instance.command("loadfile", &["video.mp4".into()]).unwrap();
+///
+/// assert_eq!(fake.commands()[0].name, "loadfile");
+/// assert_eq!(instance.get_property("duration", "double").unwrap(), 60.0);
+/// ```
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<State>>,
+}
+
+type CommandHandler =
+    Arc<dyn Fn(&RecordedCommand) -> std::result::Result<serde_json::Value, String> + Send + Sync>;
+
+#[derive(Default)]
+struct State {
+    default_properties: IndexMap<String, serde_json::Value>,
+    command_results: HashMap<String, std::result::Result<serde_json::Value, String>>,
+    command_handlers: HashMap<String, CommandHandler>,
+    create_error: Option<String>,
+    instances: IndexMap<String, Arc<FakeInstance>>,
+    commands: Vec<RecordedCommand>,
+    destroyed: Vec<String>,
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a property value every new instance starts with.
+    pub fn set_default_property(
+        &self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) {
+        self.lock()
+            .default_properties
+            .insert(name.into(), value.into());
+    }
+
+    /// Sets what `name` commands return. Commands without a result return `null`.
+    pub fn set_command_result(
+        &self,
+        name: impl Into<String>,
+        result: std::result::Result<serde_json::Value, String>,
+    ) {
+        self.lock().command_results.insert(name.into(), result);
+    }
+
+    /// Runs `handler` with every `name` command and returns its result.
+    ///
+    /// Takes precedence over [`Self::set_command_result`]. Use it for commands with side
+    /// effects, like writing the file of `screenshot-to-file` or sending events.
+    pub fn on_command<F>(&self, name: impl Into<String>, handler: F)
+    where
+        F: Fn(&RecordedCommand) -> std::result::Result<serde_json::Value, String>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.lock()
+            .command_handlers
+            .insert(name.into(), Arc::new(handler));
+    }
+
+    /// Makes the next `create` fail with `message` as its log.
+    pub fn fail_next_create(&self, message: impl Into<String>) {
+        self.lock().create_error = Some(message.into());
+    }
+
+    /// Delivers `event` to the instance for `window_label`. Returns `false` if there is none.
+    pub fn emit(&self, window_label: &str, event: serde_json::Value) -> bool {
+        match self.instance(window_label) {
+            Some(instance) => {
+                (instance.events)(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Changes a property as if mpv did, sending `property-change` if it is observed.
+    pub fn update_property(
+        &self,
+        window_label: &str,
+        name: &str,
+        value: impl Into<serde_json::Value>,
+    ) -> bool {
+        match self.instance(window_label) {
+            Some(instance) => {
+                instance.update(name, value.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Delivers `frame` as if the instance for `window_label` rendered it.
+    ///
+    /// Returns `false` if there is no such instance or it isn't rendering.
+    pub fn render_frame(&self, window_label: &str, frame: VideoFrame) -> bool {
+        let Some(instance) = self.instance(window_label) else {
+            return false;
+        };
+        let frames = lock(&instance.render)
+            .as_ref()
+            .map(|(_, frames)| frames.clone());
+        match frames {
+            Some(frames) => {
+                frames(frame);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The size the instance for `window_label` renders frames at, if it is rendering.
+    pub fn render_size(&self, window_label: &str) -> Option<(u32, u32)> {
+        let instance = self.instance(window_label)?;
+        let render = lock(&instance.render);
+        render.as_ref().map(|(size, _)| *size)
+    }
+
+    /// The current value of a property of the instance for `window_label`.
+    pub fn property(&self, window_label: &str, name: &str) -> Option<serde_json::Value> {
+        let instance = self.instance(window_label)?;
+        let properties = lock(&instance.properties);
+        properties.get(name).cloned()
+    }
+
+    /// The options the instance for `window_label` was created with.
+    pub fn initial_options(
+        &self,
+        window_label: &str,
+    ) -> Option<IndexMap<String, serde_json::Value>> {
+        self.instance(window_label)
+            .map(|instance| instance.initial_options.clone())
+    }
+
+    /// Every command received so far, in order.
+    pub fn commands(&self) -> Vec<RecordedCommand> {
+        self.lock().commands.clone()
+    }
+
+    /// Labels of the instances that are running.
+    pub fn instances(&self) -> Vec<String> {
+        self.lock().instances.keys().cloned().collect()
+    }
+
+    /// Labels of the destroyed instances, in order.
+    pub fn destroyed(&self) -> Vec<String> {
+        self.lock().destroyed.clone()
+    }
+
+    fn instance(&self, window_label: &str) -> Option<Arc<FakeInstance>> {
+        self.lock().instances.get(window_label).cloned()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl MpvBackend for FakeBackend {
+    fn create(
+        &self,
+        window_label: &str,
+        initial_options: &IndexMap<String, serde_json::Value>,
+        observed_properties: &IndexMap<String, String>,
+        events: EventSink,
+    ) -> Result<Arc<dyn BackendInstance>> {
+        let mut state = self.lock();
+
+        if let Some(message) = state.create_error.take() {
+            return Err(Error::CreateInstance {
+                window_label: window_label.to_string(),
+                invalid_options: Vec::new(),
+                log: vec![message],
+            });
+        }
+
+        let mut properties = state.default_properties.clone();
+        properties.extend(initial_options.clone());
+
+        let instance = Arc::new(FakeInstance {
+            backend: self.clone(),
+            window_label: window_label.to_string(),
+            initial_options: initial_options.clone(),
+            observed_properties: observed_properties.clone(),
+            properties: Mutex::new(properties),
+            events,
+            render: Mutex::new(None),
+        });
+        state
+            .instances
+            .insert(window_label.to_string(), instance.clone());
+
+        Ok(instance)
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            render_api: true,
+            ..Default::default()
+        }
+    }
+}
+
+struct FakeInstance {
+    backend: FakeBackend,
+    window_label: String,
+    initial_options: IndexMap<String, serde_json::Value>,
+    observed_properties: IndexMap<String, String>,
+    properties: Mutex<IndexMap<String, serde_json::Value>>,
+    events: EventSink,
+    render: Mutex<Option<((u32, u32), FrameSink)>>,
+}
+
+impl std::fmt::Debug for FakeInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeInstance")
+            .field("window_label", &self.window_label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FakeInstance {
+    fn update(&self, name: &str, value: serde_json::Value) {
+        lock(&self.properties).insert(name.to_string(), value.clone());
+
+        if let Some(index) = self.observed_properties.get_index_of(name) {
+            (self.events)(serde_json::json!({
+                "event": "property-change",
+                "name": name,
+                "data": value,
+                "id": index + 1,
+            }));
+        }
+    }
+}
+
+impl BackendInstance for FakeInstance {
+    fn command(&self, name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value> {
+        let command = RecordedCommand {
+            window_label: self.window_label.clone(),
+            name: name.to_string(),
+            args: args.to_vec(),
+        };
+        let (handler, result) = {
+            let mut state = self.backend.lock();
+            state.commands.push(command.clone());
+            (
+                state.command_handlers.get(name).cloned(),
+                state.command_results.get(name).cloned(),
+            )
+        };
+
+        // Handlers run without the lock, so they can use the backend.
+        match handler.map(|handler| handler(&command)).or(result) {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(message)) => Err(Error::Command {
+                window_label: self.window_label.clone(),
+                message,
+            }),
+            None => Ok(serde_json::Value::Null),
+        }
+    }
+
+    fn set_property(&self, name: &str, value: &serde_json::Value) -> Result<()> {
+        self.update(name, value.clone());
+        Ok(())
+    }
+
+    fn get_property(&self, name: &str, _format: &str) -> Result<serde_json::Value> {
+        lock(&self.properties)
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::GetProperty {
+                window_label: self.window_label.clone(),
+                message: "property not found".to_string(),
+            })
+    }
+
+    fn start_render(&self, width: u32, height: u32, frames: FrameSink) -> Result<()> {
+        *lock(&self.render) = Some(((width, height), frames));
+        Ok(())
+    }
+
+    fn resize_render(&self, width: u32, height: u32) -> Result<()> {
+        match lock(&self.render).as_mut() {
+            Some((size, _)) => {
+                *size = (width, height);
+                Ok(())
+            }
+            None => Err(Error::Command {
+                window_label: self.window_label.clone(),
+                message: "rendering has not been started".to_string(),
+            }),
+        }
+    }
+
+    fn destroy(&self) -> Result<()> {
+        let mut state = self.backend.lock();
+        state.instances.shift_remove(&self.window_label);
+        state.destroyed.push(self.window_label.clone());
+        Ok(())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
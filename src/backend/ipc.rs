@@ -0,0 +1,441 @@
+use indexmap::IndexMap;
+use log::{error, warn};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::process::{crashed_event, wait_for_exit};
+use super::socket::SocketClient;
+use super::{BackendCapabilities, BackendInstance, EventSink, MpvBackend};
+use crate::{validation, Error, Result};
+
+/// How long mpv has to create its IPC socket.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long mpv has to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many lines of mpv's error output are kept for error reports.
+const LOG_LINES: usize = 50;
+
+/// mpv's error code for failed commands, used in `command-reply` events.
+const MPV_ERROR_COMMAND: i64 = -12;
+
+static NEXT_SOCKET_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Drives the `mpv` executable over its JSON IPC protocol, one process per instance.
+///
+/// Initial options are passed as command-line options. Hooks and the render API
+/// are not available over IPC.
+pub struct IpcBackend {
+    program: PathBuf,
+    version: String,
+}
+
+impl IpcBackend {
+    /// Checks that `program` runs and is mpv.
+    pub fn new(program: impl Into<PathBuf>) -> Result<Self> {
+        let program = program.into();
+        let output = Command::new(&program)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|e| Error::Ipc(format!("failed to run '{}': {}", program.display(), e)))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let version = stdout
+            .lines()
+            .find(|line| line.starts_with("mpv "))
+            .ok_or_else(|| Error::Ipc(format!("'{}' is not mpv", program.display())))?
+            .to_string();
+
+        Ok(Self { program, version })
+    }
+
+    /// The first line of `mpv --version`.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+impl MpvBackend for IpcBackend {
+    fn create(
+        &self,
+        window_label: &str,
+        initial_options: &IndexMap<String, serde_json::Value>,
+        observed_properties: &IndexMap<String, String>,
+        events: EventSink,
+    ) -> Result<Arc<dyn BackendInstance>> {
+        let socket_dir = create_socket_dir()?;
+        let socket_path = socket_dir.join("mpv.sock");
+
+        // Match libmpv's defaults, which initial options may override.
+        let mut command = Command::new(&self.program);
+        command
+            .arg("--config=no")
+            .arg("--idle=yes")
+            .arg("--no-input-terminal")
+            .arg("--msg-level=all=error")
+            .arg(format!("--input-ipc-server={}", socket_path.display()));
+        for (name, value) in initial_options {
+            command.arg(format!("--{}={}", name, option_value(value)));
+        }
+
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                let _ = std::fs::remove_dir_all(&socket_dir);
+                Error::Ipc(format!(
+                    "failed to start '{}': {}",
+                    self.program.display(),
+                    e
+                ))
+            })?;
+
+        let log: Arc<Mutex<VecDeque<String>>> = Arc::default();
+        let log_reader = child.stderr.take().map(|stderr| {
+            let log = log.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    let mut log = lock(&log);
+                    if log.len() == LOG_LINES {
+                        log.pop_front();
+                    }
+                    log.push_back(line);
+                }
+            })
+        });
+
+        let stream = match connect(&socket_path, &mut child) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                if let Some(log_reader) = log_reader {
+                    let _ = log_reader.join();
+                }
+                let _ = std::fs::remove_dir_all(&socket_dir);
+
+                error!("mpv for window '{}' did not start: {}", window_label, e);
+                let mut log: Vec<String> = lock(&log).drain(..).collect();
+                if log.is_empty() {
+                    log.push(e.to_string());
+                }
+                return Err(Error::CreateInstance {
+                    window_label: window_label.to_string(),
+                    invalid_options: validation::find_invalid_options(
+                        self,
+                        window_label,
+                        initial_options,
+                    ),
+                    log,
+                });
+            }
+        };
+
+        let child = Arc::new(Mutex::new(child));
+        let destroyed = Arc::new(AtomicBool::new(false));
+        let on_close = {
+            let child = child.clone();
+            let destroyed = destroyed.clone();
+            let events = events.clone();
+            let window_label = window_label.to_string();
+            move || {
+                if destroyed.load(Ordering::SeqCst) {
+                    return;
+                }
+                let status = wait_for_exit(&mut lock(&child));
+                if status.is_some_and(|status| status.success()) {
+                    return;
+                }
+                warn!("mpv for window '{}' exited unexpectedly.", window_label);
+                events(crashed_event(status, false));
+            }
+        };
+
+        let client = SocketClient::new(
+            window_label,
+            stream.try_clone()?,
+            stream,
+            {
+                let events = events.clone();
+                move |event| events(event)
+            },
+            on_close,
+        )?;
+
+        let instance = IpcInstance {
+            client: Arc::new(client),
+            events,
+            child,
+            socket_dir,
+            window_label: window_label.to_string(),
+            destroyed,
+        };
+
+        // The wrapper numbers the initially observed properties the same way.
+        for (index, (name, format)) in observed_properties.iter().enumerate() {
+            instance.observe_property(index as u64 + 1, name, format)?;
+        }
+
+        Ok(Arc::new(instance))
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            abi_version: None,
+            async_commands: true,
+            observe_properties: true,
+            hooks: false,
+            render_api: false,
+        }
+    }
+}
+
+/// Creates a directory for one socket that only the current user can access.
+///
+/// The directory must not exist yet, so nobody else can have prepared it.
+fn create_socket_dir() -> Result<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(std::env::temp_dir);
+    let dir = base.join(format!(
+        "tauri-plugin-libmpv-{}-{}",
+        std::process::id(),
+        NEXT_SOCKET_ID.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .map_err(|e| Error::Ipc(format!("failed to create '{}': {}", dir.display(), e)))?;
+    Ok(dir)
+}
+
+/// Waits for mpv to create the socket at `path`.
+fn connect(path: &Path, child: &mut Child) -> Result<UnixStream> {
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        match UnixStream::connect(path) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                if let Some(status) = child.try_wait()? {
+                    return Err(Error::Ipc(format!("mpv exited ({})", status)));
+                }
+                if Instant::now() >= deadline {
+                    return Err(Error::Ipc(format!(
+                        "failed to connect to '{}': {}",
+                        path.display(),
+                        e
+                    )));
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+    }
+}
+
+/// Formats an initial option value for the command line.
+fn option_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Bool(true) => "yes".to_string(),
+        serde_json::Value::Bool(false) => "no".to_string(),
+        serde_json::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+struct IpcInstance {
+    client: Arc<SocketClient>,
+    events: EventSink,
+    child: Arc<Mutex<Child>>,
+    socket_dir: PathBuf,
+    window_label: String,
+    destroyed: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for IpcInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IpcInstance")
+            .field("socket_dir", &self.socket_dir)
+            .field("window_label", &self.window_label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IpcInstance {
+    /// Runs an IPC command, returning its data or mpv's error message.
+    fn run(
+        &self,
+        command: serde_json::Value,
+    ) -> Result<std::result::Result<serde_json::Value, String>> {
+        request(&self.client, command_message(command), REQUEST_TIMEOUT)
+    }
+}
+
+fn command_message(command: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    let mut message = serde_json::Map::new();
+    message.insert("command".to_string(), command);
+    message
+}
+
+fn request(
+    client: &SocketClient,
+    message: serde_json::Map<String, serde_json::Value>,
+    timeout: Duration,
+) -> Result<std::result::Result<serde_json::Value, String>> {
+    let mut response = client.request(message, timeout)?;
+
+    Ok(match response["error"].as_str() {
+        Some("success") => Ok(response["data"].take()),
+        Some(message) => Err(message.to_string()),
+        None => Err(format!("invalid IPC response: {}", response)),
+    })
+}
+
+impl BackendInstance for IpcInstance {
+    fn command(&self, name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value> {
+        let mut command = vec![serde_json::json!(name)];
+        command.extend_from_slice(args);
+
+        self.run(command.into())?.map_err(|message| Error::Command {
+            window_label: self.window_label.clone(),
+            message,
+        })
+    }
+
+    fn set_property(&self, name: &str, value: &serde_json::Value) -> Result<()> {
+        self.run(serde_json::json!(["set_property", name, value]))?
+            .map(|_| ())
+            .map_err(|message| Error::SetProperty {
+                window_label: self.window_label.clone(),
+                message,
+            })
+    }
+
+    fn get_property(&self, name: &str, format: &str) -> Result<serde_json::Value> {
+        let command = match format {
+            "string" => "get_property_string",
+            _ => "get_property",
+        };
+        self.run(serde_json::json!([command, name]))?
+            .map_err(|message| Error::GetProperty {
+                window_label: self.window_label.clone(),
+                message,
+            })
+    }
+
+    fn command_async(&self, reply_id: u64, name: &str, args: &[serde_json::Value]) -> Result<()> {
+        let mut command = vec![serde_json::json!(name)];
+        command.extend_from_slice(args);
+        let mut message = command_message(command.into());
+        message.insert("async".to_string(), true.into());
+        let client = self.client.clone();
+        let events = self.events.clone();
+        let window_label = self.window_label.clone();
+
+        // IPC answers when the command is done, so the reply is waited for on another thread.
+        std::thread::Builder::new()
+            .name(format!("mpv-command-{}", reply_id))
+            .spawn(move || {
+                let event = match request(&client, message, Duration::MAX) {
+                    Ok(Ok(result)) => serde_json::json!({
+                        "event": "command-reply",
+                        "id": reply_id,
+                        "error": 0,
+                        "result": result,
+                    }),
+                    Ok(Err(message)) => {
+                        warn!(
+                            "Async command for window '{}' failed: {}",
+                            window_label, message
+                        );
+                        serde_json::json!({
+                            "event": "command-reply",
+                            "id": reply_id,
+                            "error": MPV_ERROR_COMMAND,
+                            "result": null,
+                        })
+                    }
+                    Err(e) => {
+                        error!("Async command for window '{}' failed: {}", window_label, e);
+                        return;
+                    }
+                };
+                events(event);
+            })?;
+        Ok(())
+    }
+
+    fn observe_property(&self, reply_id: u64, name: &str, format: &str) -> Result<()> {
+        // Other formats arrive as JSON values, which already match them.
+        let command = match format {
+            "string" => "observe_property_string",
+            _ => "observe_property",
+        };
+        self.run(serde_json::json!([command, reply_id, name]))?
+            .map(|_| ())
+            .map_err(|message| Error::Command {
+                window_label: self.window_label.clone(),
+                message,
+            })
+    }
+
+    fn unobserve_property(&self, reply_id: u64) -> Result<()> {
+        self.run(serde_json::json!(["unobserve_property", reply_id]))?
+            .map(|_| ())
+            .map_err(|message| Error::Command {
+                window_label: self.window_label.clone(),
+                message,
+            })
+    }
+
+    fn destroy(&self) -> Result<()> {
+        if self.destroyed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // mpv may exit before it answers.
+        if let Err(e) = self
+            .client
+            .send(&command_message(serde_json::json!(["quit"])))
+        {
+            warn!("Failed to send 'quit' to mpv: {}", e);
+        }
+
+        wait_for_exit(&mut lock(&self.child));
+        let _ = std::fs::remove_dir_all(&self.socket_dir);
+        Ok(())
+    }
+}
+
+impl Drop for IpcInstance {
+    fn drop(&mut self) {
+        if !self.destroyed.swap(true, Ordering::SeqCst) {
+            let mut child = lock(&self.child);
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = std::fs::remove_dir_all(&self.socket_dir);
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
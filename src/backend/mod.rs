@@ -0,0 +1,180 @@
+//! The interface between [`Mpv`](crate::MpvExt::mpv) and the library that runs mpv.
+
+#[cfg(feature = "test-util")]
+mod fake;
+#[cfg(unix)]
+mod ipc;
+mod process;
+mod socket;
+mod wrapper;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{Error, Result};
+
+#[cfg(feature = "test-util")]
+pub use fake::{FakeBackend, RecordedCommand};
+#[cfg(unix)]
+pub use ipc::IpcBackend;
+pub use process::ProcessBackend;
+pub use wrapper::{WrapperBackend, WRAPPER_ABI_MAJOR};
+
+/// Receives every mpv event as JSON, in the shape of the frontend `MpvEvent` types.
+pub type EventSink = Arc<dyn Fn(serde_json::Value) + Send + Sync>;
+
+/// Receives the frames of an instance rendered with [`BackendInstance::start_render`].
+pub type FrameSink = Arc<dyn Fn(VideoFrame) + Send + Sync>;
+
+/// A video frame rendered in software.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoFrame {
+    pub width: u32,
+    pub height: u32,
+    /// `width * height` RGBA pixels, row by row without padding.
+    pub pixels: Vec<u8>,
+}
+
+/// Creates mpv instances.
+///
+/// The plugin picks [`WrapperBackend`] or `LibmpvBackend` from `plugins.libmpv.backend`
+/// unless one is registered with [`Builder::backend`](crate::Builder::backend).
+pub trait MpvBackend: Send + Sync {
+    /// Creates an instance for `window_label` and starts delivering its events to `events`.
+    ///
+    /// Failures to create the instance are reported as [`Error::CreateInstance`](crate::Error::CreateInstance).
+    fn create(
+        &self,
+        window_label: &str,
+        initial_options: &IndexMap<String, serde_json::Value>,
+        observed_properties: &IndexMap<String, String>,
+        events: EventSink,
+    ) -> Result<Arc<dyn BackendInstance>>;
+
+    /// What the instances of this backend support beyond the required calls.
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::default()
+    }
+}
+
+/// Optional features a backend supports, detected when its library is loaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendCapabilities {
+    /// Interface version reported by the library, as `major.minor`.
+    pub abi_version: Option<String>,
+    pub async_commands: bool,
+    pub observe_properties: bool,
+    pub hooks: bool,
+    pub render_api: bool,
+}
+
+/// An optional backend feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    AsyncCommands,
+    ObserveProperties,
+    Hooks,
+    RenderApi,
+}
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::AsyncCommands => "async commands",
+            Capability::ObserveProperties => "observing properties",
+            Capability::Hooks => "hooks",
+            Capability::RenderApi => "the render API",
+        }
+    }
+}
+
+impl BackendCapabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::AsyncCommands => self.async_commands,
+            Capability::ObserveProperties => self.observe_properties,
+            Capability::Hooks => self.hooks,
+            Capability::RenderApi => self.render_api,
+        }
+    }
+
+    /// Returns [`Error::UnsupportedFeature`] unless `capability` is supported.
+    pub fn require(&self, capability: Capability) -> Result<()> {
+        if self.supports(capability) {
+            Ok(())
+        } else {
+            Err(unsupported(capability, self.abi_version.as_deref()))
+        }
+    }
+}
+
+pub(crate) fn unsupported(capability: Capability, abi_version: Option<&str>) -> Error {
+    Error::UnsupportedFeature(format!(
+        "{} not supported by the loaded backend (interface version {})",
+        capability.name(),
+        abi_version.unwrap_or("unknown")
+    ))
+}
+
+/// A running mpv instance created by an [`MpvBackend`].
+///
+/// Errors reported by mpv are returned as [`Error::Command`](crate::Error::Command),
+/// [`Error::SetProperty`](crate::Error::SetProperty) and
+/// [`Error::GetProperty`](crate::Error::GetProperty).
+pub trait BackendInstance: Send + Sync + std::fmt::Debug {
+    fn command(&self, name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value>;
+
+    fn set_property(&self, name: &str, value: &serde_json::Value) -> Result<()>;
+
+    /// Reads a property in `format`: `string`, `flag`, `int64`, `double` or `node`.
+    fn get_property(&self, name: &str, format: &str) -> Result<serde_json::Value>;
+
+    /// Starts a command without waiting for it. mpv sends `command-reply` with `id` = `reply_id`.
+    fn command_async(
+        &self,
+        _reply_id: u64,
+        _name: &str,
+        _args: &[serde_json::Value],
+    ) -> Result<()> {
+        Err(unsupported(Capability::AsyncCommands, None))
+    }
+
+    /// Sends `property-change` events with `id` = `reply_id` whenever `name` changes.
+    fn observe_property(&self, _reply_id: u64, _name: &str, _format: &str) -> Result<()> {
+        Err(unsupported(Capability::ObserveProperties, None))
+    }
+
+    /// Stops every observation registered with `reply_id`.
+    fn unobserve_property(&self, _reply_id: u64) -> Result<()> {
+        Err(unsupported(Capability::ObserveProperties, None))
+    }
+
+    /// Registers a hook. mpv sends `hook` events that must be answered with [`Self::hook_continue`].
+    fn hook_add(&self, _reply_id: u64, _name: &str, _priority: i32) -> Result<()> {
+        Err(unsupported(Capability::Hooks, None))
+    }
+
+    fn hook_continue(&self, _hook_id: u64) -> Result<()> {
+        Err(unsupported(Capability::Hooks, None))
+    }
+
+    /// Renders every new video frame at `width` x `height` with mpv's `sw` render API.
+    ///
+    /// The instance must have been created with `vo=libmpv`. Calling it again replaces
+    /// the size and `frames`.
+    fn start_render(&self, _width: u32, _height: u32, _frames: FrameSink) -> Result<()> {
+        Err(unsupported(Capability::RenderApi, None))
+    }
+
+    /// Changes the size frames are rendered at, rendering the current frame again.
+    fn resize_render(&self, _width: u32, _height: u32) -> Result<()> {
+        Err(unsupported(Capability::RenderApi, None))
+    }
+
+    /// Shuts the instance down. No events are delivered afterwards.
+    ///
+    /// Called once, after the instance has been removed from the plugin.
+    fn destroy(&self) -> Result<()>;
+}
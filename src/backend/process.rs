@@ -0,0 +1,520 @@
+use indexmap::IndexMap;
+use log::{error, info, warn};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use super::socket::SocketClient;
+use super::{BackendCapabilities, BackendInstance, EventSink, MpvBackend};
+use crate::host::{HostError, HostRequest, HOST_ENV, HOST_TOKEN_ENV, HOST_WRAPPER_ENV};
+use crate::{Error, Result};
+
+/// How long a new host process has to connect back.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a host process has to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a host process has to exit before it is killed.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often an instance is restarted after its host process crashed.
+const MAX_RESTARTS: u32 = 3;
+
+/// Runs each instance in its own host process, so a crash in mpv doesn't take the app down.
+///
+/// When a host process exits unexpectedly, the instance sends a `crashed` event and is
+/// started again with the same options, up to three times.
+pub struct ProcessBackend {
+    launcher: Arc<Launcher>,
+}
+
+/// Starts host processes.
+struct Launcher {
+    program: PathBuf,
+    args: Vec<String>,
+    wrapper_path: PathBuf,
+    /// Reported by the last host that started.
+    capabilities: Mutex<BackendCapabilities>,
+}
+
+impl ProcessBackend {
+    /// Runs hosts as `program args...`, loading the wrapper at `wrapper_path`.
+    ///
+    /// `program` must call [`host::run_if_requested`](crate::host::run_if_requested) on startup.
+    ///
+    /// The wrapper is never loaded into this process. A first host is started here to check
+    /// it and report its capabilities, and exits again.
+    ///
+    /// # Safety
+    ///
+    /// `wrapper_path` must point to a `libmpv-wrapper` build.
+    pub unsafe fn load(
+        program: impl Into<PathBuf>,
+        args: Vec<String>,
+        wrapper_path: impl Into<PathBuf>,
+    ) -> Result<Self> {
+        let launcher = Arc::new(Launcher {
+            program: program.into(),
+            args,
+            wrapper_path: wrapper_path.into(),
+            capabilities: Mutex::default(),
+        });
+
+        let mut host = launcher.spawn("check", Arc::new(|_| {}), || {})?;
+        // A host without an instance just exits on `destroy`.
+        if let serde_json::Value::Object(message) = serde_json::to_value(HostRequest::Destroy)? {
+            let _ = host.client.request(message, REQUEST_TIMEOUT);
+        }
+        wait_for_exit(&mut host.child);
+
+        Ok(Self { launcher })
+    }
+}
+
+impl MpvBackend for ProcessBackend {
+    fn create(
+        &self,
+        window_label: &str,
+        initial_options: &IndexMap<String, serde_json::Value>,
+        observed_properties: &IndexMap<String, String>,
+        events: EventSink,
+    ) -> Result<Arc<dyn BackendInstance>> {
+        let instance = Arc::new(ProcessInstance {
+            launcher: self.launcher.clone(),
+            window_label: window_label.to_string(),
+            initial_options: initial_options.clone(),
+            observed_properties: observed_properties.clone(),
+            events,
+            host: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            observations: Mutex::new(IndexMap::new()),
+            hooks: Mutex::new(IndexMap::new()),
+            restarts: AtomicU32::new(0),
+            destroyed: AtomicBool::new(false),
+        });
+        instance.start()?;
+        Ok(instance)
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            // Frames can't be rendered across processes.
+            render_api: false,
+            ..lock(&self.launcher.capabilities).clone()
+        }
+    }
+}
+
+/// A running host process and the connection to it.
+struct Host {
+    client: Arc<SocketClient>,
+    child: Child,
+}
+
+impl Launcher {
+    /// Starts a host process and waits for it to connect and report the loaded wrapper.
+    fn spawn(
+        &self,
+        window_label: &str,
+        events: EventSink,
+        on_close: impl FnOnce() + Send + 'static,
+    ) -> Result<Host> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let token = new_token();
+
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .env(HOST_ENV, listener.local_addr()?.to_string())
+            .env(HOST_TOKEN_ENV, &token)
+            .env(HOST_WRAPPER_ENV, &self.wrapper_path)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                Error::Ipc(format!(
+                    "failed to start mpv host '{}': {}",
+                    self.program.display(),
+                    e
+                ))
+            })?;
+
+        let (stream, hello) = match accept(&listener, &mut child, &token) {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+        };
+        if let Some(error) = hello["error"].as_str() {
+            wait_for_exit(&mut child);
+            return Err(Error::FFI(error.to_string()));
+        }
+        match serde_json::from_value(hello["capabilities"].clone()) {
+            Ok(capabilities) => *lock(&self.capabilities) = capabilities,
+            Err(e) => warn!("mpv host reported invalid capabilities: {}", e),
+        }
+
+        let client = SocketClient::new(
+            window_label,
+            stream.try_clone()?,
+            stream,
+            move |event| events(event),
+            on_close,
+        )?;
+        Ok(Host {
+            client: Arc::new(client),
+            child,
+        })
+    }
+}
+
+/// Accepts the connection of `child`, identified by the token in the hello it sends first.
+fn accept(
+    listener: &TcpListener,
+    child: &mut Child,
+    token: &str,
+) -> Result<(TcpStream, serde_json::Value)> {
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+                let hello = serde_json::from_str::<serde_json::Value>(&read_line(&stream)?)
+                    .unwrap_or_default();
+                if hello["token"] == token {
+                    stream.set_read_timeout(None)?;
+                    stream.set_nodelay(true)?;
+                    return Ok((stream, hello));
+                }
+                warn!("Ignoring a connection that isn't from the mpv host.");
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if let Some(status) = child.try_wait()? {
+                    return Err(Error::Ipc(format!(
+                        "mpv host exited before connecting ({})",
+                        status
+                    )));
+                }
+                if Instant::now() >= deadline {
+                    return Err(Error::Ipc(format!(
+                        "mpv host did not connect within {:?}",
+                        CONNECT_TIMEOUT
+                    )));
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Reads one line without buffering past it.
+fn read_line(mut stream: &TcpStream) -> Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    while stream.read(&mut byte)? == 1 && byte[0] != b'\n' {
+        line.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+fn new_token() -> String {
+    let state = RandomState::new();
+    let mut token = String::new();
+    for part in 0..2u8 {
+        let mut hasher = state.build_hasher();
+        hasher.write_u8(part);
+        hasher.write_u32(std::process::id());
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        token.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    token
+}
+
+/// Waits up to [`EXIT_TIMEOUT`] for `child` to exit, then kills it.
+pub(super) fn wait_for_exit(child: &mut Child) -> Option<ExitStatus> {
+    let deadline = Instant::now() + EXIT_TIMEOUT;
+    while Instant::now() < deadline {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+            Err(_) => break,
+        }
+    }
+    let _ = child.kill();
+    child.wait().ok()
+}
+
+/// The `crashed` event for a process that exited with `status`.
+pub(super) fn crashed_event(status: Option<ExitStatus>, restarting: bool) -> serde_json::Value {
+    #[cfg(unix)]
+    let signal = status.and_then(|status| std::os::unix::process::ExitStatusExt::signal(&status));
+    #[cfg(not(unix))]
+    let signal: Option<i32> = None;
+
+    serde_json::json!({
+        "event": "crashed",
+        "exitCode": status.and_then(|status| status.code()),
+        "signal": signal,
+        "restarting": restarting,
+    })
+}
+
+struct ProcessInstance {
+    launcher: Arc<Launcher>,
+    window_label: String,
+    initial_options: IndexMap<String, serde_json::Value>,
+    observed_properties: IndexMap<String, String>,
+    events: EventSink,
+    host: Mutex<Option<Host>>,
+    /// Increases with every started host, so only the current one's exit counts as a crash.
+    generation: AtomicU64,
+    /// Observations and hooks added at runtime, restored after a restart.
+    observations: Mutex<IndexMap<u64, (String, String)>>,
+    hooks: Mutex<IndexMap<u64, (String, i32)>>,
+    restarts: AtomicU32,
+    destroyed: AtomicBool,
+}
+
+impl std::fmt::Debug for ProcessInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessInstance")
+            .field("window_label", &self.window_label)
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProcessInstance {
+    /// Starts a host process and creates the instance in it.
+    fn start(self: &Arc<Self>) -> Result<()> {
+        let generation = self.generation.load(Ordering::SeqCst) + 1;
+        let on_close = {
+            let instance: Weak<Self> = Arc::downgrade(self);
+            move || {
+                if let Some(instance) = instance.upgrade() {
+                    instance.host_closed(generation);
+                }
+            }
+        };
+
+        let mut host = self
+            .launcher
+            .spawn(&self.window_label, self.events.clone(), on_close)?;
+        let created = self.request_with(
+            &host.client,
+            HostRequest::Create {
+                window_label: self.window_label.clone(),
+                initial_options: self.initial_options.clone(),
+                observed_properties: self.observed_properties.clone(),
+            },
+        );
+        if let Err(e) = created {
+            let _ = host.child.kill();
+            let _ = host.child.wait();
+            return Err(e);
+        }
+
+        let client = host.client.clone();
+        *lock(&self.host) = Some(host);
+        self.generation.store(generation, Ordering::SeqCst);
+
+        // The host may have crashed before it became the current one.
+        if client.is_closed() {
+            self.host_closed(generation);
+        }
+        Ok(())
+    }
+
+    /// Reports a crash and restarts the host, unless the instance is being destroyed.
+    fn host_closed(self: &Arc<Self>, generation: u64) {
+        if self.destroyed.load(Ordering::SeqCst)
+            || self.generation.load(Ordering::SeqCst) != generation
+        {
+            return;
+        }
+        let Some(mut host) = lock(&self.host).take() else {
+            return;
+        };
+
+        let status = wait_for_exit(&mut host.child);
+        let restarting = self.restarts.fetch_add(1, Ordering::SeqCst) < MAX_RESTARTS;
+        error!(
+            "mpv host for window '{}' exited unexpectedly ({}).",
+            self.window_label,
+            status.map_or("unknown status".to_string(), |status| status.to_string())
+        );
+        (self.events)(crashed_event(status, restarting));
+
+        if !restarting {
+            return;
+        }
+        match self.start().and_then(|_| self.restore()) {
+            Ok(()) => info!("Restarted the mpv host for window '{}'.", self.window_label),
+            Err(e) => error!(
+                "Failed to restart the mpv host for window '{}': {}",
+                self.window_label, e
+            ),
+        }
+    }
+
+    /// Adds the observations and hooks of the previous host again.
+    fn restore(&self) -> Result<()> {
+        let observations = lock(&self.observations).clone();
+        for (reply_id, (name, format)) in observations {
+            self.request(HostRequest::ObserveProperty {
+                reply_id,
+                name,
+                format,
+            })?;
+        }
+        let hooks = lock(&self.hooks).clone();
+        for (reply_id, (name, priority)) in hooks {
+            self.request(HostRequest::HookAdd {
+                reply_id,
+                name,
+                priority,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn request(&self, request: HostRequest) -> Result<serde_json::Value> {
+        let client = lock(&self.host)
+            .as_ref()
+            .map(|host| host.client.clone())
+            .ok_or_else(|| {
+                Error::Ipc(format!(
+                    "no mpv host is running for window '{}'",
+                    self.window_label
+                ))
+            })?;
+        self.request_with(&client, request)
+    }
+
+    fn request_with(
+        &self,
+        client: &SocketClient,
+        request: HostRequest,
+    ) -> Result<serde_json::Value> {
+        let serde_json::Value::Object(message) = serde_json::to_value(&request)? else {
+            return Err(Error::Ipc("request is not an object".to_string()));
+        };
+
+        let mut response = client.request(message, REQUEST_TIMEOUT)?;
+        match response.get_mut("error").map(serde_json::Value::take) {
+            None | Some(serde_json::Value::Null) => Ok(response["data"].take()),
+            Some(error) => {
+                Err(serde_json::from_value::<HostError>(error)?.into_error(&self.window_label))
+            }
+        }
+    }
+}
+
+impl BackendInstance for ProcessInstance {
+    fn command(&self, name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value> {
+        self.request(HostRequest::Command {
+            name: name.to_string(),
+            args: args.to_vec(),
+        })
+    }
+
+    fn set_property(&self, name: &str, value: &serde_json::Value) -> Result<()> {
+        self.request(HostRequest::SetProperty {
+            name: name.to_string(),
+            value: value.clone(),
+        })
+        .map(|_| ())
+    }
+
+    fn get_property(&self, name: &str, format: &str) -> Result<serde_json::Value> {
+        self.request(HostRequest::GetProperty {
+            name: name.to_string(),
+            format: format.to_string(),
+        })
+    }
+
+    fn command_async(&self, reply_id: u64, name: &str, args: &[serde_json::Value]) -> Result<()> {
+        self.request(HostRequest::CommandAsync {
+            reply_id,
+            name: name.to_string(),
+            args: args.to_vec(),
+        })
+        .map(|_| ())
+    }
+
+    fn observe_property(&self, reply_id: u64, name: &str, format: &str) -> Result<()> {
+        self.request(HostRequest::ObserveProperty {
+            reply_id,
+            name: name.to_string(),
+            format: format.to_string(),
+        })?;
+        lock(&self.observations).insert(reply_id, (name.to_string(), format.to_string()));
+        Ok(())
+    }
+
+    fn unobserve_property(&self, reply_id: u64) -> Result<()> {
+        self.request(HostRequest::UnobserveProperty { reply_id })?;
+        lock(&self.observations).shift_remove(&reply_id);
+        Ok(())
+    }
+
+    fn hook_add(&self, reply_id: u64, name: &str, priority: i32) -> Result<()> {
+        self.request(HostRequest::HookAdd {
+            reply_id,
+            name: name.to_string(),
+            priority,
+        })?;
+        lock(&self.hooks).insert(reply_id, (name.to_string(), priority));
+        Ok(())
+    }
+
+    fn hook_continue(&self, hook_id: u64) -> Result<()> {
+        self.request(HostRequest::HookContinue { hook_id })
+            .map(|_| ())
+    }
+
+    fn destroy(&self) -> Result<()> {
+        if self.destroyed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let Some(mut host) = lock(&self.host).take() else {
+            return Ok(());
+        };
+
+        let result = self.request_with(&host.client, HostRequest::Destroy);
+        wait_for_exit(&mut host.child);
+        result.map(|_| ())
+    }
+}
+
+impl Drop for ProcessInstance {
+    fn drop(&mut self) {
+        if let Some(mut host) = lock(&self.host).take() {
+            let _ = host.child.kill();
+            let _ = host.child.wait();
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
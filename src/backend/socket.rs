@@ -0,0 +1,146 @@
+use log::{error, trace};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::{Error, Result};
+
+type Pending = Arc<Mutex<HashMap<u64, mpsc::Sender<serde_json::Value>>>>;
+
+/// A connection exchanging one JSON object per line.
+///
+/// Requests get a `request_id`. Incoming lines with a `request_id` and no `event` are
+/// responses and go to the matching request. Every other line is passed to `on_message`.
+/// This is the framing of mpv's JSON IPC, which the process host speaks as well.
+pub(crate) struct SocketClient {
+    writer: Mutex<Box<dyn Write + Send>>,
+    pending: Pending,
+    next_request_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+}
+
+impl SocketClient {
+    /// Starts reading `reader` on a new thread. `on_close` runs on that thread once it ends.
+    pub fn new<F, C>(
+        name: &str,
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+        on_message: F,
+        on_close: C,
+    ) -> Result<Self>
+    where
+        F: Fn(serde_json::Value) + Send + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        let pending: Pending = Arc::default();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        {
+            let pending = pending.clone();
+            let closed = closed.clone();
+            std::thread::Builder::new()
+                .name(format!("mpv-socket-{}", name))
+                .spawn(move || {
+                    for line in BufReader::new(reader).lines() {
+                        let Ok(line) = line else {
+                            break;
+                        };
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        match serde_json::from_str::<serde_json::Value>(&line) {
+                            Ok(message) => route(&pending, message, &on_message),
+                            Err(e) => error!("Failed to deserialize socket message: {}", e),
+                        }
+                    }
+
+                    closed.store(true, Ordering::SeqCst);
+                    // Dropping the senders fails every request still waiting.
+                    lock(&pending).clear();
+                    on_close();
+                })?;
+        }
+
+        Ok(Self {
+            writer: Mutex::new(Box::new(writer)),
+            pending,
+            next_request_id: AtomicU64::new(1),
+            closed,
+        })
+    }
+
+    /// Sends `message` with a new `request_id` and waits for the response to it.
+    pub fn request(
+        &self,
+        mut message: serde_json::Map<String, serde_json::Value>,
+        timeout: Duration,
+    ) -> Result<serde_json::Value> {
+        if self.is_closed() {
+            return Err(Error::Ipc("connection closed".to_string()));
+        }
+
+        let request_id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+        message.insert("request_id".to_string(), request_id.into());
+
+        let (tx, rx) = mpsc::channel();
+        lock(&self.pending).insert(request_id, tx);
+
+        if let Err(e) = self.send(&message) {
+            lock(&self.pending).remove(&request_id);
+            return Err(e);
+        }
+
+        match rx.recv_timeout(timeout) {
+            Ok(response) => Ok(response),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                lock(&self.pending).remove(&request_id);
+                Err(Error::Ipc(format!("no response within {:?}", timeout)))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(Error::Ipc("connection closed".to_string()))
+            }
+        }
+    }
+
+    /// Sends `message` without waiting for anything.
+    pub fn send(&self, message: &serde_json::Map<String, serde_json::Value>) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        trace!("SOCKET SEND {}", line);
+        line.push('\n');
+
+        let mut writer = lock(&self.writer);
+        writer.write_all(line.as_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+fn route(pending: &Pending, message: serde_json::Value, on_message: &impl Fn(serde_json::Value)) {
+    let request_id = match message.get("event") {
+        Some(_) => None,
+        None => message.get("request_id").and_then(|id| id.as_u64()),
+    };
+
+    match request_id {
+        Some(request_id) => match lock(pending).remove(&request_id) {
+            Some(tx) => {
+                let _ = tx.send(message);
+            }
+            None => trace!("Dropping response to request {}.", request_id),
+        },
+        None => on_message(message),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
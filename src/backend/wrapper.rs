@@ -0,0 +1,382 @@
+use indexmap::IndexMap;
+use log::error;
+use scopeguard::defer;
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr, CString, OsStr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::{unsupported, BackendCapabilities, BackendInstance, Capability, EventSink, MpvBackend};
+use crate::models::FfiResponse;
+use crate::validation;
+use crate::wrapper::{LibmpvWrapper, MpvHandle};
+use crate::{Error, Result};
+
+/// Major `libmpv-wrapper` interface version the plugin speaks.
+///
+/// Wrappers that don't export `mpv_wrapper_version` are treated as `1.0`.
+pub const WRAPPER_ABI_MAJOR: u32 = 1;
+
+type VersionFn = unsafe extern "C" fn() -> c_uint;
+type CommandAsyncFn =
+    unsafe extern "C" fn(*mut MpvHandle, u64, *const c_char, *const c_char) -> *mut c_char;
+type ObservePropertyFn =
+    unsafe extern "C" fn(*mut MpvHandle, u64, *const c_char, *const c_char) -> *mut c_char;
+type UnobservePropertyFn = unsafe extern "C" fn(*mut MpvHandle, u64) -> *mut c_char;
+type HookAddFn = unsafe extern "C" fn(*mut MpvHandle, u64, *const c_char, c_int) -> *mut c_char;
+type HookContinueFn = unsafe extern "C" fn(*mut MpvHandle, u64) -> *mut c_char;
+
+/// Symbols newer wrappers export in addition to the required ones.
+#[derive(Default)]
+struct Extensions {
+    _library: Option<libloading::Library>,
+    version: Option<(u32, u32)>,
+    command_async: Option<CommandAsyncFn>,
+    observe_property: Option<ObservePropertyFn>,
+    unobserve_property: Option<UnobservePropertyFn>,
+    hook_add: Option<HookAddFn>,
+    hook_continue: Option<HookContinueFn>,
+}
+
+impl Extensions {
+    /// Checks the interface version of the library at `path` and looks up its optional symbols.
+    unsafe fn load(path: &OsStr) -> Result<Self> {
+        let library = unsafe { libloading::Library::new(path) }?;
+
+        let version = match unsafe { optional::<VersionFn>(&library, b"mpv_wrapper_version\0") } {
+            Some(version) => {
+                let version = unsafe { version() };
+                (version >> 16, version & 0xffff)
+            }
+            None => (1, 0),
+        };
+        if version.0 != WRAPPER_ABI_MAJOR {
+            return Err(Error::FFI(format!(
+                "libmpv-wrapper interface version {}.{} is not supported, expected {}.x",
+                version.0, version.1, WRAPPER_ABI_MAJOR
+            )));
+        }
+
+        unsafe {
+            Ok(Extensions {
+                version: Some(version),
+                command_async: optional(&library, b"mpv_wrapper_command_async\0"),
+                observe_property: optional(&library, b"mpv_wrapper_observe_property\0"),
+                unobserve_property: optional(&library, b"mpv_wrapper_unobserve_property\0"),
+                hook_add: optional(&library, b"mpv_wrapper_hook_add\0"),
+                hook_continue: optional(&library, b"mpv_wrapper_hook_continue\0"),
+                _library: Some(library),
+            })
+        }
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            abi_version: self
+                .version
+                .map(|(major, minor)| format!("{}.{}", major, minor)),
+            async_commands: self.command_async.is_some(),
+            observe_properties: self.observe_property.is_some()
+                && self.unobserve_property.is_some(),
+            hooks: self.hook_add.is_some() && self.hook_continue.is_some(),
+            // The render symbols of newer wrappers aren't used, software rendering needs libmpv.
+            render_api: false,
+        }
+    }
+}
+
+unsafe fn optional<T: Copy>(library: &libloading::Library, symbol: &[u8]) -> Option<T> {
+    unsafe { library.get::<T>(symbol) }
+        .ok()
+        .map(|symbol| *symbol)
+}
+
+/// Runs mpv through the `libmpv-wrapper` library.
+pub struct WrapperBackend {
+    wrapper: Arc<LibmpvWrapper>,
+    extensions: Arc<Extensions>,
+}
+
+impl WrapperBackend {
+    /// Wraps an already loaded wrapper. Only the required symbols are used.
+    pub fn new(wrapper: LibmpvWrapper) -> Self {
+        Self {
+            wrapper: Arc::new(wrapper),
+            extensions: Arc::new(Extensions::default()),
+        }
+    }
+
+    /// Loads the wrapper at `path`, checking its interface version and optional symbols.
+    ///
+    /// # Safety
+    ///
+    /// `path` must point to a `libmpv-wrapper` build.
+    pub unsafe fn load(path: impl AsRef<OsStr>) -> Result<Self> {
+        let path = path.as_ref();
+        let extensions = unsafe { Extensions::load(path) }?;
+        let wrapper = unsafe { LibmpvWrapper::new(path) }?;
+        Ok(Self {
+            wrapper: Arc::new(wrapper),
+            extensions: Arc::new(extensions),
+        })
+    }
+}
+
+struct EventUserData {
+    events: EventSink,
+    free_fn: unsafe extern "C" fn(*mut c_char),
+}
+
+unsafe extern "C" fn event_callback(event: *const c_char, userdata: *mut c_void) {
+    if event.is_null() || userdata.is_null() {
+        return;
+    }
+
+    let EventUserData { events, free_fn } = unsafe { &*(userdata as *const EventUserData) };
+
+    let event_string = unsafe { CStr::from_ptr(event).to_string_lossy().to_string() };
+
+    unsafe {
+        free_fn(event as *mut c_char);
+    }
+
+    match serde_json::from_str::<serde_json::Value>(&event_string) {
+        Ok(event) => events(event),
+        Err(e) => {
+            error!("Failed to deserialize mpv FFI event: {}", e);
+        }
+    }
+}
+
+impl MpvBackend for WrapperBackend {
+    fn create(
+        &self,
+        window_label: &str,
+        initial_options: &IndexMap<String, serde_json::Value>,
+        observed_properties: &IndexMap<String, String>,
+        events: EventSink,
+    ) -> Result<Arc<dyn BackendInstance>> {
+        let wrapper = &self.wrapper;
+
+        let c_initial_options = CString::new(serde_json::to_string(initial_options)?)?;
+        let c_observed_properties = CString::new(serde_json::to_string(observed_properties)?)?;
+
+        let event_callback_data = Box::new(EventUserData {
+            events,
+            free_fn: wrapper.mpv_wrapper_free,
+        });
+        let event_userdata = Box::into_raw(event_callback_data) as *mut c_void;
+
+        let handle = unsafe {
+            wrapper.mpv_wrapper_create(
+                c_initial_options.as_ptr(),
+                c_observed_properties.as_ptr(),
+                Some(event_callback),
+                event_userdata,
+            )
+        };
+
+        if handle.is_null() {
+            let _ = unsafe { Box::from_raw(event_userdata as *mut EventUserData) };
+
+            error!(
+                "Failed to create mpv instance for window '{}'. Validating options...",
+                window_label
+            );
+            return Err(Error::CreateInstance {
+                window_label: window_label.to_string(),
+                invalid_options: validation::find_invalid_options(
+                    self,
+                    window_label,
+                    initial_options,
+                ),
+                log: validation::capture_creation_log(wrapper, initial_options, window_label),
+            });
+        }
+
+        Ok(Arc::new(WrapperInstance {
+            wrapper: wrapper.clone(),
+            extensions: self.extensions.clone(),
+            handle,
+            event_userdata,
+            window_label: window_label.to_string(),
+            destroyed: AtomicBool::new(false),
+        }))
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.extensions.capabilities()
+    }
+}
+
+struct WrapperInstance {
+    wrapper: Arc<LibmpvWrapper>,
+    extensions: Arc<Extensions>,
+    handle: *mut MpvHandle,
+    event_userdata: *mut c_void,
+    window_label: String,
+    destroyed: AtomicBool,
+}
+
+unsafe impl Send for WrapperInstance {}
+unsafe impl Sync for WrapperInstance {}
+
+impl std::fmt::Debug for WrapperInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WrapperInstance")
+            .field("handle", &self.handle)
+            .field("window_label", &self.window_label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WrapperInstance {
+    /// Reads and frees a JSON response returned by `libmpv-wrapper`.
+    fn response(&self, result_ptr: *mut c_char) -> Result<FfiResponse> {
+        if result_ptr.is_null() {
+            return Err(Error::FFI("Call returned null pointer".into()));
+        }
+
+        defer! {
+            unsafe { self.wrapper.mpv_wrapper_free(result_ptr) };
+        }
+
+        let response_str = unsafe { CStr::from_ptr(result_ptr).to_string_lossy() };
+        Ok(serde_json::from_str(&response_str)?)
+    }
+
+    /// Checks the response of a call that returns no data.
+    fn acknowledge(&self, result_ptr: *mut c_char) -> Result<()> {
+        match self.response(result_ptr)?.error {
+            Some(message) => Err(Error::Command {
+                window_label: self.window_label.clone(),
+                message,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn extension<T>(&self, symbol: Option<T>, capability: Capability) -> Result<T> {
+        symbol.ok_or_else(|| {
+            unsupported(
+                capability,
+                self.extensions.capabilities().abi_version.as_deref(),
+            )
+        })
+    }
+}
+
+impl BackendInstance for WrapperInstance {
+    fn command(&self, name: &str, args: &[serde_json::Value]) -> Result<serde_json::Value> {
+        let c_name = CString::new(name)?;
+        let c_args = CString::new(serde_json::to_string(args)?)?;
+
+        let result_ptr = unsafe {
+            self.wrapper
+                .mpv_wrapper_command(self.handle, c_name.as_ptr(), c_args.as_ptr())
+        };
+
+        let response = self.response(result_ptr)?;
+        match response.error {
+            Some(message) => Err(Error::Command {
+                window_label: self.window_label.clone(),
+                message,
+            }),
+            None => Ok(response.data.unwrap_or_default()),
+        }
+    }
+
+    fn set_property(&self, name: &str, value: &serde_json::Value) -> Result<()> {
+        let c_name = CString::new(name)?;
+        let c_value = CString::new(serde_json::to_string(value)?)?;
+
+        let result_ptr = unsafe {
+            self.wrapper
+                .mpv_wrapper_set_property(self.handle, c_name.as_ptr(), c_value.as_ptr())
+        };
+
+        let response = self.response(result_ptr)?;
+        match response.error {
+            Some(message) => Err(Error::SetProperty {
+                window_label: self.window_label.clone(),
+                message,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn get_property(&self, name: &str, format: &str) -> Result<serde_json::Value> {
+        let c_name = CString::new(name)?;
+        let c_format = CString::new(format)?;
+
+        let result_ptr = unsafe {
+            self.wrapper
+                .mpv_wrapper_get_property(self.handle, c_name.as_ptr(), c_format.as_ptr())
+        };
+
+        let response = self.response(result_ptr)?;
+        let message = match (response.error, response.data) {
+            (None, Some(data)) => return Ok(data),
+            (Some(message), _) => message,
+            (None, None) => "FFI response contained no data".to_string(),
+        };
+        Err(Error::GetProperty {
+            window_label: self.window_label.clone(),
+            message,
+        })
+    }
+
+    fn command_async(&self, reply_id: u64, name: &str, args: &[serde_json::Value]) -> Result<()> {
+        let command_async =
+            self.extension(self.extensions.command_async, Capability::AsyncCommands)?;
+        let c_name = CString::new(name)?;
+        let c_args = CString::new(serde_json::to_string(args)?)?;
+        self.acknowledge(unsafe {
+            command_async(self.handle, reply_id, c_name.as_ptr(), c_args.as_ptr())
+        })
+    }
+
+    fn observe_property(&self, reply_id: u64, name: &str, format: &str) -> Result<()> {
+        let observe_property = self.extension(
+            self.extensions.observe_property,
+            Capability::ObserveProperties,
+        )?;
+        let c_name = CString::new(name)?;
+        let c_format = CString::new(format)?;
+        self.acknowledge(unsafe {
+            observe_property(self.handle, reply_id, c_name.as_ptr(), c_format.as_ptr())
+        })
+    }
+
+    fn unobserve_property(&self, reply_id: u64) -> Result<()> {
+        let unobserve_property = self.extension(
+            self.extensions.unobserve_property,
+            Capability::ObserveProperties,
+        )?;
+        self.acknowledge(unsafe { unobserve_property(self.handle, reply_id) })
+    }
+
+    fn hook_add(&self, reply_id: u64, name: &str, priority: i32) -> Result<()> {
+        let hook_add = self.extension(self.extensions.hook_add, Capability::Hooks)?;
+        let c_name = CString::new(name)?;
+        self.acknowledge(unsafe { hook_add(self.handle, reply_id, c_name.as_ptr(), priority) })
+    }
+
+    fn hook_continue(&self, hook_id: u64) -> Result<()> {
+        let hook_continue = self.extension(self.extensions.hook_continue, Capability::Hooks)?;
+        self.acknowledge(unsafe { hook_continue(self.handle, hook_id) })
+    }
+
+    fn destroy(&self) -> Result<()> {
+        if self.destroyed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        unsafe {
+            self.wrapper.mpv_wrapper_destroy(self.handle);
+        }
+
+        let _ = unsafe { Box::from_raw(self.event_userdata as *mut EventUserData) };
+
+        Ok(())
+    }
+}
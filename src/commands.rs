@@ -1,8 +1,31 @@
+use base64::Engine;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::ipc::{Channel, InvokeResponseBody};
 use tauri::{command, AppHandle, Runtime};
 
+use crate::BackendCapabilities;
+use crate::Chapter;
+use crate::Edition;
+use crate::ExternalTrackOptions;
+use crate::FrameSink;
+use crate::MediaFile;
+use crate::MediaInfo;
+use crate::MediaKind;
 use crate::MpvConfig;
 use crate::MpvExt;
+use crate::PlaylistEntry;
+use crate::Profile;
+use crate::QueueState;
+use crate::RepeatMode;
 use crate::Result;
+use crate::ResumePoint;
+use crate::ScreenshotOptions;
+use crate::Storyboard;
+use crate::StoryboardOptions;
+use crate::Track;
+use crate::TrackKind;
+use crate::VideoFrame;
 use crate::VideoMarginRatio;
 
 #[command]
@@ -19,7 +42,6 @@ pub(crate) async fn destroy<R: Runtime>(app: AppHandle<R>, window_label: String)
     tauri::async_runtime::spawn_blocking(move || app.mpv().destroy(&window_label))
         .await
         .map_err(|e| crate::Error::Destroy(e.to_string()))?
-        .map_err(Into::into)
 }
 
 #[command]
@@ -36,7 +58,7 @@ pub(crate) async fn command<R: Runtime>(
     {
         Ok(Ok(_)) => Ok(()),
         Ok(Err(e)) => Err(e),
-        Err(e) => Err(crate::Error::Tauri(e.into())),
+        Err(e) => Err(crate::Error::Tauri(e)),
     }
 }
 
@@ -54,7 +76,7 @@ pub(crate) async fn set_property<R: Runtime>(
     {
         Ok(Ok(_)) => Ok(()),
         Ok(Err(e)) => Err(e),
-        Err(e) => Err(crate::Error::Tauri(e.into())),
+        Err(e) => Err(crate::Error::Tauri(e)),
     }
 }
 
@@ -72,7 +94,7 @@ pub(crate) async fn get_property<R: Runtime>(
     {
         Ok(Ok(value)) => Ok(value),
         Ok(Err(e)) => Err(e),
-        Err(e) => Err(crate::Error::Tauri(e.into())),
+        Err(e) => Err(crate::Error::Tauri(e)),
     }
 }
 
@@ -89,6 +111,679 @@ pub(crate) async fn set_video_margin_ratio<R: Runtime>(
     {
         Ok(Ok(_)) => Ok(()),
         Ok(Err(e)) => Err(e),
-        Err(e) => Err(crate::Error::Tauri(e.into())),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn get_profiles<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<Vec<Profile>> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().get_profiles(&window_label)).await
+    {
+        Ok(Ok(profiles)) => Ok(profiles),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn apply_profile<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().apply_profile(&name, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn reload_config<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().reload_config(&window_label)).await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn get_capabilities<R: Runtime>(app: AppHandle<R>) -> Result<BackendCapabilities> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().capabilities()).await {
+        Ok(Ok(capabilities)) => Ok(capabilities),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+/// Sends every frame as its width and height (little-endian `u32`s) followed by RGBA pixels.
+#[command]
+pub(crate) async fn start_render<R: Runtime>(
+    app: AppHandle<R>,
+    width: u32,
+    height: u32,
+    on_frame: Channel<InvokeResponseBody>,
+    window_label: String,
+) -> Result<()> {
+    let frames: FrameSink = Arc::new(move |frame: VideoFrame| {
+        let mut body = Vec::with_capacity(8 + frame.pixels.len());
+        body.extend_from_slice(&frame.width.to_le_bytes());
+        body.extend_from_slice(&frame.height.to_le_bytes());
+        body.extend_from_slice(&frame.pixels);
+        if let Err(e) = on_frame.send(InvokeResponseBody::Raw(body)) {
+            log::error!("Failed to send a frame to the frontend: {}", e);
+        }
+    });
+
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().start_render(width, height, frames, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn resize_render<R: Runtime>(
+    app: AppHandle<R>,
+    width: u32,
+    height: u32,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().resize_render(width, height, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+/// Returns the image as base64.
+#[command]
+pub(crate) async fn screenshot<R: Runtime>(
+    app: AppHandle<R>,
+    options: ScreenshotOptions,
+    window_label: String,
+) -> Result<String> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().screenshot(&options, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(image)) => Ok(base64::engine::general_purpose::STANDARD.encode(image)),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn save_screenshot<R: Runtime>(
+    app: AppHandle<R>,
+    options: ScreenshotOptions,
+    window_label: String,
+) -> Result<PathBuf> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().save_screenshot(&options, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(path)) => Ok(path),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn get_thumbnail<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    time: f64,
+    width: u32,
+    window_label: String,
+) -> Result<String> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().thumbnail(&path, time, width, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(image)) => Ok(base64::engine::general_purpose::STANDARD.encode(image.as_slice())),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn generate_storyboard<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    options: StoryboardOptions,
+    window_label: String,
+) -> Result<Storyboard> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv()
+            .generate_storyboard(&path, &options, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(storyboard)) => Ok(storyboard),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn cancel_storyboard<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    window_label: String,
+) -> Result<bool> {
+    app.mpv().cancel_storyboard(&path, &window_label)
+}
+
+#[command]
+pub(crate) async fn probe<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    window_label: String,
+) -> Result<MediaInfo> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().probe(&path, &window_label)).await
+    {
+        Ok(Ok(info)) => Ok(info),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn list_tracks<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<Vec<Track>> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().list_tracks(&window_label)).await {
+        Ok(Ok(tracks)) => Ok(tracks),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn select_track<R: Runtime>(
+    app: AppHandle<R>,
+    kind: TrackKind,
+    id: Option<i64>,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().select_track(kind, id, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn add_external_track<R: Runtime>(
+    app: AppHandle<R>,
+    kind: TrackKind,
+    path: String,
+    options: ExternalTrackOptions,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv()
+            .add_external_track(kind, &path, &options, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn remove_track<R: Runtime>(
+    app: AppHandle<R>,
+    kind: TrackKind,
+    id: i64,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().remove_track(kind, id, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn list_editions<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<Vec<Edition>> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().list_editions(&window_label)).await
+    {
+        Ok(Ok(editions)) => Ok(editions),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn select_edition<R: Runtime>(
+    app: AppHandle<R>,
+    id: i64,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().select_edition(id, &window_label))
+        .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn list_chapters<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<Vec<Chapter>> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().list_chapters(&window_label)).await
+    {
+        Ok(Ok(chapters)) => Ok(chapters),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn next_chapter<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().next_chapter(&window_label)).await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn prev_chapter<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().prev_chapter(&window_label)).await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn seek_chapter<R: Runtime>(
+    app: AppHandle<R>,
+    index: usize,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().seek_chapter(index, &window_label))
+        .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn get_playlist<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<Vec<PlaylistEntry>> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().playlist(&window_label).entries())
+        .await
+    {
+        Ok(Ok(entries)) => Ok(entries),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_append<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).append(&path)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_insert_at<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    index: usize,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).insert_at(&path, index)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_insert_next<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).insert_next(&path)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_move<R: Runtime>(
+    app: AppHandle<R>,
+    from: usize,
+    to: usize,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).move_entry(from, to)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_remove<R: Runtime>(
+    app: AppHandle<R>,
+    index: usize,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).remove(index)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_clear<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().playlist(&window_label).clear())
+        .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_play_index<R: Runtime>(
+    app: AppHandle<R>,
+    index: usize,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).play_index(index)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_play_entry<R: Runtime>(
+    app: AppHandle<R>,
+    id: i64,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).play_entry(id)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_export<R: Runtime>(
+    app: AppHandle<R>,
+    path: PathBuf,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).export(&path)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn playlist_import<R: Runtime>(
+    app: AppHandle<R>,
+    path: PathBuf,
+    replace: bool,
+    window_label: String,
+) -> Result<usize> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).import(&path, replace)
+    })
+    .await
+    {
+        Ok(Ok(count)) => Ok(count),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn get_queue<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<QueueState> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().playlist(&window_label).queue())
+        .await
+    {
+        Ok(Ok(queue)) => Ok(queue),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn set_repeat<R: Runtime>(
+    app: AppHandle<R>,
+    repeat: RepeatMode,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).set_repeat(repeat)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn set_shuffle<R: Runtime>(
+    app: AppHandle<R>,
+    enabled: bool,
+    seed: Option<u64>,
+    window_label: String,
+) -> Result<()> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().playlist(&window_label).set_shuffle(enabled, seed)
+    })
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn queue_next<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<bool> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().playlist(&window_label).next())
+        .await
+    {
+        Ok(Ok(played)) => Ok(played),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn queue_previous<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<bool> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().playlist(&window_label).previous())
+        .await
+    {
+        Ok(Ok(played)) => Ok(played),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn load_directory<R: Runtime>(
+    app: AppHandle<R>,
+    path: PathBuf,
+    recursive: bool,
+    filter: Vec<MediaKind>,
+    window_label: String,
+) -> Result<Vec<MediaFile>> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv()
+            .playlist(&window_label)
+            .load_directory(&path, recursive, &filter)
+    })
+    .await
+    {
+        Ok(Ok(files)) => Ok(files),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn get_resume_point<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    window_label: String,
+) -> Result<Option<ResumePoint>> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().resume_point(&path, &window_label))
+        .await
+    {
+        Ok(Ok(point)) => Ok(point),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn list_resume_points<R: Runtime>(
+    app: AppHandle<R>,
+    window_label: String,
+) -> Result<Vec<ResumePoint>> {
+    match tauri::async_runtime::spawn_blocking(move || app.mpv().resume_points(&window_label)).await
+    {
+        Ok(Ok(points)) => Ok(points),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
+    }
+}
+
+#[command]
+pub(crate) async fn clear_resume_point<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    window_label: String,
+) -> Result<bool> {
+    match tauri::async_runtime::spawn_blocking(move || {
+        app.mpv().clear_resume_point(&path, &window_label)
+    })
+    .await
+    {
+        Ok(Ok(cleared)) => Ok(cleared),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(crate::Error::Tauri(e)),
     }
 }
@@ -0,0 +1,414 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer};
+use std::path::PathBuf;
+
+use crate::models::ClosePolicy;
+
+const OBSERVABLE_FORMATS: &[&str] = &["string", "flag", "int64", "double", "node"];
+
+const EVENT_NAMES: &[&str] = &[
+    "shutdown",
+    "log-message",
+    "get-property-reply",
+    "set-property-reply",
+    "command-reply",
+    "start-file",
+    "end-file",
+    "file-loaded",
+    "idle",
+    "tick",
+    "client-message",
+    "video-reconfig",
+    "audio-reconfig",
+    "seek",
+    "playback-restart",
+    "property-change",
+    "queue-overflow",
+    "hook",
+    "crashed",
+    "screenshot-saved",
+    "storyboard-progress",
+    "tracks-changed",
+    "chapter-changed",
+    "playlist-changed",
+];
+
+/// Plugin configuration read from `plugins.libmpv` in `tauri.conf.json`.
+///
+/// ```json
+/// {
+///   "plugins": {
+///     "libmpv": {
+///       "wrapperPath": ["lib/libmpv-wrapper.so"],
+///       "initialOptions": { "hwdec": "auto-safe", "keep-open": "yes" },
+///       "observedProperties": { "pause": "flag" },
+///       "closePolicy": "quit-watch-later",
+///       "eventFilter": { "exclude": ["tick", "log-message"] }
+///     }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Config {
+    /// Which library the plugin drives mpv through.
+    #[serde(default)]
+    pub backend: BackendKind,
+    /// Paths tried, in order, when loading `libmpv-wrapper`.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub wrapper_path: Vec<PathBuf>,
+    /// Paths tried, in order, when loading libmpv for the `libmpv` backend.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub libmpv_path: Vec<PathBuf>,
+    /// The `mpv` executable the `mpv` backend runs. Defaults to `mpv` on the `PATH`.
+    ///
+    /// Relative paths are resolved against the directory of the app executable.
+    pub mpv_path: Option<PathBuf>,
+    /// Executable the `process` backend runs instances in. Defaults to the app itself.
+    ///
+    /// Relative paths are resolved against the directory of the app executable.
+    pub host_path: Option<PathBuf>,
+    /// Arguments passed to the host executable.
+    #[serde(default)]
+    pub host_args: Vec<String>,
+    /// Options merged into every `MpvConfig::initial_options`. Per-instance options win.
+    #[serde(default)]
+    pub initial_options: IndexMap<String, serde_json::Value>,
+    /// Properties observed by every instance, merged with `MpvConfig::observed_properties`.
+    #[serde(default)]
+    pub observed_properties: IndexMap<String, String>,
+    /// Close policy for instances that don't set one.
+    #[serde(default)]
+    pub close_policy: ClosePolicy,
+    /// Which mpv events are forwarded to the frontend.
+    #[serde(default)]
+    pub event_filter: EventFilter,
+    /// How long app exit waits for instances to shut down, in milliseconds.
+    pub exit_timeout: Option<u64>,
+    /// Directory saved screenshots are written to. Defaults to `screenshots` in the app data directory.
+    ///
+    /// Relative paths are resolved against the app data directory.
+    pub screenshot_dir: Option<PathBuf>,
+    /// File name template for saved screenshots, without extension.
+    ///
+    /// `{name}`, `{pos}`, `{window}` and `{unix}` are replaced with the media file name,
+    /// the playback position, the window label and the current Unix time.
+    pub screenshot_template: Option<String>,
+    /// Directory generated storyboards are cached in. Defaults to `storyboards` in the app cache directory.
+    ///
+    /// Relative paths are resolved against the app cache directory.
+    pub storyboard_dir: Option<PathBuf>,
+    /// Limits of the seek-bar thumbnail service.
+    #[serde(default)]
+    pub thumbnails: ThumbnailConfig,
+    /// Limits of `probe`.
+    #[serde(default)]
+    pub probe: ProbeConfig,
+    /// Resume positions kept by the plugin.
+    #[serde(default)]
+    pub resume: ResumeConfig,
+}
+
+/// Limits of the seek-bar thumbnail service, read from `plugins.libmpv.thumbnails`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct ThumbnailConfig {
+    /// How many hidden instances render thumbnails at the same time.
+    pub workers: usize,
+    /// How many thumbnails are kept in memory.
+    pub cache_size: usize,
+    /// How long rendering one thumbnail may take, in milliseconds.
+    pub timeout: u64,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            cache_size: 256,
+            timeout: 10_000,
+        }
+    }
+}
+
+/// Limits of `probe`, read from `plugins.libmpv.probe`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct ProbeConfig {
+    /// How many hidden instances open files at the same time.
+    pub workers: usize,
+    /// How long opening one file may take, in milliseconds.
+    pub timeout: u64,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            timeout: 10_000,
+        }
+    }
+}
+
+/// Resume positions kept by the plugin, read from `plugins.libmpv.resume`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct ResumeConfig {
+    /// Whether instances save where playback stopped.
+    pub enabled: bool,
+    /// File the positions are stored in. Defaults to `resume.json` in the app data directory.
+    ///
+    /// Relative paths are resolved against the app data directory.
+    pub file: Option<PathBuf>,
+    /// How often the position of a playing file is saved, in milliseconds.
+    pub save_interval: u64,
+    /// How many files are remembered. The least recently played are forgotten first.
+    pub max_entries: usize,
+}
+
+impl Default for ResumeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: None,
+            save_interval: 10_000,
+            max_entries: 500,
+        }
+    }
+}
+
+/// The library mpv is driven through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    /// `libmpv-wrapper`, falling back to libmpv when the `libmpv` feature is enabled,
+    /// then to the `mpv` executable on Unix.
+    #[default]
+    Auto,
+    /// `libmpv-wrapper` only.
+    Wrapper,
+    /// libmpv directly. Requires the `libmpv` cargo feature.
+    Libmpv,
+    /// `libmpv-wrapper` in a separate host process per instance.
+    Process,
+    /// The `mpv` executable, driven over JSON IPC. Unix only.
+    Mpv,
+}
+
+/// Selects mpv events by their `event` name.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EventFilter {
+    /// Only these events are forwarded. All events when unset.
+    pub include: Option<Vec<String>>,
+    /// These events are never forwarded.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl EventFilter {
+    pub fn allows(&self, event_name: &str) -> bool {
+        if self.exclude.iter().any(|name| name == event_name) {
+            return false;
+        }
+        match &self.include {
+            Some(include) => include.iter().any(|name| name == event_name),
+            None => true,
+        }
+    }
+}
+
+impl Config {
+    /// Checks the values serde can't, reporting every problem at once.
+    pub fn validate(&self) -> crate::Result<()> {
+        let mut problems = Vec::new();
+
+        if self.backend == BackendKind::Libmpv && !cfg!(feature = "libmpv") {
+            problems.push(
+                "`backend` is 'libmpv', but the plugin was built without the `libmpv` feature"
+                    .to_string(),
+            );
+        }
+
+        for path in &self.wrapper_path {
+            if path.as_os_str().is_empty() {
+                problems.push("`wrapperPath` contains an empty path".to_string());
+            }
+        }
+
+        if self.backend == BackendKind::Mpv && !cfg!(unix) {
+            problems.push("`backend` is 'mpv', which is only supported on Unix".to_string());
+        }
+
+        for (field, path) in [("mpvPath", &self.mpv_path), ("hostPath", &self.host_path)] {
+            if path
+                .as_ref()
+                .is_some_and(|path| path.as_os_str().is_empty())
+            {
+                problems.push(format!("`{}` is empty", field));
+            }
+        }
+
+        for path in &self.libmpv_path {
+            if path.as_os_str().is_empty() {
+                problems.push("`libmpvPath` contains an empty path".to_string());
+            }
+        }
+
+        for (name, value) in &self.initial_options {
+            if !(value.is_string() || value.is_boolean() || value.is_number()) {
+                problems.push(format!(
+                    "`initialOptions.{}` must be a string, boolean or number, got {}",
+                    name, value
+                ));
+            }
+        }
+
+        for (name, format) in &self.observed_properties {
+            if !OBSERVABLE_FORMATS.contains(&format.as_str()) {
+                problems.push(format!(
+                    "`observedProperties.{}` has unknown format '{}', expected one of {:?}",
+                    name, format, OBSERVABLE_FORMATS
+                ));
+            }
+        }
+
+        let filter_names = self
+            .event_filter
+            .include
+            .iter()
+            .flatten()
+            .chain(self.event_filter.exclude.iter());
+        for name in filter_names {
+            if !EVENT_NAMES.contains(&name.as_str()) {
+                problems.push(format!("`eventFilter` has unknown event '{}'", name));
+            }
+        }
+
+        if let Some(template) = &self.screenshot_template {
+            if let Err(e) = crate::screenshot::check_template(template) {
+                problems.push(format!("`screenshotTemplate` {}", e));
+            }
+        }
+
+        if self.thumbnails.workers == 0 {
+            problems.push("`thumbnails.workers` must be at least 1".to_string());
+        }
+
+        if self.probe.workers == 0 {
+            problems.push("`probe.workers` must be at least 1".to_string());
+        }
+
+        if self.resume.max_entries == 0 {
+            problems.push("`resume.maxEntries` must be at least 1".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::Config(problems.join("; ")))
+        }
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> std::result::Result<Vec<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackendKind, Config};
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn parse(value: serde_json::Value) -> serde_json::Result<Config> {
+        serde_json::from_value(value)
+    }
+
+    #[test]
+    fn parses_camel_case_fields() {
+        let config = parse(json!({
+            "backend": "process",
+            "wrapperPath": "lib/libmpv-wrapper.so",
+            "libmpvPath": ["a.so", "b.so"],
+            "initialOptions": { "hwdec": "auto-safe", "volume": 50 },
+            "observedProperties": { "pause": "flag" },
+            "eventFilter": { "exclude": ["tick"] },
+            "thumbnails": { "workers": 3 },
+        }))
+        .unwrap();
+        assert_eq!(config.backend, BackendKind::Process);
+        assert_eq!(
+            config.wrapper_path,
+            vec![PathBuf::from("lib/libmpv-wrapper.so")]
+        );
+        assert_eq!(
+            config.libmpv_path,
+            vec![PathBuf::from("a.so"), PathBuf::from("b.so")]
+        );
+        assert_eq!(config.initial_options["volume"], json!(50));
+        assert_eq!(config.thumbnails.workers, 3);
+        assert_eq!(config.thumbnails.cache_size, 256);
+        assert!(!config.event_filter.allows("tick"));
+        assert!(config.event_filter.allows("seek"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn defaults_an_empty_config() {
+        let config = parse(json!({})).unwrap();
+        assert_eq!(config.backend, BackendKind::Auto);
+        assert!(config.wrapper_path.is_empty());
+        assert!(!config.resume.enabled);
+        assert_eq!(config.probe.workers, 4);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let e = parse(json!({ "wrapperPaths": ["a.so"] })).unwrap_err();
+        assert!(e.to_string().contains("wrapperPaths"), "{}", e);
+
+        let e = parse(json!({ "thumbnails": { "worker": 1 } })).unwrap_err();
+        assert!(e.to_string().contains("worker"), "{}", e);
+
+        assert!(parse(json!({ "backend": "vlc" })).is_err());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let config = parse(json!({
+            "wrapperPath": [""],
+            "initialOptions": { "vo": ["gpu"] },
+            "observedProperties": { "pause": "bool" },
+            "eventFilter": { "include": ["ticks"] },
+            "thumbnails": { "workers": 0 },
+            "resume": { "maxEntries": 0 },
+        }))
+        .unwrap();
+        let Err(crate::Error::Config(message)) = config.validate() else {
+            panic!("expected a config error");
+        };
+        let problems: Vec<&str> = message.split("; ").collect();
+        assert_eq!(problems.len(), 6, "{}", message);
+        assert!(message.contains("`wrapperPath` contains an empty path"));
+        assert!(message.contains("`initialOptions.vo`"));
+        assert!(message.contains("`observedProperties.pause` has unknown format 'bool'"));
+        assert!(message.contains("unknown event 'ticks'"));
+        assert!(message.contains("`thumbnails.workers` must be at least 1"));
+        assert!(message.contains("`resume.maxEntries` must be at least 1"));
+    }
+}
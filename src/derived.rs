@@ -0,0 +1,133 @@
+//! Events the plugin derives from properties every instance observes.
+
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::media;
+
+/// Observed on every instance in addition to the app's properties, with their formats.
+const DERIVED_PROPERTIES: &[(&str, &str)] = &[
+    ("track-list", "node"),
+    ("chapter-list", "node"),
+    ("chapter", "int64"),
+    ("duration", "double"),
+    ("playlist", "node"),
+];
+
+/// Turns `property-change` events of [`DERIVED_PROPERTIES`] into typed events for one instance.
+pub(crate) struct EventDeriver {
+    /// [`DERIVED_PROPERTIES`] and the properties other parts of the plugin observe.
+    properties: Vec<(&'static str, &'static str)>,
+    /// Derived properties the app observes itself, whose `property-change` is forwarded too.
+    app_observed: HashSet<String>,
+    /// Derived properties observed in another format by the app, which derive nothing.
+    foreign_format: HashSet<String>,
+    chapter_list: Value,
+    chapter: Option<i64>,
+    duration: Option<f64>,
+}
+
+impl EventDeriver {
+    /// Adds [`DERIVED_PROPERTIES`] and `internal` to `observed_properties`, after the app's own.
+    ///
+    /// `internal` properties derive no events, their `property-change` is only forwarded if
+    /// the app observes them too.
+    pub fn new(
+        observed_properties: &mut IndexMap<String, String>,
+        internal: &[(&'static str, &'static str)],
+    ) -> Self {
+        let properties: Vec<_> = DERIVED_PROPERTIES.iter().chain(internal).copied().collect();
+        let mut app_observed = HashSet::new();
+        let mut foreign_format = HashSet::new();
+        for (name, format) in &properties {
+            match observed_properties.get(*name) {
+                Some(app_format) => {
+                    app_observed.insert(name.to_string());
+                    if app_format != format {
+                        foreign_format.insert(name.to_string());
+                    }
+                }
+                None => {
+                    observed_properties.insert(name.to_string(), format.to_string());
+                }
+            }
+        }
+
+        Self {
+            properties,
+            app_observed,
+            foreign_format,
+            chapter_list: Value::Null,
+            chapter: None,
+            duration: None,
+        }
+    }
+
+    /// The events to send for `event`: itself unless it only served derivation, and any derived.
+    pub fn process(&mut self, event: Value) -> Vec<Value> {
+        let name = match (event["event"].as_str(), event["name"].as_str()) {
+            (Some("property-change"), Some(name))
+                if self
+                    .properties
+                    .iter()
+                    .any(|(property, _)| *property == name) =>
+            {
+                name.to_string()
+            }
+            _ => return vec![event],
+        };
+
+        let mut events = Vec::new();
+        if self.app_observed.contains(&name) {
+            events.push(event.clone());
+        }
+        if self.foreign_format.contains(&name) {
+            return events;
+        }
+
+        let data = &event["data"];
+        match name.as_str() {
+            "track-list" => events.push(serde_json::json!({
+                "event": "tracks-changed",
+                "tracks": media::parse_tracks(data),
+            })),
+            "chapter-list" => {
+                self.chapter_list = data.clone();
+                // Titles may have changed, or arrived after the chapter.
+                if self.chapter.is_some() {
+                    events.push(self.chapter_changed());
+                }
+            }
+            "chapter" => {
+                // -1 before the first chapter, missing without a file.
+                let chapter = data.as_i64().filter(|chapter| *chapter >= 0);
+                if chapter != self.chapter {
+                    self.chapter = chapter;
+                    events.push(self.chapter_changed());
+                }
+            }
+            "duration" => self.duration = data.as_f64(),
+            "playlist" => events.push(serde_json::json!({
+                "event": "playlist-changed",
+                "entries": media::parse_playlist(data),
+            })),
+            _ => {}
+        }
+        events
+    }
+
+    fn chapter_changed(&self) -> Value {
+        let chapter = self.chapter.and_then(|index| {
+            media::parse_chapters(&self.chapter_list, self.duration)
+                .into_iter()
+                .nth(index as usize)
+        });
+        serde_json::json!({
+            "event": "chapter-changed",
+            "index": self.chapter,
+            "title": chapter.as_ref().and_then(|chapter| chapter.title.clone()),
+            "chapter": chapter,
+        })
+    }
+}
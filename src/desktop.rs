@@ -1,94 +1,188 @@
+use indexmap::IndexMap;
 use log::{error, info, trace, warn};
 use once_cell::sync::OnceCell;
 use raw_window_handle::HasWindowHandle;
-use scopeguard::defer;
-use serde::de::DeserializeOwned;
-use std::collections::HashMap;
-use std::ffi::{c_char, c_void, CStr, CString};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use tauri::Emitter;
 use tauri::{plugin::PluginApi, AppHandle, Manager, Runtime};
 
+#[cfg(unix)]
+use crate::backend::IpcBackend;
+use crate::backend::{
+    BackendCapabilities, Capability, EventSink, FrameSink, MpvBackend, ProcessBackend,
+    WrapperBackend,
+};
+use crate::config::{BackendKind, Config};
+use crate::derived::EventDeriver;
+#[cfg(feature = "libmpv")]
+use crate::libmpv::LibmpvBackend;
+use crate::loader;
+use crate::media;
 use crate::models::*;
+use crate::playlist::PlaylistHandle;
+use crate::probe::Probes;
+use crate::queue::{Queue, QueueAction};
+use crate::resume::{ResumeStore, ResumeTracker, ResumeWriter, RESUME_PROPERTIES};
+use crate::screenshot::{self, TemplateValues, DEFAULT_SCREENSHOT_TEMPLATE};
+use crate::storyboard::Storyboards;
+use crate::thumbnails::Thumbnails;
 use crate::utils::get_wid;
-use crate::wrapper::LibmpvWrapper;
 use crate::Error;
+use crate::Hooks;
+use crate::MpvExt;
 use crate::Result;
 
-pub fn init<R: Runtime, C: DeserializeOwned>(
+/// Commands whose first argument is a path or URL.
+const PATH_COMMANDS: &[&str] = &["loadfile", "loadlist", "sub-add", "audio-add", "video-add"];
+
+/// How long app exit waits for all mpv instances to shut down, unless configured.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn init<R: Runtime>(
     app: &AppHandle<R>,
-    _api: PluginApi<R, C>,
+    api: PluginApi<R, Option<Config>>,
+    hooks: Hooks<R>,
 ) -> crate::Result<Mpv<R>> {
+    let config = api.config().clone().unwrap_or_default();
+    config.validate()?;
+
     info!("Plugin registered.");
     let mpv = Mpv {
         app: app.clone(),
+        thumbnails: Thumbnails::new(&config.thumbnails),
+        storyboards: Storyboards::new(),
+        probes: Probes::new(&config.probe),
+        resume: OnceCell::new(),
+        resume_writer: OnceCell::new(),
+        config,
+        hooks,
         instances: Mutex::new(HashMap::new()),
-        wrapper: OnceCell::new(),
+        backend: OnceCell::new(),
     };
     Ok(mpv)
 }
 
 pub struct Mpv<R: Runtime> {
     app: AppHandle<R>,
+    pub config: Config,
+    hooks: Hooks<R>,
     pub instances: Mutex<HashMap<String, MpvInstance>>,
-    pub wrapper: OnceCell<LibmpvWrapper>,
+    backend: OnceCell<Arc<dyn MpvBackend>>,
+    thumbnails: Thumbnails,
+    storyboards: Storyboards,
+    probes: Probes,
+    resume: OnceCell<ResumeStore>,
+    resume_writer: OnceCell<Arc<ResumeWriter>>,
 }
 
-pub unsafe extern "C" fn event_callback<R: Runtime>(event: *const c_char, userdata: *mut c_void) {
-    if event.is_null() || userdata.is_null() {
-        return;
+/// Resolves a configured relative path against the directory of the app executable.
+fn resolve_from_exe_dir(path: &std::path::Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
     }
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|exe_dir| exe_dir.join(path)))
+        .unwrap_or_else(|| path.to_path_buf())
+}
 
-    let EventUserData {
-        app,
-        free_fn,
-        window_label,
-    } = unsafe { &*(userdata as *const EventUserData<R>) };
+/// Runs the `on_event` hook and forwards the event to the frontend if the filter allows it.
+fn dispatch_event<R: Runtime>(app: AppHandle<R>, window_label: String, event: serde_json::Value) {
+    tauri::async_runtime::spawn(async move {
+        let mpv = app.mpv();
+        if let Some(on_event) = &mpv.hooks.on_event {
+            on_event(&app, &window_label, &event);
+        }
 
-    let event_string = unsafe { CStr::from_ptr(event).to_string_lossy().to_string() };
+        let mpv_event_name = event["event"].as_str().unwrap_or_default();
+        if !mpv.config.event_filter.allows(mpv_event_name) {
+            return;
+        }
 
-    unsafe {
-        free_fn(event as *mut c_char);
-    }
+        let event_name = format!("mpv-event-{}", window_label);
+        if let Err(e) = app.emit_to(window_label.as_str(), &event_name, &event) {
+            error!("Failed to emit mpv event to frontend: {}", e);
+        }
+    });
+}
 
-    tauri::async_runtime::spawn(async move {
-        match serde_json::from_str::<serde_json::Value>(&event_string) {
-            Ok(event) => {
-                let event_name = format!("mpv-event-{}", window_label);
-                if let Err(e) = app.emit_to(&window_label, &event_name, &event) {
-                    error!("Failed to emit mpv event to frontend: {}", e);
-                }
-            }
-            Err(e) => {
-                error!("Failed to deserialize mpv FFI event: {}", e);
-            }
+/// Carries out a decision of the queue engine off the event thread, which a command could block.
+fn run_queue_action<R: Runtime>(app: AppHandle<R>, window_label: String, action: QueueAction) {
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = app.mpv().playlist(&window_label).run(action) {
+            error!(
+                "Failed to advance the queue for window '{}': {}",
+                window_label, e
+            );
         }
     });
 }
 
 impl<R: Runtime> Mpv<R> {
     pub fn init(&self, mpv_config: MpvConfig, window_label: &str) -> Result<String> {
-        self.init_wid_mode(mpv_config, window_label)?;
+        self.report(window_label, self.init_instance(mpv_config, window_label))?;
         Ok(window_label.to_string())
     }
 
-    fn init_wid_mode(&self, mpv_config: MpvConfig, window_label: &str) -> Result<String> {
-        let app = self.app.clone();
+    fn init_instance(&self, mpv_config: MpvConfig, window_label: &str) -> Result<String> {
+        let backend = self.get_backend()?;
+        if mpv_config.render_mode == RenderMode::Software {
+            backend.capabilities().require(Capability::RenderApi)?;
+        }
 
-        let wrapper = self.get_wrapper()?;
+        let mut initial_options = self.hooks.default_options.clone();
+        initial_options.extend(self.config.initial_options.clone());
+        if let Some(preset) = &mpv_config.preset {
+            initial_options.extend(self.preset_options(preset)?);
+        }
+        initial_options.extend(mpv_config.initial_options.clone());
 
-        let free_fn = wrapper.mpv_wrapper_free;
+        let config_dir = match &mpv_config.config_dir {
+            Some(config_dir) => Some(self.resolve_config_dir(config_dir)?),
+            None => None,
+        };
+        if let Some(config_dir) = &config_dir {
+            if !config_dir.is_dir() {
+                warn!(
+                    "mpv config directory '{}' for window '{}' does not exist.",
+                    config_dir.display(),
+                    window_label
+                );
+            }
+            initial_options.insert("config".to_string(), serde_json::json!("yes"));
+            initial_options.insert(
+                "config-dir".to_string(),
+                serde_json::json!(config_dir.to_string_lossy()),
+            );
+        }
 
-        let mut initial_options = mpv_config.initial_options.clone();
+        let mut observed_properties = self.config.observed_properties.clone();
+        observed_properties.extend(mpv_config.observed_properties.clone());
+        let resume = if self.config.resume.enabled {
+            Some(Arc::new(ResumeTracker::new(
+                Duration::from_millis(self.config.resume.save_interval),
+                self.resume_writer()?,
+            )))
+        } else {
+            None
+        };
+        let internal_properties = if resume.is_some() {
+            RESUME_PROPERTIES
+        } else {
+            &[]
+        };
+        let deriver = EventDeriver::new(&mut observed_properties, internal_properties);
 
         let Some(mut instances_lock) = self.lock_and_check_existence(window_label)? else {
             return Ok(window_label.to_string());
         };
 
         let audio_only = initial_options.iter().any(|(key, value)| {
-            (key == "video" && (value == "no" || value == false))
-                || (key == "vid" && (value == "no" || value == false))
+            (key == "video" || key == "vid") && (value == "no" || value == false)
         });
 
         if audio_only {
@@ -98,7 +192,10 @@ impl<R: Runtime> Mpv<R> {
             );
         }
 
-        if !audio_only && !initial_options.contains_key("wid") {
+        if mpv_config.render_mode == RenderMode::Software {
+            initial_options.shift_remove("wid");
+            initial_options.insert("vo".to_string(), serde_json::json!("libmpv"));
+        } else if !audio_only && !initial_options.contains_key("wid") {
             let wid_result = (|| -> crate::Result<i64> {
                 let window = self
                     .app
@@ -122,56 +219,69 @@ impl<R: Runtime> Mpv<R> {
             }
         }
 
-        let initial_options_string = serde_json::to_string(&initial_options)?;
-        let observed_properties_string = serde_json::to_string(&mpv_config.observed_properties)?;
-
-        let c_initial_options = CString::new(initial_options_string)?;
-        let c_observed_properties = CString::new(observed_properties_string)?;
-
-        let event_callback_data = Box::new(EventUserData {
-            app,
-            free_fn,
-            window_label: window_label.to_string(),
-        });
-        let event_userdata = Box::into_raw(event_callback_data) as *mut c_void;
-
-        let mpv_handle = unsafe {
-            wrapper.mpv_wrapper_create(
-                c_initial_options.as_ptr(),
-                c_observed_properties.as_ptr(),
-                Some(event_callback::<R>),
-                event_userdata,
-            )
+        let queue = Arc::new(Queue::default());
+        let events: EventSink = {
+            let app = self.app.clone();
+            let window_label = window_label.to_string();
+            let deriver = Mutex::new(deriver);
+            let queue = queue.clone();
+            let resume = resume.clone();
+            Arc::new(move |event| {
+                if let Some(resume) = &resume {
+                    resume.observe(&event);
+                }
+                let events = match deriver.lock() {
+                    Ok(mut deriver) => deriver.process(event),
+                    Err(poisoned) => poisoned.into_inner().process(event),
+                };
+                for event in events {
+                    if let Some(action) = queue.observe(&event) {
+                        run_queue_action(app.clone(), window_label.clone(), action);
+                    }
+                    dispatch_event(app.clone(), window_label.clone(), event);
+                }
+            })
         };
 
-        if mpv_handle.is_null() {
-            let _ = unsafe { Box::from_raw(event_userdata as *mut (AppHandle<R>, String)) };
-            return Err(crate::Error::CreateInstance);
-        }
+        let backend_instance =
+            backend.create(window_label, &initial_options, &observed_properties, events)?;
 
         info!("mpv instance initialized for window '{}'.", window_label);
 
         let instance = MpvInstance {
-            handle: mpv_handle,
-            event_userdata: event_userdata,
+            backend: backend_instance,
+            close_policy: mpv_config.close_policy.unwrap_or(self.config.close_policy),
+            config_dir,
+            queue,
+            resume,
         };
 
         instances_lock.insert(window_label.to_string(), instance);
+        drop(instances_lock);
 
-        info!("Wid mode initialized for window '{}'.", window_label);
+        info!(
+            "{:?} render mode initialized for window '{}'.",
+            mpv_config.render_mode, window_label
+        );
+
+        if let Some(on_instance_created) = &self.hooks.on_instance_created {
+            on_instance_created(&self.app, window_label);
+        }
 
         Ok(window_label.to_string())
     }
 
+    pub fn exit_timeout(&self) -> Duration {
+        self.config
+            .exit_timeout
+            .map(Duration::from_millis)
+            .unwrap_or(EXIT_TIMEOUT)
+    }
+
     pub fn destroy(&self, window_label: &str) -> Result<()> {
         if let Some(instance) = self.remove_instance(window_label)? {
-            let wrapper = self.get_wrapper()?;
-
-            unsafe {
-                wrapper.mpv_wrapper_destroy(instance.handle);
-            }
-
-            let _ = unsafe { Box::from_raw(instance.event_userdata as *mut EventUserData<R>) };
+            let result = self.shutdown_instance(instance, window_label);
+            self.report(window_label, result)?;
 
             info!(
                 "mpv instance for window '{}' has been destroyed.",
@@ -186,6 +296,84 @@ impl<R: Runtime> Mpv<R> {
         Ok(())
     }
 
+    /// Destroys every running instance, waiting at most `timeout` for all of them.
+    ///
+    /// Returns the labels of the instances that failed to stop in time.
+    pub fn destroy_all(&self, timeout: Duration) -> Vec<String> {
+        self.thumbnails.shutdown();
+        self.storyboards.shutdown();
+        self.probes.shutdown();
+
+        let instances: Vec<(String, MpvInstance)> = {
+            let mut instances_lock = match self.instances.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    warn!("Mutex was poisoned, recovering.");
+                    poisoned.into_inner()
+                }
+            };
+            instances_lock.drain().collect()
+        };
+
+        if instances.is_empty() {
+            return Vec::new();
+        }
+
+        info!("Destroying {} mpv instance(s)...", instances.len());
+
+        let (tx, rx) = mpsc::channel();
+        let mut pending: HashSet<String> = HashSet::new();
+        let mut failed: Vec<String> = Vec::new();
+
+        for (window_label, instance) in instances {
+            let app = self.app.clone();
+            let tx = tx.clone();
+            pending.insert(window_label.clone());
+
+            let spawn_result = std::thread::Builder::new()
+                .name(format!("mpv-destroy-{}", window_label))
+                .spawn(move || {
+                    let result = app.mpv().shutdown_instance(instance, &window_label);
+                    let _ = tx.send((window_label, result));
+                });
+
+            if let Err(e) = spawn_result {
+                error!("Failed to spawn mpv teardown thread: {}", e);
+            }
+        }
+        drop(tx);
+
+        let deadline = Instant::now() + timeout;
+        while !pending.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(remaining) {
+                Ok((window_label, result)) => {
+                    pending.remove(&window_label);
+                    match result {
+                        Ok(()) => info!(
+                            "mpv instance for window '{}' has been destroyed.",
+                            window_label
+                        ),
+                        Err(e) => {
+                            error!("Failed to destroy mpv for '{}': {}", window_label, e);
+                            failed.push(window_label);
+                        }
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+
+        for window_label in pending {
+            error!(
+                "mpv instance for window '{}' did not stop within {:?}.",
+                window_label, timeout
+            );
+            failed.push(window_label);
+        }
+        failed
+    }
+
     pub fn command(
         &self,
         name: &str,
@@ -198,38 +386,12 @@ impl<R: Runtime> Mpv<R> {
             trace!("COMMAND '{}' '{:?}'", name, args);
         }
 
-        self.with_instance(window_label, |instance| {
-            let wrapper = self.get_wrapper()?;
-
-            let args_string = serde_json::to_string(&args)?;
-
-            let c_name = CString::new(name)?;
-            let c_args = CString::new(args_string)?;
-
-            let result_ptr = unsafe {
-                wrapper.mpv_wrapper_command(instance.handle, c_name.as_ptr(), c_args.as_ptr())
-            };
-
-            if result_ptr.is_null() {
-                return Err(crate::Error::FFI("Call returned null pointer".into()));
-            }
-
-            defer! {
-                unsafe { wrapper.mpv_wrapper_free(result_ptr) };
-            }
-
-            let response_str = unsafe { CStr::from_ptr(result_ptr).to_string_lossy() };
-            let response: FfiResponse = serde_json::from_str(&response_str)?;
+        let result = self.with_instance(window_label, |instance| {
+            let args = self.resolve_path_args(name, args)?;
+            instance.backend.command(name, &args).map(|_| ())
+        });
 
-            if let Some(err) = response.error {
-                Err(crate::Error::Command {
-                    window_label: window_label.to_string(),
-                    message: err,
-                })
-            } else {
-                Ok(())
-            }
-        })
+        self.report(window_label, result)
     }
 
     pub fn set_property(
@@ -240,38 +402,11 @@ impl<R: Runtime> Mpv<R> {
     ) -> crate::Result<()> {
         trace!("SET PROPERTY '{}' '{:?}'", name, value);
 
-        self.with_instance(window_label, |instance| {
-            let wrapper = self.get_wrapper()?;
-
-            let value_string = serde_json::to_string(value)?;
-
-            let c_name = CString::new(name)?;
-            let c_value = CString::new(value_string)?;
-
-            let result_ptr = unsafe {
-                wrapper.mpv_wrapper_set_property(instance.handle, c_name.as_ptr(), c_value.as_ptr())
-            };
-
-            if result_ptr.is_null() {
-                return Err(crate::Error::FFI("Call returned null pointer".into()));
-            }
-
-            defer! {
-                unsafe { wrapper.mpv_wrapper_free(result_ptr) };
-            }
-
-            let response_str = unsafe { CStr::from_ptr(result_ptr).to_string_lossy() };
-            let response: FfiResponse = serde_json::from_str(&response_str)?;
+        let result = self.with_instance(window_label, |instance| {
+            instance.backend.set_property(name, value)
+        });
 
-            if let Some(err) = response.error {
-                Err(crate::Error::SetProperty {
-                    window_label: window_label.to_string(),
-                    message: err,
-                })
-            } else {
-                Ok(())
-            }
-        })
+        self.report(window_label, result)
     }
 
     pub fn get_property(
@@ -280,51 +415,65 @@ impl<R: Runtime> Mpv<R> {
         format: String,
         window_label: &str,
     ) -> crate::Result<serde_json::Value> {
-        self.with_instance(window_label, |instance| {
-            let wrapper = self.get_wrapper()?;
-
-            let c_name = CString::new(name.clone())?;
-            let c_format = CString::new(format.as_str())?;
-
-            let result_ptr = unsafe {
-                wrapper.mpv_wrapper_get_property(
-                    instance.handle,
-                    c_name.as_ptr(),
-                    c_format.as_ptr(),
-                )
-            };
+        let result = self.with_instance(window_label, |instance| {
+            let value = instance.backend.get_property(&name, &format)?;
 
-            defer! {
-                unsafe { wrapper.mpv_wrapper_free(result_ptr) };
-            }
+            trace!("GET PROPERTY '{}' '{:?}'", name, value);
+            Ok(value)
+        });
 
-            let response_str = unsafe {
-                if result_ptr.is_null() {
-                    return Err(crate::Error::GetProperty {
-                        window_label: window_label.to_string(),
-                        message: "FFI call returned null pointer".into(),
-                    });
-                }
-                CStr::from_ptr(result_ptr).to_string_lossy()
-            };
+        self.report(window_label, result)
+    }
 
-            let response: FfiResponse = serde_json::from_str(&response_str)?;
+    pub fn get_profiles(&self, window_label: &str) -> Result<Vec<Profile>> {
+        let value =
+            self.get_property("profile-list".to_string(), "node".to_string(), window_label)?;
+        let profiles = serde_json::from_value(value).map_err(|e| Error::GetProperty {
+            window_label: window_label.to_string(),
+            message: format!("Unexpected profile-list: {}", e),
+        });
+        self.report(window_label, profiles)
+    }
 
-            if let Some(err) = response.error {
-                return Err(crate::Error::GetProperty {
-                    window_label: window_label.to_string(),
-                    message: err,
-                });
-            }
+    pub fn apply_profile(&self, name: &str, window_label: &str) -> Result<()> {
+        self.command(
+            "apply-profile",
+            &vec![serde_json::json!(name)],
+            window_label,
+        )
+    }
+
+    /// Loads `mpv.conf` and `input.conf` from the instance's config directory again.
+    pub fn reload_config(&self, window_label: &str) -> Result<()> {
+        let config_dir =
+            self.with_instance(window_label, |instance| Ok(instance.config_dir.clone()))?;
+        let Some(config_dir) = config_dir else {
+            return self.report(
+                window_label,
+                Err(Error::NoConfigDir(window_label.to_string())),
+            );
+        };
 
-            let value = response.data.ok_or_else(|| crate::Error::GetProperty {
-                window_label: window_label.to_string(),
-                message: "FFI response contained no data".to_string(),
-            })?;
+        let mpv_conf = config_dir.join("mpv.conf");
+        if mpv_conf.is_file() {
+            self.command(
+                "load-config-file",
+                &vec![serde_json::json!(mpv_conf.to_string_lossy())],
+                window_label,
+            )?;
+        }
 
-            trace!("GET PROPERTY '{}' '{:?}'", name, value);
-            Ok(value)
-        })
+        let input_conf = config_dir.join("input.conf");
+        if input_conf.is_file() {
+            self.command(
+                "load-input-conf",
+                &vec![serde_json::json!(input_conf.to_string_lossy())],
+                window_label,
+            )?;
+        }
+
+        info!("Reloaded mpv config for window '{}'.", window_label);
+        Ok(())
     }
 
     pub fn set_video_margin_ratio(
@@ -349,6 +498,561 @@ impl<R: Runtime> Mpv<R> {
         Ok(())
     }
 
+    /// The video, audio and subtitle tracks of the playing file.
+    pub fn list_tracks(&self, window_label: &str) -> Result<Vec<Track>> {
+        let value =
+            self.get_property("track-list".to_string(), "node".to_string(), window_label)?;
+        Ok(media::parse_tracks(&value))
+    }
+
+    /// Selects track `id` of `kind`, or disables the kind if `id` is `None`.
+    pub fn select_track(&self, kind: TrackKind, id: Option<i64>, window_label: &str) -> Result<()> {
+        let value = match id {
+            Some(id) => serde_json::json!(id),
+            None => serde_json::json!("no"),
+        };
+        self.set_property(kind.property(), &value, window_label)
+    }
+
+    /// Loads `path` as an external track of `kind` with `sub-add`, `audio-add` or `video-add`.
+    pub fn add_external_track(
+        &self,
+        kind: TrackKind,
+        path: &str,
+        options: &ExternalTrackOptions,
+        window_label: &str,
+    ) -> Result<()> {
+        let flag = if options.select { "select" } else { "auto" };
+        let mut args = vec![serde_json::json!(path), serde_json::json!(flag)];
+        if options.title.is_some() || options.lang.is_some() {
+            args.push(serde_json::json!(options
+                .title
+                .as_deref()
+                .unwrap_or_default()));
+        }
+        if let Some(lang) = &options.lang {
+            args.push(serde_json::json!(lang));
+        }
+        self.command(kind.add_command(), &args, window_label)
+    }
+
+    /// Removes the external track `id` of `kind`.
+    pub fn remove_track(&self, kind: TrackKind, id: i64, window_label: &str) -> Result<()> {
+        self.command(
+            kind.remove_command(),
+            &vec![serde_json::json!(id)],
+            window_label,
+        )
+    }
+
+    /// The editions of the playing file, empty for files without any.
+    pub fn list_editions(&self, window_label: &str) -> Result<Vec<Edition>> {
+        let value =
+            self.get_property("edition-list".to_string(), "node".to_string(), window_label)?;
+        // Files without editions have no current one.
+        let current = self
+            .with_instance(window_label, |instance| {
+                Ok(instance
+                    .backend
+                    .get_property("current-edition", "int64")
+                    .ok())
+            })?
+            .and_then(|current| current.as_i64());
+        Ok(media::parse_editions(&value, current))
+    }
+
+    /// Switches to edition `id`, which reloads the file.
+    pub fn select_edition(&self, id: i64, window_label: &str) -> Result<()> {
+        self.set_property("edition", &serde_json::json!(id), window_label)
+    }
+
+    /// The chapters of the playing file, each ending where the next starts.
+    pub fn list_chapters(&self, window_label: &str) -> Result<Vec<Chapter>> {
+        let value =
+            self.get_property("chapter-list".to_string(), "node".to_string(), window_label)?;
+        // Unknown for some streams, leaving the last chapter open.
+        let duration = self
+            .with_instance(window_label, |instance| {
+                Ok(instance.backend.get_property("duration", "double").ok())
+            })?
+            .and_then(|duration| duration.as_f64());
+        Ok(media::parse_chapters(&value, duration))
+    }
+
+    /// Seeks to the start of the next chapter.
+    pub fn next_chapter(&self, window_label: &str) -> Result<()> {
+        self.command(
+            "add",
+            &vec![serde_json::json!("chapter"), serde_json::json!(1)],
+            window_label,
+        )
+    }
+
+    /// Seeks to the start of the previous chapter.
+    pub fn prev_chapter(&self, window_label: &str) -> Result<()> {
+        self.command(
+            "add",
+            &vec![serde_json::json!("chapter"), serde_json::json!(-1)],
+            window_label,
+        )
+    }
+
+    /// Seeks to the start of the chapter at `index`.
+    pub fn seek_chapter(&self, index: usize, window_label: &str) -> Result<()> {
+        self.set_property("chapter", &serde_json::json!(index), window_label)
+    }
+
+    /// The playlist of the instance of `window_label`.
+    pub fn playlist(&self, window_label: &str) -> PlaylistHandle<'_, R> {
+        PlaylistHandle::new(self, window_label)
+    }
+
+    pub(crate) fn queue(&self, window_label: &str) -> Result<Arc<Queue>> {
+        self.with_instance(window_label, |instance| Ok(instance.queue.clone()))
+    }
+
+    /// What the loaded backend supports beyond the required calls. Loads the backend if needed.
+    pub fn capabilities(&self) -> Result<BackendCapabilities> {
+        Ok(self.get_backend()?.capabilities())
+    }
+
+    /// Starts a command without waiting for it. The result arrives as a `command-reply` event.
+    pub fn command_async(
+        &self,
+        reply_id: u64,
+        name: &str,
+        args: &[serde_json::Value],
+        window_label: &str,
+    ) -> Result<()> {
+        trace!("COMMAND ASYNC '{}' '{:?}' ({})", name, args, reply_id);
+        let result = self.with_capability(window_label, Capability::AsyncCommands, |instance| {
+            let args = self.resolve_path_args(name, args)?;
+            instance.backend.command_async(reply_id, name, &args)
+        });
+        self.report(window_label, result)
+    }
+
+    pub fn observe_property(
+        &self,
+        reply_id: u64,
+        name: &str,
+        format: &str,
+        window_label: &str,
+    ) -> Result<()> {
+        trace!("OBSERVE PROPERTY '{}' '{}' ({})", name, format, reply_id);
+        let result =
+            self.with_capability(window_label, Capability::ObserveProperties, |instance| {
+                instance.backend.observe_property(reply_id, name, format)
+            });
+        self.report(window_label, result)
+    }
+
+    pub fn unobserve_property(&self, reply_id: u64, window_label: &str) -> Result<()> {
+        trace!("UNOBSERVE PROPERTY ({})", reply_id);
+        let result =
+            self.with_capability(window_label, Capability::ObserveProperties, |instance| {
+                instance.backend.unobserve_property(reply_id)
+            });
+        self.report(window_label, result)
+    }
+
+    pub fn hook_add(
+        &self,
+        reply_id: u64,
+        name: &str,
+        priority: i32,
+        window_label: &str,
+    ) -> Result<()> {
+        trace!("HOOK ADD '{}' {} ({})", name, priority, reply_id);
+        let result = self.with_capability(window_label, Capability::Hooks, |instance| {
+            instance.backend.hook_add(reply_id, name, priority)
+        });
+        self.report(window_label, result)
+    }
+
+    pub fn hook_continue(&self, hook_id: u64, window_label: &str) -> Result<()> {
+        trace!("HOOK CONTINUE ({})", hook_id);
+        let result = self.with_capability(window_label, Capability::Hooks, |instance| {
+            instance.backend.hook_continue(hook_id)
+        });
+        self.report(window_label, result)
+    }
+
+    /// Starts delivering software-rendered frames of `width` x `height` pixels to `frames`.
+    pub fn start_render(
+        &self,
+        width: u32,
+        height: u32,
+        frames: FrameSink,
+        window_label: &str,
+    ) -> Result<()> {
+        trace!("START RENDER {}x{}", width, height);
+        let result = self.with_capability(window_label, Capability::RenderApi, |instance| {
+            instance.backend.start_render(width, height, frames)
+        });
+        self.report(window_label, result)
+    }
+
+    pub fn resize_render(&self, width: u32, height: u32, window_label: &str) -> Result<()> {
+        trace!("RESIZE RENDER {}x{}", width, height);
+        let result = self.with_capability(window_label, Capability::RenderApi, |instance| {
+            instance.backend.resize_render(width, height)
+        });
+        self.report(window_label, result)
+    }
+
+    /// Takes a screenshot and returns it encoded as `options.format`.
+    pub fn screenshot(&self, options: &ScreenshotOptions, window_label: &str) -> Result<Vec<u8>> {
+        let path = screenshot::temp_path(options.format.extension());
+        self.write_screenshot(options, &path, window_label)?;
+
+        let image = std::fs::read(&path).map_err(|e| {
+            Error::Screenshot(format!("mpv wrote no image to '{}': {}", path.display(), e))
+        });
+        let _ = std::fs::remove_file(&path);
+        self.report(window_label, image)
+    }
+
+    /// A JPEG of the frame at `time` seconds into `path`, `width` pixels wide.
+    ///
+    /// Rendered by a hidden instance and cached, so `window_label` only needs to name the
+    /// window asking. A newer request for the same window and path cancels this one if it
+    /// is still waiting for an instance.
+    pub fn thumbnail(
+        &self,
+        path: &str,
+        time: f64,
+        width: u32,
+        window_label: &str,
+    ) -> Result<Arc<Vec<u8>>> {
+        let result = (|| {
+            let backend = self.get_backend()?;
+            let path = self.resolve_path(path)?;
+            let options = self.headless_options()?;
+            self.thumbnails
+                .get(backend.as_ref(), &options, &path, time, width, window_label)
+        })();
+
+        match result {
+            // Superseded requests are expected while scrubbing.
+            Err(Error::Cancelled(_)) => result,
+            result => self.report(window_label, result),
+        }
+    }
+
+    /// Returns the storyboard of the local file `path`, generating and caching it if needed.
+    ///
+    /// Sends `storyboard-progress` to `window_label` while generating. Fails with
+    /// [`Error::Cancelled`] if [`Self::cancel_storyboard`] is called meanwhile.
+    pub fn generate_storyboard(
+        &self,
+        path: &str,
+        options: &StoryboardOptions,
+        window_label: &str,
+    ) -> Result<Storyboard> {
+        let result = (|| {
+            let backend = self.get_backend()?;
+            let resolved_path = self.resolve_path(path)?;
+            let app_cache_dir = self.app.path().app_cache_dir()?;
+            // Joining an absolute path replaces `app_cache_dir`.
+            let cache_dir = match &self.config.storyboard_dir {
+                Some(storyboard_dir) => app_cache_dir.join(storyboard_dir),
+                None => app_cache_dir.join("storyboards"),
+            };
+            std::fs::create_dir_all(&cache_dir)?;
+
+            self.storyboards.generate(
+                backend.as_ref(),
+                &self.headless_options()?,
+                &resolved_path,
+                options,
+                &cache_dir,
+                window_label,
+                Duration::from_millis(self.config.thumbnails.timeout),
+                &|done, total| {
+                    dispatch_event(
+                        self.app.clone(),
+                        window_label.to_string(),
+                        serde_json::json!({
+                            "event": "storyboard-progress",
+                            "path": path,
+                            "done": done,
+                            "total": total,
+                        }),
+                    )
+                },
+            )
+        })();
+
+        match result {
+            Err(Error::Cancelled(_)) => result,
+            result => self.report(window_label, result),
+        }
+    }
+
+    /// Cancels generating the storyboard of `path` for `window_label`.
+    ///
+    /// Returns `false` if no such storyboard is being generated.
+    pub fn cancel_storyboard(&self, path: &str, window_label: &str) -> Result<bool> {
+        let path = self.report(window_label, self.resolve_path(path))?;
+        Ok(self.storyboards.cancel(window_label, &path))
+    }
+
+    /// Reads duration, tracks, chapters, tags and container of `path` without playing it.
+    ///
+    /// The file is opened by a hidden instance. `window_label` only names the window asking.
+    pub fn probe(&self, path: &str, window_label: &str) -> Result<MediaInfo> {
+        let result = (|| {
+            let backend = self.get_backend()?;
+            let path = self.resolve_path(path)?;
+            let mut options = self.headless_options()?;
+            // Only the demuxer is needed.
+            options.insert("vid".to_string(), serde_json::json!("no"));
+            options.insert("aid".to_string(), serde_json::json!("no"));
+            self.probes.probe(backend.as_ref(), &options, &path)
+        })();
+        self.report(window_label, result)
+    }
+
+    /// Where playback of `path` stopped last, unless it was played to its end.
+    ///
+    /// Only known for files played while `plugins.libmpv.resume.enabled` is set.
+    /// `window_label` only names the window asking.
+    pub fn resume_point(&self, path: &str, window_label: &str) -> Result<Option<ResumePoint>> {
+        let result = (|| {
+            let path = self.resolve_path(path)?;
+            Ok(self.resume_store()?.get(&path))
+        })();
+        self.report(window_label, result)
+    }
+
+    /// All saved resume positions, most recently played first.
+    pub fn resume_points(&self, window_label: &str) -> Result<Vec<ResumePoint>> {
+        let result = self.resume_store().map(ResumeStore::list);
+        self.report(window_label, result)
+    }
+
+    /// Forgets where playback of `path` stopped, returning whether it was known.
+    pub fn clear_resume_point(&self, path: &str, window_label: &str) -> Result<bool> {
+        let result = (|| {
+            let path = self.resolve_path(path)?;
+            // A position of the path still waiting to be written would bring it back.
+            if let Some(writer) = self.resume_writer.get() {
+                writer.flush();
+            }
+            self.resume_store()?.remove(&path)
+        })();
+        self.report(window_label, result)
+    }
+
+    /// Saves a screenshot into the screenshot directory and sends `screenshot-saved`.
+    pub fn save_screenshot(
+        &self,
+        options: &ScreenshotOptions,
+        window_label: &str,
+    ) -> Result<PathBuf> {
+        let path = self.report(window_label, self.screenshot_path(options, window_label))?;
+        self.write_screenshot(options, &path, window_label)?;
+
+        if !path.is_file() {
+            return self.report(
+                window_label,
+                Err(Error::Screenshot(format!(
+                    "mpv wrote no image to '{}'",
+                    path.display()
+                ))),
+            );
+        }
+
+        info!("Saved screenshot '{}'.", path.display());
+        dispatch_event(
+            self.app.clone(),
+            window_label.to_string(),
+            serde_json::json!({ "event": "screenshot-saved", "path": path }),
+        );
+        Ok(path)
+    }
+
+    fn write_screenshot(
+        &self,
+        options: &ScreenshotOptions,
+        path: &Path,
+        window_label: &str,
+    ) -> Result<()> {
+        self.command(
+            "screenshot-to-file",
+            &vec![
+                serde_json::json!(path.to_string_lossy()),
+                serde_json::json!(options.mode.flag()),
+            ],
+            window_label,
+        )
+    }
+
+    /// A free path in the screenshot directory, named after the template.
+    fn screenshot_path(&self, options: &ScreenshotOptions, window_label: &str) -> Result<PathBuf> {
+        let app_data_dir = self.app.path().app_data_dir()?;
+        // Joining an absolute path replaces `app_data_dir`.
+        let dir = match &self.config.screenshot_dir {
+            Some(screenshot_dir) => app_data_dir.join(screenshot_dir),
+            None => app_data_dir.join("screenshots"),
+        };
+        std::fs::create_dir_all(&dir)?;
+
+        // The file name and position are optional, so their errors are not reported.
+        let (name, pos) = self.with_instance(window_label, |instance| {
+            let name = instance
+                .backend
+                .get_property("filename/no-ext", "string")
+                .ok()
+                .and_then(|name| name.as_str().map(str::to_string))
+                .unwrap_or_else(|| "screenshot".to_string());
+            let pos = instance
+                .backend
+                .get_property("time-pos", "double")
+                .ok()
+                .and_then(|pos| pos.as_f64())
+                .unwrap_or_default();
+            Ok((name, pos))
+        })?;
+
+        let template = options
+            .template
+            .as_deref()
+            .or(self.config.screenshot_template.as_deref())
+            .unwrap_or(DEFAULT_SCREENSHOT_TEMPLATE);
+        let values = TemplateValues {
+            name: &name,
+            pos,
+            window: window_label,
+            unix: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        };
+        let file_name =
+            screenshot::expand_template(template, &values).map_err(Error::Screenshot)?;
+
+        Ok(screenshot::unique_path(
+            &dir,
+            &file_name,
+            options.format.extension(),
+        ))
+    }
+
+    fn shutdown_instance(&self, instance: MpvInstance, window_label: &str) -> Result<()> {
+        if let Some(resume) = &instance.resume {
+            resume.finish();
+        }
+
+        if instance.close_policy == ClosePolicy::QuitWatchLater {
+            trace!("COMMAND 'quit-watch-later' for window '{}'", window_label);
+            if let Err(e) = instance.backend.command("quit-watch-later", &[]) {
+                warn!("{}", e);
+            }
+        }
+
+        instance.backend.destroy()
+    }
+
+    fn resolve_config_dir(&self, config_dir: &ConfigDir) -> Result<PathBuf> {
+        let path = match config_dir {
+            ConfigDir::AppConfig => self.app.path().app_config_dir()?.join("mpv"),
+            ConfigDir::Resource => self.app.path().resource_dir()?.join("mpv"),
+            ConfigDir::Path(path) => path.clone(),
+        };
+        Ok(path)
+    }
+
+    /// The resume position store, in `resume.json` of the app data directory unless configured.
+    fn resume_store(&self) -> Result<&ResumeStore> {
+        self.resume.get_or_try_init(|| {
+            // Joining an absolute path replaces the app data directory.
+            let file = self.app.path().app_data_dir()?.join(
+                self.config
+                    .resume
+                    .file
+                    .as_deref()
+                    .unwrap_or(Path::new("resume.json")),
+            );
+            Ok(ResumeStore::new(file, self.config.resume.max_entries))
+        })
+    }
+
+    /// The thread writing the resume positions of all instances, started on first use.
+    fn resume_writer(&self) -> Result<Arc<ResumeWriter>> {
+        self.resume_writer
+            .get_or_try_init(|| {
+                let app = self.app.clone();
+                let writer = ResumeWriter::spawn(move |update| {
+                    let result = app
+                        .mpv()
+                        .resume_store()
+                        .and_then(|store| store.apply(update));
+                    if let Err(e) = result {
+                        error!("Failed to save the resume position: {}", e);
+                    }
+                })?;
+                Ok(Arc::new(writer))
+            })
+            .cloned()
+    }
+
+    /// Looks up a preset registered through the builder, then the built-in ones.
+    fn preset_options(&self, name: &str) -> Result<IndexMap<String, serde_json::Value>> {
+        if let Some(options) = self.hooks.presets.get(name) {
+            return Ok(options.clone());
+        }
+        crate::presets::builtin_preset(name).ok_or_else(|| Error::UnknownPreset(name.to_string()))
+    }
+
+    /// Options of the hidden instances: the `thumbnailer` preset, keeping files open at the end.
+    fn headless_options(&self) -> Result<IndexMap<String, serde_json::Value>> {
+        let mut options = self.preset_options("thumbnailer")?;
+        // Seeks near the end must still find a frame.
+        options.insert("keep-open".to_string(), serde_json::json!("always"));
+        Ok(options)
+    }
+
+    /// Runs `path` through the registered path resolver, if any.
+    fn resolve_path(&self, path: &str) -> Result<String> {
+        let args = [serde_json::json!(path)];
+        let args = self.resolve_path_args("loadfile", &args)?;
+        Ok(args[0].as_str().unwrap_or(path).to_string())
+    }
+
+    /// Passes a failed result to the `on_error` hook before returning it.
+    fn report<T>(&self, window_label: &str, result: Result<T>) -> Result<T> {
+        if let (Err(e), Some(on_error)) = (&result, &self.hooks.on_error) {
+            on_error(&self.app, window_label, e);
+        }
+        result
+    }
+
+    /// Runs the first argument of path-taking commands through the registered path resolver.
+    fn resolve_path_args<'a>(
+        &self,
+        name: &str,
+        args: &'a [serde_json::Value],
+    ) -> Result<Cow<'a, [serde_json::Value]>> {
+        let Some(path_resolver) = &self.hooks.path_resolver else {
+            return Ok(Cow::Borrowed(args));
+        };
+
+        if !PATH_COMMANDS.contains(&name) {
+            return Ok(Cow::Borrowed(args));
+        }
+
+        let Some(path) = args.first().and_then(|arg| arg.as_str()) else {
+            return Ok(Cow::Borrowed(args));
+        };
+
+        let mut resolved_args = args.to_vec();
+        resolved_args[0] = serde_json::Value::String(path_resolver(&self.app, path)?);
+        trace!("Resolved path '{}' to {}", path, resolved_args[0]);
+        Ok(Cow::Owned(resolved_args))
+    }
+
     fn lock_and_check_existence<'a>(
         &'a self,
         window_label: &str,
@@ -391,6 +1095,20 @@ impl<R: Runtime> Mpv<R> {
         operation(instance)
     }
 
+    /// Like [`Self::with_instance`], failing with [`Error::UnsupportedFeature`] first if needed.
+    fn with_capability<F, T>(
+        &self,
+        window_label: &str,
+        capability: Capability,
+        operation: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(&MpvInstance) -> Result<T>,
+    {
+        self.get_backend()?.capabilities().require(capability)?;
+        self.with_instance(window_label, operation)
+    }
+
     fn remove_instance(&self, window_label: &str) -> Result<Option<MpvInstance>> {
         let mut instances_lock = match self.instances.lock() {
             Ok(guard) => guard,
@@ -402,46 +1120,124 @@ impl<R: Runtime> Mpv<R> {
         Ok(instances_lock.remove(window_label))
     }
 
-    fn get_wrapper(&self) -> Result<&LibmpvWrapper> {
-        self.wrapper.get_or_try_init(|| {
-            info!("libmpv-wrapper not initialized. Trying to load libmpv-wrapper now...");
+    /// Returns the backend registered with the builder, or the one `Config::backend` selects.
+    fn get_backend(&self) -> Result<&Arc<dyn MpvBackend>> {
+        self.backend.get_or_try_init(|| {
+            if let Some(backend) = &self.hooks.backend {
+                info!("Using the backend registered with the builder.");
+                return Ok(backend.clone());
+            }
 
-            #[cfg(target_os = "windows")]
-            let lib_name = "libmpv-wrapper.dll";
-            #[cfg(target_os = "macos")]
-            let lib_name = "libmpv-wrapper.dylib";
-            #[cfg(target_os = "linux")]
-            let lib_name = "libmpv-wrapper.so";
-
-            let mut search_dirs: Vec<PathBuf> = Vec::new();
-            if let Ok(exe_path) = std::env::current_exe() {
-                if let Some(exe_dir) = exe_path.parent() {
-                    search_dirs.push(exe_dir.to_path_buf());
-                    search_dirs.push(exe_dir.join("lib"));
+            match self.config.backend {
+                BackendKind::Wrapper => self.load_wrapper_backend(),
+                BackendKind::Process => self.load_process_backend(),
+                #[cfg(feature = "libmpv")]
+                BackendKind::Libmpv => self.load_libmpv_backend(),
+                #[cfg(not(feature = "libmpv"))]
+                BackendKind::Libmpv => Err(Error::Config(
+                    "the `libmpv` backend requires the `libmpv` feature".to_string(),
+                )),
+                #[cfg(unix)]
+                BackendKind::Mpv => self.load_ipc_backend(),
+                #[cfg(not(unix))]
+                BackendKind::Mpv => Err(Error::UnsupportedPlatform(
+                    "the `mpv` backend requires Unix domain sockets".to_string(),
+                )),
+                BackendKind::Auto => self.load_auto_backend(),
+            }
+        })
+    }
+
+    /// Tries `libmpv-wrapper`, then libmpv, then the `mpv` executable, as far as available.
+    fn load_auto_backend(&self) -> Result<Arc<dyn MpvBackend>> {
+        type Loader<R> = fn(&Mpv<R>) -> Result<Arc<dyn MpvBackend>>;
+
+        let mut loaders: Vec<(&str, Loader<R>)> =
+            vec![("libmpv-wrapper", Self::load_wrapper_backend)];
+        #[cfg(feature = "libmpv")]
+        loaders.push(("libmpv", Self::load_libmpv_backend));
+        #[cfg(unix)]
+        loaders.push(("the mpv executable", Self::load_ipc_backend));
+
+        let mut errors = Vec::new();
+        for (index, (name, load)) in loaders.iter().enumerate() {
+            match load(self) {
+                Ok(backend) => return Ok(backend),
+                Err(e) => {
+                    if let Some((next, _)) = loaders.get(index + 1) {
+                        warn!("{} Falling back to {}.", e, next);
+                    }
+                    errors.push(format!("{}: {}", name, e));
                 }
             }
+        }
+        Err(Error::FFI(errors.join("\n")))
+    }
 
-            let valid_lib_path: String = search_dirs
-                .iter()
-                .map(|dir| dir.join(lib_name))
-                .find(|path| path.exists())
-                .map(|path| path.to_string_lossy().into_owned())
-                .unwrap_or_else(|| lib_name.to_string());
+    fn load_wrapper_backend(&self) -> Result<Arc<dyn MpvBackend>> {
+        let backend = if let Some(wrapper_loader) = &self.hooks.wrapper_loader {
+            info!("Loading libmpv-wrapper with the custom loader...");
+            WrapperBackend::new(wrapper_loader(&self.app)?)
+        } else {
+            info!("libmpv-wrapper not initialized. Trying to load libmpv-wrapper now...");
+            loader::load_wrapper(
+                &self.app,
+                &self.config.wrapper_path,
+                &self.hooks.wrapper_search_dirs,
+            )?
+        };
+        info!("libmpv-wrapper capabilities: {:?}", backend.capabilities());
+        Ok(Arc::new(backend))
+    }
 
-            info!("Attempting to load libmpv-wrapper from: {}", valid_lib_path);
-            let result = unsafe { LibmpvWrapper::new(&valid_lib_path) };
+    fn load_process_backend(&self) -> Result<Arc<dyn MpvBackend>> {
+        if self.hooks.wrapper_loader.is_some() {
+            return Err(Error::Config(
+                "`Builder::wrapper_loader` can't be used with the 'process' backend, whose hosts load `libmpv-wrapper` by path".to_string(),
+            ));
+        }
 
-            match result {
-                Ok(wrapper) => {
-                    info!("Successfully loaded libmpv-wrapper.");
-                    Ok(wrapper)
-                }
-                Err(e) => Err(Error::FFI(format!(
-                    "Failed to load libmpv-wrapper from '{}'. Error: {:?}",
-                    valid_lib_path, e
-                ))
-                .into()),
-            }
-        })
+        let wrapper_path = loader::locate_wrapper(
+            &self.app,
+            &self.config.wrapper_path,
+            &self.hooks.wrapper_search_dirs,
+        )?;
+
+        let program = match &self.config.host_path {
+            Some(host_path) => resolve_from_exe_dir(host_path),
+            None => std::env::current_exe()?,
+        };
+
+        info!(
+            "Running mpv instances in '{}' with '{}'.",
+            program.display(),
+            wrapper_path.display()
+        );
+        let backend =
+            unsafe { ProcessBackend::load(program, self.config.host_args.clone(), wrapper_path) }?;
+        Ok(Arc::new(backend))
+    }
+
+    #[cfg(unix)]
+    fn load_ipc_backend(&self) -> Result<Arc<dyn MpvBackend>> {
+        let program = match &self.config.mpv_path {
+            Some(mpv_path) => resolve_from_exe_dir(mpv_path),
+            None => PathBuf::from("mpv"),
+        };
+
+        let backend = IpcBackend::new(program)?;
+        info!("Driving the mpv executable over IPC: {}", backend.version());
+        Ok(Arc::new(backend))
+    }
+
+    #[cfg(feature = "libmpv")]
+    fn load_libmpv_backend(&self) -> Result<Arc<dyn MpvBackend>> {
+        info!("libmpv not initialized. Trying to load libmpv now...");
+        let lib = loader::load_libmpv(
+            &self.app,
+            &self.config.libmpv_path,
+            &self.hooks.libmpv_search_dirs,
+        )?;
+        Ok(Arc::new(LibmpvBackend::new(lib)))
     }
 }
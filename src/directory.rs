@@ -0,0 +1,258 @@
+//! Turning a directory into a naturally sorted list of media files.
+
+use log::warn;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::models::{MediaFile, MediaKind};
+use crate::Result;
+
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "3gp", "asf", "avi", "divx", "f4v", "flv", "m2ts", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg",
+    "mts", "mxf", "ogv", "rm", "rmvb", "ts", "vob", "webm", "wmv",
+];
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "aac", "ac3", "aif", "aiff", "alac", "ape", "dts", "flac", "m4a", "mka", "mp3", "mpc", "oga",
+    "ogg", "opus", "tta", "wav", "wma", "wv",
+];
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "avif", "bmp", "gif", "heic", "jpeg", "jpg", "jxl", "png", "tif", "tiff", "webp",
+];
+const SUBTITLE_EXTENSIONS: &[&str] = &[
+    "ass", "idx", "lrc", "smi", "srt", "ssa", "sub", "sup", "vtt",
+];
+const ARCHIVE_EXTENSIONS: &[&str] = &["7z", "cbr", "cbz", "rar", "tar", "zip"];
+
+/// Names of cover art and thumbnails media managers put next to media files.
+const ARTWORK_NAMES: &[&str] = &[
+    "albumart", "banner", "cover", "fanart", "folder", "front", "poster", "thumb",
+];
+
+/// The kinds loaded when no filter is given.
+pub(crate) const DEFAULT_KINDS: &[MediaKind] = &[MediaKind::Video, MediaKind::Audio];
+
+impl MediaKind {
+    /// The kind of `path` by its extension, if it is a media file.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let kinds = [
+            (MediaKind::Video, VIDEO_EXTENSIONS),
+            (MediaKind::Audio, AUDIO_EXTENSIONS),
+            (MediaKind::Image, IMAGE_EXTENSIONS),
+            (MediaKind::Subtitle, SUBTITLE_EXTENSIONS),
+            (MediaKind::Archive, ARCHIVE_EXTENSIONS),
+        ];
+        kinds
+            .into_iter()
+            .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+            .map(|(kind, _)| kind)
+    }
+}
+
+/// The media files of `kinds` in `dir`, naturally sorted by their path below it.
+///
+/// Subtitles named like a video or audio file are attached to it instead of listed.
+/// Hidden files, cover art and files of unknown types are skipped.
+pub(crate) fn scan_directory(
+    dir: &Path,
+    recursive: bool,
+    kinds: &[MediaKind],
+) -> Result<Vec<MediaFile>> {
+    let mut files = Vec::new();
+    collect(dir, recursive, &mut files)?;
+    files.sort_by(|a, b| {
+        natural_path_cmp(
+            a.strip_prefix(dir).unwrap_or(a),
+            b.strip_prefix(dir).unwrap_or(b),
+        )
+    });
+
+    // Subtitles by directory, so sidecars are only matched next to their media file.
+    let mut subtitles: HashMap<&Path, Vec<&Path>> = HashMap::new();
+    for file in &files {
+        if MediaKind::from_path(file) == Some(MediaKind::Subtitle) {
+            subtitles
+                .entry(file.parent().unwrap_or(dir))
+                .or_default()
+                .push(file);
+        }
+    }
+
+    let mut sidecars = Vec::new();
+    let mut media = Vec::new();
+    for file in &files {
+        let Some(kind) = MediaKind::from_path(file) else {
+            continue;
+        };
+        let matched = match kind {
+            MediaKind::Video | MediaKind::Audio => subtitles
+                .get(file.parent().unwrap_or(dir))
+                .map(|candidates| sidecars_of(file, candidates))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        sidecars.extend(matched.iter().map(|sidecar| sidecar.to_path_buf()));
+        let attached = attachable(&matched);
+        if kinds.contains(&kind) && !is_artwork(file, kind) {
+            media.push(MediaFile {
+                path: file.clone(),
+                kind,
+                subtitles: attached,
+            });
+        }
+    }
+
+    media.retain(|file| file.kind != MediaKind::Subtitle || !sidecars.contains(&file.path));
+    Ok(media)
+}
+
+/// Compares strings with runs of digits by their value, so "ep2" sorts before "ep10".
+/// Letters compare case-insensitively.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x_digits, x_rest) = split_digits(a);
+                let (y_digits, y_rest) = split_digits(b);
+                let (x_value, y_value) = (
+                    x_digits.trim_start_matches('0'),
+                    y_digits.trim_start_matches('0'),
+                );
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    // "01" after "1", so that different names never compare equal.
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                (a, b) = (x_rest, y_rest);
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x
+                    .to_lowercase()
+                    .cmp(y.to_lowercase())
+                    .then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+            }
+        }
+    }
+}
+
+/// Lists the files in `dir`, failing only if `dir` itself can't be read.
+///
+/// Subdirectories that can't be read are skipped, so one of them doesn't lose the rest.
+fn collect(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if is_hidden(&path) {
+            continue;
+        }
+        // Symlinked directories aren't followed, as they can form cycles.
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if recursive {
+                if let Err(e) = collect(&path, recursive, files) {
+                    warn!("Skipping directory '{}': {}", path.display(), e);
+                }
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The subtitles named like `media`, as in `movie.srt` or `movie.en.forced.ass` for `movie.mkv`.
+fn sidecars_of<'a>(media: &Path, subtitles: &[&'a Path]) -> Vec<&'a Path> {
+    let Some(stem) = media.file_stem().and_then(OsStr::to_str) else {
+        return Vec::new();
+    };
+    subtitles
+        .iter()
+        .copied()
+        .filter(|subtitle| {
+            subtitle
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .is_some_and(|name| {
+                    name == stem
+                        || name
+                            .strip_prefix(stem)
+                            .is_some_and(|rest| rest.starts_with('.'))
+                })
+        })
+        .collect()
+}
+
+/// The sidecars to load, without the `.sub` of a VobSub `.idx`, which mpv finds by itself.
+fn attachable(sidecars: &[&Path]) -> Vec<PathBuf> {
+    sidecars
+        .iter()
+        .filter(|sidecar| {
+            !has_extension(sidecar, "sub")
+                || !sidecars.iter().any(|other| {
+                    has_extension(other, "idx") && other.with_extension("sub") == **sidecar
+                })
+        })
+        .map(|sidecar| sidecar.to_path_buf())
+        .collect()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+fn is_artwork(path: &Path, kind: MediaKind) -> bool {
+    kind == MediaKind::Image
+        && path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+            .is_some_and(|stem| {
+                ARTWORK_NAMES.iter().any(|name| {
+                    stem.strip_prefix(name)
+                        .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+                })
+            })
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|value| value.eq_ignore_ascii_case(extension))
+}
+
+fn natural_path_cmp(a: &Path, b: &Path) -> Ordering {
+    let components = |path: &Path| -> Vec<String> {
+        path.components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+    a.iter()
+        .zip(&b)
+        .map(|(a, b)| natural_cmp(a, b))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+fn split_digits(value: &str) -> (&str, &str) {
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    value.split_at(end)
+}
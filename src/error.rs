@@ -1,4 +1,5 @@
-use serde::{ser::Serializer, Serialize};
+use serde::{ser::Serializer, Deserialize, Serialize};
+use std::fmt;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -13,14 +14,25 @@ pub enum Error {
     Tauri(#[from] tauri::Error),
     #[error("Unsupported platform {0}")]
     UnsupportedPlatform(String),
+    #[error("Unsupported feature: {0}")]
+    UnsupportedFeature(String),
     #[error("Not found window with label: '{0}'")]
     WindowNotFound(String),
     #[error("Failed to get window handle: {0}")]
     WindowHandle(#[from] raw_window_handle::HandleError),
     #[error("FFI error: {0}")]
     FFI(String),
-    #[error("Failed to create mpv instance")]
-    CreateInstance,
+    #[error("IPC error: {0}")]
+    Ipc(String),
+    #[error(
+        "Failed to create mpv instance for window '{window_label}'{}",
+        create_instance_details(.invalid_options, .log)
+    )]
+    CreateInstance {
+        window_label: String,
+        invalid_options: Vec<InvalidOption>,
+        log: Vec<String>,
+    },
     #[error("mpv instance not found: {0}")]
     InstanceNotFound(String),
     #[error(transparent)]
@@ -48,6 +60,53 @@ pub enum Error {
     InvalidPropertyValue { name: String, message: String },
     #[error("Failed to destroy mpv instance: {0}")]
     Destroy(String),
+    #[error("Invalid plugin configuration: {0}")]
+    Config(String),
+    #[error("Unknown preset: '{0}'")]
+    UnknownPreset(String),
+    #[error("No mpv config directory for window '{0}'")]
+    NoConfigDir(String),
+    #[error("Screenshot failed: {0}")]
+    Screenshot(String),
+    #[error("Storyboard failed: {0}")]
+    Storyboard(String),
+    #[error("Invalid playlist file: {0}")]
+    PlaylistFile(String),
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+    #[error("Timed out: {0}")]
+    Timeout(String),
+}
+
+/// An entry of `initial_options` that mpv rejected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvalidOption {
+    pub name: String,
+    pub value: serde_json::Value,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' = {}: {}", self.name, self.value, self.reason)
+    }
+}
+
+fn create_instance_details(invalid_options: &[InvalidOption], log: &[String]) -> String {
+    let mut details = String::new();
+    if !invalid_options.is_empty() {
+        details.push_str("\nInvalid options:");
+        for option in invalid_options {
+            details.push_str(&format!("\n  {}", option));
+        }
+    }
+    if !log.is_empty() {
+        details.push_str("\nmpv log:");
+        for line in log {
+            details.push_str(&format!("\n  {}", line));
+        }
+    }
+    details
 }
 
 impl Serialize for Error {
@@ -0,0 +1,358 @@
+//! Hidden mpv instances the plugin runs for its own work, like rendering thumbnails.
+//!
+//! They are created through the same backend as the players, but without a window, and
+//! their events go to the code using them instead of the frontend.
+
+use indexmap::IndexMap;
+use log::{info, warn};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::backend::{BackendInstance, MpvBackend};
+use crate::screenshot;
+use crate::{Error, Result};
+
+/// How often waiting for a free instance or an event checks whether the caller gave up.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A hidden instance and the events it sent.
+pub(crate) struct HeadlessInstance {
+    pub backend: Arc<dyn BackendInstance>,
+    pub label: String,
+    /// The file currently loaded.
+    pub loaded: Option<String>,
+    events: mpsc::Receiver<serde_json::Value>,
+    /// Set when a wait was given up, which leaves the events of its command to come.
+    interrupted: bool,
+}
+
+impl HeadlessInstance {
+    fn create(
+        backend: &dyn MpvBackend,
+        label: String,
+        options: &IndexMap<String, serde_json::Value>,
+    ) -> Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let instance = backend.create(
+            &label,
+            options,
+            &IndexMap::new(),
+            Arc::new(move |event| {
+                let _ = lock(&tx).send(event);
+            }),
+        )?;
+
+        Ok(Self {
+            backend: instance,
+            label,
+            loaded: None,
+            events,
+            interrupted: false,
+        })
+    }
+
+    /// Loads `path` after setting `file_options`, waiting until its first frame is decoded.
+    pub fn load(
+        &mut self,
+        path: &str,
+        file_options: &[(&str, serde_json::Value)],
+        timeout: Duration,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<()> {
+        self.loadfile(path, file_options, "playback-restart", timeout, cancelled)
+    }
+
+    /// Opens `path` without waiting for decoding, only until its properties are known.
+    pub fn open(&mut self, path: &str, timeout: Duration) -> Result<()> {
+        self.loadfile(path, &[], "file-loaded", timeout, &|| false)
+    }
+
+    /// Closes the loaded file, so it isn't kept open while the instance is idle.
+    pub fn close(&mut self) -> Result<()> {
+        self.loaded = None;
+        self.backend.command("stop", &[])?;
+        Ok(())
+    }
+
+    fn loadfile(
+        &mut self,
+        path: &str,
+        file_options: &[(&str, serde_json::Value)],
+        event: &str,
+        timeout: Duration,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<()> {
+        self.loaded = None;
+        for (name, value) in file_options {
+            self.backend.set_property(name, value)?;
+        }
+
+        self.clear_events();
+        self.backend
+            .command("loadfile", &[serde_json::json!(path)])?;
+        self.wait_for_event(event, timeout, cancelled)?;
+        self.loaded = Some(path.to_string());
+        Ok(())
+    }
+
+    /// Seeks to `time` exactly, waiting until the frame there is decoded.
+    pub fn seek(
+        &mut self,
+        time: f64,
+        timeout: Duration,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<()> {
+        self.clear_events();
+        self.backend.command(
+            "seek",
+            &[serde_json::json!(time), serde_json::json!("absolute+exact")],
+        )?;
+        self.wait_for_event("playback-restart", timeout, cancelled)?;
+        Ok(())
+    }
+
+    /// The current video frame, encoded by mpv in the format `extension` stands for.
+    pub fn capture(&self, extension: &str) -> Result<Vec<u8>> {
+        let path = screenshot::temp_path(extension);
+        self.backend.command(
+            "screenshot-to-file",
+            &[
+                serde_json::json!(path.to_string_lossy()),
+                serde_json::json!("video"),
+            ],
+        )?;
+        let image = std::fs::read(&path).map_err(|e| {
+            Error::Screenshot(format!("mpv wrote no image to '{}': {}", path.display(), e))
+        });
+        let _ = std::fs::remove_file(&path);
+        image
+    }
+
+    /// Waits for the next event called `name`, failing if the file can't be played.
+    ///
+    /// Fails with [`Error::Cancelled`] once `cancelled` is true. The instance is then
+    /// destroyed when it is released, as the events of the interrupted command may still come.
+    pub fn wait_for_event(
+        &mut self,
+        name: &str,
+        timeout: Duration,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<serde_json::Value> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let event = match self
+                .events
+                .recv_timeout(remaining.min(CANCEL_POLL_INTERVAL))
+            {
+                Ok(event) => event,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if cancelled() {
+                        self.interrupted = true;
+                        return Err(Error::Cancelled(format!(
+                            "'{}' gave up waiting for '{}'",
+                            self.label, name
+                        )));
+                    }
+                    if remaining.is_zero() {
+                        return Err(Error::Timeout(format!(
+                            "'{}' sent no '{}' within {:?}",
+                            self.label, name, timeout
+                        )));
+                    }
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err(Error::Ipc(format!("'{}' stopped", self.label)))
+                }
+            };
+
+            match event["event"].as_str().unwrap_or_default() {
+                event_name if event_name == name => return Ok(event),
+                "end-file" if event["reason"] == "error" => {
+                    return Err(Error::Command {
+                        window_label: self.label.clone(),
+                        message: format!("failed to open the file (mpv error {})", event["error"]),
+                    })
+                }
+                "shutdown" | "crashed" => {
+                    return Err(Error::Ipc(format!("'{}' stopped", self.label)))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Drops the events sent so far.
+    pub fn clear_events(&self) {
+        while self.events.try_recv().is_ok() {}
+    }
+}
+
+/// Up to `limit` hidden instances, reused between tasks.
+pub(crate) struct HeadlessPool {
+    name: &'static str,
+    limit: usize,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+#[derive(Default)]
+struct PoolState {
+    idle: Vec<HeadlessInstance>,
+    /// Instances that exist or are being created, idle or not.
+    count: usize,
+    next_id: u64,
+    closed: bool,
+}
+
+impl HeadlessPool {
+    pub fn new(name: &'static str, limit: usize) -> Self {
+        Self {
+            name,
+            limit: limit.max(1),
+            state: Mutex::default(),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Takes an idle instance, or creates one with `options` while fewer than the limit exist.
+    ///
+    /// Waits for an instance otherwise, failing with [`Error::Cancelled`] once `cancelled` is true.
+    pub fn acquire(
+        &self,
+        backend: &dyn MpvBackend,
+        options: &IndexMap<String, serde_json::Value>,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<PooledInstance<'_>> {
+        let label = {
+            let mut state = lock(&self.state);
+            loop {
+                if state.closed {
+                    return Err(Error::Cancelled(format!(
+                        "the {} pool is shut down",
+                        self.name
+                    )));
+                }
+                if cancelled() {
+                    return Err(Error::Cancelled(
+                        "superseded by a newer request".to_string(),
+                    ));
+                }
+                if let Some(instance) = state.idle.pop() {
+                    return Ok(PooledInstance::new(self, instance));
+                }
+                if state.count < self.limit {
+                    state.count += 1;
+                    state.next_id += 1;
+                    break format!("{}-{}", self.name, state.next_id);
+                }
+                state = match self.available.wait_timeout(state, CANCEL_POLL_INTERVAL) {
+                    Ok((guard, _)) => guard,
+                    Err(poisoned) => poisoned.into_inner().0,
+                };
+            }
+        };
+
+        match HeadlessInstance::create(backend, label, options) {
+            Ok(instance) => {
+                info!("Created headless mpv instance '{}'.", instance.label);
+                Ok(PooledInstance::new(self, instance))
+            }
+            Err(e) => {
+                lock(&self.state).count -= 1;
+                self.available.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    /// Destroys the idle instances. Busy ones are destroyed when they are released.
+    pub fn shutdown(&self) {
+        let idle = {
+            let mut state = lock(&self.state);
+            state.closed = true;
+            state.count -= state.idle.len();
+            std::mem::take(&mut state.idle)
+        };
+        self.available.notify_all();
+
+        for instance in idle {
+            destroy(instance);
+        }
+    }
+
+    fn release(&self, instance: HeadlessInstance, discard: bool) {
+        let mut state = lock(&self.state);
+        if discard || instance.interrupted || state.closed {
+            state.count -= 1;
+            drop(state);
+            destroy(instance);
+        } else {
+            state.idle.push(instance);
+            drop(state);
+        }
+        self.available.notify_one();
+    }
+}
+
+/// An instance taken from a [`HeadlessPool`], returned to it when dropped.
+pub(crate) struct PooledInstance<'a> {
+    pool: &'a HeadlessPool,
+    instance: Option<HeadlessInstance>,
+    discard: bool,
+}
+
+impl<'a> PooledInstance<'a> {
+    fn new(pool: &'a HeadlessPool, instance: HeadlessInstance) -> Self {
+        Self {
+            pool,
+            instance: Some(instance),
+            discard: false,
+        }
+    }
+
+    /// Destroys the instance instead of reusing it, for when it may be in a bad state.
+    pub fn discard(&mut self) {
+        self.discard = true;
+    }
+}
+
+impl std::ops::Deref for PooledInstance<'_> {
+    type Target = HeadlessInstance;
+
+    fn deref(&self) -> &HeadlessInstance {
+        self.instance.as_ref().expect("instance taken before drop")
+    }
+}
+
+impl std::ops::DerefMut for PooledInstance<'_> {
+    fn deref_mut(&mut self) -> &mut HeadlessInstance {
+        self.instance.as_mut().expect("instance taken before drop")
+    }
+}
+
+impl Drop for PooledInstance<'_> {
+    fn drop(&mut self) {
+        if let Some(instance) = self.instance.take() {
+            self.pool.release(instance, self.discard);
+        }
+    }
+}
+
+fn destroy(instance: HeadlessInstance) {
+    if let Err(e) = instance.backend.destroy() {
+        warn!(
+            "Failed to destroy headless mpv instance '{}': {}",
+            instance.label, e
+        );
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
@@ -0,0 +1,324 @@
+//! The host process the `process` backend runs each mpv instance in.
+//!
+//! The plugin starts the host executable with [`HOST_ENV`] set to a local address.
+//! The host loads `libmpv-wrapper`, connects back with its capabilities and serves one
+//! instance over newline-delimited JSON until the plugin destroys it or disconnects.
+//!
+//! By default the host executable is the app itself, which must call
+//! [`run_if_requested`] before anything else in `main`:
+//!
+//! ```rust,no_run
+//! // The first line of `main`:
+//! tauri_plugin_libmpv::host::run_if_requested();
+//! ```
+
+use indexmap::IndexMap;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::backend::{BackendInstance, MpvBackend, WrapperBackend};
+use crate::{Error, InvalidOption, Result};
+
+/// Address the host connects to. Set only in host processes.
+pub const HOST_ENV: &str = "TAURI_PLUGIN_LIBMPV_HOST";
+
+/// Token the host sends first, so the plugin ignores other connections.
+pub(crate) const HOST_TOKEN_ENV: &str = "TAURI_PLUGIN_LIBMPV_HOST_TOKEN";
+
+/// Path of the `libmpv-wrapper` the host loads.
+pub(crate) const HOST_WRAPPER_ENV: &str = "TAURI_PLUGIN_LIBMPV_HOST_WRAPPER";
+
+/// A call the plugin makes on the instance in a host process.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub(crate) enum HostRequest {
+    Create {
+        window_label: String,
+        initial_options: IndexMap<String, serde_json::Value>,
+        observed_properties: IndexMap<String, String>,
+    },
+    Command {
+        name: String,
+        args: Vec<serde_json::Value>,
+    },
+    SetProperty {
+        name: String,
+        value: serde_json::Value,
+    },
+    GetProperty {
+        name: String,
+        format: String,
+    },
+    CommandAsync {
+        reply_id: u64,
+        name: String,
+        args: Vec<serde_json::Value>,
+    },
+    ObserveProperty {
+        reply_id: u64,
+        name: String,
+        format: String,
+    },
+    UnobserveProperty {
+        reply_id: u64,
+    },
+    HookAdd {
+        reply_id: u64,
+        name: String,
+        priority: i32,
+    },
+    HookContinue {
+        hook_id: u64,
+    },
+    Destroy,
+}
+
+/// An [`Error`] sent back from a host process.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub(crate) enum HostError {
+    CreateInstance {
+        invalid_options: Vec<InvalidOption>,
+        log: Vec<String>,
+    },
+    Command {
+        message: String,
+    },
+    SetProperty {
+        message: String,
+    },
+    GetProperty {
+        message: String,
+    },
+    UnsupportedFeature {
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl From<Error> for HostError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::CreateInstance {
+                invalid_options,
+                log,
+                ..
+            } => HostError::CreateInstance {
+                invalid_options,
+                log,
+            },
+            Error::Command { message, .. } => HostError::Command { message },
+            Error::SetProperty { message, .. } => HostError::SetProperty { message },
+            Error::GetProperty { message, .. } => HostError::GetProperty { message },
+            Error::UnsupportedFeature(message) => HostError::UnsupportedFeature { message },
+            error => HostError::Other {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl HostError {
+    pub fn into_error(self, window_label: &str) -> Error {
+        let window_label = window_label.to_string();
+        match self {
+            HostError::CreateInstance {
+                invalid_options,
+                log,
+            } => Error::CreateInstance {
+                window_label,
+                invalid_options,
+                log,
+            },
+            HostError::Command { message } => Error::Command {
+                window_label,
+                message,
+            },
+            HostError::SetProperty { message } => Error::SetProperty {
+                window_label,
+                message,
+            },
+            HostError::GetProperty { message } => Error::GetProperty {
+                window_label,
+                message,
+            },
+            HostError::UnsupportedFeature { message } => Error::UnsupportedFeature(message),
+            HostError::Other { message } => Error::Ipc(message),
+        }
+    }
+}
+
+/// Serves an mpv instance and exits if this process was started as a host. Returns otherwise.
+pub fn run_if_requested() {
+    let Some(address) = std::env::var_os(HOST_ENV) else {
+        return;
+    };
+
+    let code = match serve(&address.to_string_lossy()) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("mpv host process failed: {}", e);
+            1
+        }
+    };
+    std::process::exit(code);
+}
+
+fn serve(address: &str) -> Result<()> {
+    let token = std::env::var(HOST_TOKEN_ENV).unwrap_or_default();
+    let wrapper_path = PathBuf::from(std::env::var_os(HOST_WRAPPER_ENV).unwrap_or_default());
+
+    // Loaded before connecting, so the plugin sees a crash in libmpv as an exit.
+    let backend = unsafe { WrapperBackend::load(&wrapper_path) }
+        .map_err(|e| format!("'{}': {}", wrapper_path.display(), e));
+
+    let stream = TcpStream::connect(address)?;
+    stream.set_nodelay(true)?;
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let backend = match backend {
+        Ok(backend) => {
+            let hello = serde_json::json!({
+                "token": token,
+                "capabilities": backend.capabilities(),
+            });
+            write_line(&writer, &hello)?;
+            backend
+        }
+        Err(e) => {
+            write_line(&writer, &serde_json::json!({ "token": token, "error": e }))?;
+            return Err(Error::FFI(e));
+        }
+    };
+    let mut instance: Option<Arc<dyn BackendInstance>> = None;
+
+    for line in BufReader::new(stream).lines() {
+        let message: serde_json::Value = serde_json::from_str(&line?)?;
+        let request_id = message
+            .get("request_id")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+
+        let mut done = false;
+        let result = serde_json::from_value::<HostRequest>(message)
+            .map_err(Error::from)
+            .and_then(|request| {
+                done = matches!(request, HostRequest::Destroy);
+                handle(request, &backend, &mut instance, &writer)
+            });
+
+        let response = match result {
+            Ok(data) => serde_json::json!({ "request_id": request_id, "data": data }),
+            Err(e) => serde_json::json!({
+                "request_id": request_id,
+                "error": HostError::from(e),
+            }),
+        };
+        write_line(&writer, &response)?;
+
+        if done {
+            return Ok(());
+        }
+    }
+
+    // The plugin is gone, so nobody will destroy the instance.
+    if let Some(instance) = instance {
+        instance.destroy()?;
+    }
+    Ok(())
+}
+
+fn handle(
+    request: HostRequest,
+    backend: &WrapperBackend,
+    instance: &mut Option<Arc<dyn BackendInstance>>,
+    writer: &Arc<Mutex<TcpStream>>,
+) -> Result<serde_json::Value> {
+    if let HostRequest::Create {
+        window_label,
+        initial_options,
+        observed_properties,
+    } = request
+    {
+        if instance.is_some() {
+            return Err(Error::Ipc("the host already runs an instance".to_string()));
+        }
+        let writer = writer.clone();
+        *instance = Some(backend.create(
+            &window_label,
+            &initial_options,
+            &observed_properties,
+            Arc::new(move |event| {
+                let _ = write_line(&writer, &event);
+            }),
+        )?);
+        return Ok(serde_json::Value::Null);
+    }
+
+    let current = instance
+        .as_ref()
+        .ok_or_else(|| Error::Ipc("the host runs no instance".to_string()))?;
+
+    match request {
+        HostRequest::Create { .. } => unreachable!("handled above"),
+        HostRequest::Command { name, args } => current.command(&name, &args),
+        HostRequest::SetProperty { name, value } => current
+            .set_property(&name, &value)
+            .map(|_| Default::default()),
+        HostRequest::GetProperty { name, format } => current.get_property(&name, &format),
+        HostRequest::CommandAsync {
+            reply_id,
+            name,
+            args,
+        } => current
+            .command_async(reply_id, &name, &args)
+            .map(|_| Default::default()),
+        HostRequest::ObserveProperty {
+            reply_id,
+            name,
+            format,
+        } => current
+            .observe_property(reply_id, &name, &format)
+            .map(|_| Default::default()),
+        HostRequest::UnobserveProperty { reply_id } => current
+            .unobserve_property(reply_id)
+            .map(|_| Default::default()),
+        HostRequest::HookAdd {
+            reply_id,
+            name,
+            priority,
+        } => current
+            .hook_add(reply_id, &name, priority)
+            .map(|_| Default::default()),
+        HostRequest::HookContinue { hook_id } => {
+            current.hook_continue(hook_id).map(|_| Default::default())
+        }
+        HostRequest::Destroy => {
+            let result = current.destroy();
+            *instance = None;
+            result.map(|_| Default::default())
+        }
+    }
+}
+
+fn write_line(writer: &Mutex<TcpStream>, message: &serde_json::Value) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+
+    let mut writer = match writer.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
@@ -1,7 +1,5 @@
-use tauri::{
-    plugin::{Builder, TauriPlugin},
-    Manager, RunEvent, Runtime, WindowEvent,
-};
+use indexmap::IndexMap;
+use tauri::{plugin::TauriPlugin, AppHandle, Manager, RunEvent, Runtime, WindowEvent};
 
 pub use models::*;
 
@@ -10,14 +8,75 @@ mod desktop;
 #[cfg(mobile)]
 mod mobile;
 #[cfg(desktop)]
+#[allow(clippy::missing_safety_doc)]
 mod wrapper;
 
+#[cfg(desktop)]
+mod backend;
 mod commands;
+mod config;
+#[cfg(desktop)]
+mod derived;
+#[cfg(desktop)]
+mod directory;
 mod error;
+#[cfg(desktop)]
+mod headless;
+#[cfg(desktop)]
+pub mod host;
+#[cfg(all(desktop, feature = "libmpv"))]
+mod libmpv;
+#[cfg(desktop)]
+mod loader;
+#[cfg(desktop)]
+mod media;
 mod models;
+#[cfg(desktop)]
+mod playlist;
+mod playlist_file;
+mod presets;
+#[cfg(desktop)]
+mod probe;
+#[cfg(desktop)]
+mod queue;
+#[cfg(desktop)]
+mod resume;
+mod screenshot;
+#[cfg(desktop)]
+mod storyboard;
+#[cfg(desktop)]
+mod thumbnails;
 mod utils;
+#[cfg(desktop)]
+mod validation;
+
+#[cfg(all(desktop, unix))]
+pub use backend::IpcBackend;
+#[cfg(desktop)]
+pub use backend::{
+    BackendCapabilities, BackendInstance, Capability, EventSink, FrameSink, MpvBackend,
+    ProcessBackend, VideoFrame, WrapperBackend, WRAPPER_ABI_MAJOR,
+};
+#[cfg(all(desktop, feature = "test-util"))]
+pub use backend::{FakeBackend, RecordedCommand};
+pub use config::{BackendKind, Config, EventFilter, ProbeConfig, ResumeConfig, ThumbnailConfig};
+pub use error::{Error, InvalidOption, Result};
+pub use presets::{builtin_preset, BUILTIN_PRESETS};
+pub use screenshot::DEFAULT_SCREENSHOT_TEMPLATE;
 
-pub use error::{Error, Result};
+#[cfg(all(desktop, feature = "libmpv"))]
+pub use libmpv::LibmpvBackend;
+#[cfg(all(desktop, feature = "libmpv"))]
+pub use loader::LIBMPV_ENV;
+#[cfg(desktop)]
+pub use loader::{WRAPPER_ENV, WRAPPER_LIB_NAME};
+#[cfg(desktop)]
+pub use playlist::PlaylistHandle;
+pub use playlist_file::{
+    read_playlist_file, write_playlist_file, PlaylistFileEntry, PlaylistFormat,
+};
+#[cfg(desktop)]
+pub use wrapper::LibmpvWrapper;
 
 #[cfg(desktop)]
 use desktop::Mpv;
@@ -34,77 +93,320 @@ impl<R: Runtime, T: Manager<R>> crate::MpvExt<R> for T {
     }
 }
 
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::new("libmpv")
-        .invoke_handler(tauri::generate_handler![
-            commands::init,
-            commands::destroy,
-            commands::command,
-            commands::set_property,
-            commands::get_property,
-            commands::set_video_margin_ratio,
-        ])
-        .setup(|app, api| {
-            unsafe {
-                let locale = std::ffi::CString::new("C").unwrap();
-                libc::setlocale(libc::LC_NUMERIC, locale.as_ptr());
-            }
-
-            #[cfg(mobile)]
-            let mpv = mobile::init(app, api)?;
+type InstanceCreatedHook<R> = Box<dyn Fn(&AppHandle<R>, &str) + Send + Sync>;
+type EventHook<R> = Box<dyn Fn(&AppHandle<R>, &str, &serde_json::Value) + Send + Sync>;
+type ErrorHook<R> = Box<dyn Fn(&AppHandle<R>, &str, &Error) + Send + Sync>;
+type PathResolver<R> = Box<dyn Fn(&AppHandle<R>, &str) -> Result<String> + Send + Sync>;
+#[cfg(desktop)]
+type WrapperLoader<R> = Box<dyn Fn(&AppHandle<R>) -> Result<LibmpvWrapper> + Send + Sync>;
+
+/// Rust-side customizations registered through [`Builder`].
+pub(crate) struct Hooks<R: Runtime> {
+    pub default_options: IndexMap<String, serde_json::Value>,
+    pub presets: IndexMap<String, IndexMap<String, serde_json::Value>>,
+    pub on_instance_created: Option<InstanceCreatedHook<R>>,
+    pub on_event: Option<EventHook<R>>,
+    pub on_error: Option<ErrorHook<R>>,
+    pub path_resolver: Option<PathResolver<R>>,
+    #[cfg(desktop)]
+    pub wrapper_loader: Option<WrapperLoader<R>>,
+    #[cfg(desktop)]
+    pub wrapper_search_dirs: Vec<std::path::PathBuf>,
+    #[cfg(all(desktop, feature = "libmpv"))]
+    pub libmpv_search_dirs: Vec<std::path::PathBuf>,
+    #[cfg(desktop)]
+    pub backend: Option<std::sync::Arc<dyn MpvBackend>>,
+}
+
+impl<R: Runtime> Default for Hooks<R> {
+    fn default() -> Self {
+        Self {
+            default_options: IndexMap::new(),
+            presets: IndexMap::new(),
+            on_instance_created: None,
+            on_event: None,
+            on_error: None,
+            path_resolver: None,
             #[cfg(desktop)]
-            let mpv = desktop::init(app, api)?;
-            app.manage(mpv);
-            Ok(())
-        })
-        .on_event(|app_handle, run_event| {
-            if let RunEvent::WindowEvent {
-                label,
-                event: WindowEvent::CloseRequested { api, .. },
-                ..
-            } = run_event
-            {
-                let mpv_state = app_handle.state::<Mpv<R>>();
-
-                let instance_exists = {
-                    let instances_lock = match mpv_state.instances.lock() {
-                        Ok(guard) => guard,
-                        Err(poisoned) => {
-                            log::warn!("Mutex for mpv instances was poisoned. Recovering.");
-                            poisoned.into_inner()
-                        }
-                    };
-                    instances_lock.contains_key(label)
-                };
+            wrapper_loader: None,
+            #[cfg(desktop)]
+            wrapper_search_dirs: Vec::new(),
+            #[cfg(all(desktop, feature = "libmpv"))]
+            libmpv_search_dirs: Vec::new(),
+            #[cfg(desktop)]
+            backend: None,
+        }
+    }
+}
 
-                if instance_exists {
-                    api.prevent_close();
+/// Builds the plugin with Rust-side defaults and hooks.
+///
+/// ```rust,no_run
+/// tauri::Builder::default()
+///     .plugin(
+///         tauri_plugin_libmpv::Builder::new()
+///             .default_option("hwdec", "auto-safe")
+///             .on_event(|_app, window_label, event| {
+///                 log::debug!("mpv event for '{}': {}", window_label, event);
+///             })
+///             .build(),
+///     )
+/// # ;
+/// ```
+pub struct Builder<R: Runtime> {
+    hooks: Hooks<R>,
+}
 
-                    let app_handle_clone = app_handle.clone();
-                    let window_label = label.to_string();
+impl<R: Runtime> Default for Builder<R> {
+    fn default() -> Self {
+        Self {
+            hooks: Hooks::default(),
+        }
+    }
+}
 
-                    tauri::async_runtime::spawn(async move {
-                        log::info!(
-                            "Close requested for '{}', destroying mpv instance first...",
-                            &window_label
-                        );
+impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-                        if let Err(e) = app_handle_clone.mpv().destroy(&window_label) {
-                            log::error!(
-                                "Failed to destroy mpv for '{}': {}. Still closing.",
-                                &window_label,
-                                e
+    /// Sets an option applied to every instance.
+    ///
+    /// `plugins.libmpv.initialOptions` and `MpvConfig::initial_options` take precedence.
+    pub fn default_option(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.hooks.default_options.insert(name.into(), value.into());
+        self
+    }
+
+    /// Registers a preset selectable through `MpvConfig::preset`.
+    ///
+    /// A preset with the same name as a built-in one replaces it.
+    pub fn preset(
+        mut self,
+        name: impl Into<String>,
+        options: IndexMap<String, serde_json::Value>,
+    ) -> Self {
+        self.hooks.presets.insert(name.into(), options);
+        self
+    }
+
+    /// Adds a directory searched for `libmpv-wrapper`.
+    ///
+    /// These are tried after `plugins.libmpv.wrapperPath` and the
+    /// `TAURI_PLUGIN_LIBMPV_WRAPPER` environment variable.
+    #[cfg(desktop)]
+    pub fn wrapper_search_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.hooks.wrapper_search_dirs.push(dir.into());
+        self
+    }
+
+    /// Adds a directory searched for libmpv by the `libmpv` backend.
+    ///
+    /// These are tried after `plugins.libmpv.libmpvPath` and the
+    /// `TAURI_PLUGIN_LIBMPV_LIBRARY` environment variable.
+    #[cfg(all(desktop, feature = "libmpv"))]
+    pub fn libmpv_search_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.hooks.libmpv_search_dirs.push(dir.into());
+        self
+    }
+
+    /// Replaces the way `libmpv-wrapper` is loaded.
+    ///
+    /// Not supported by the `process` backend, which fails to load with a loader set.
+    #[cfg(desktop)]
+    pub fn wrapper_loader<F>(mut self, loader: F) -> Self
+    where
+        F: Fn(&AppHandle<R>) -> Result<LibmpvWrapper> + Send + Sync + 'static,
+    {
+        self.hooks.wrapper_loader = Some(Box::new(loader));
+        self
+    }
+
+    /// Creates instances through `backend` instead of the one `plugins.libmpv.backend` selects.
+    #[cfg(desktop)]
+    pub fn backend(mut self, backend: impl MpvBackend + 'static) -> Self {
+        self.hooks.backend = Some(std::sync::Arc::new(backend));
+        self
+    }
+
+    /// Called with the window label after an instance has been created.
+    pub fn on_instance_created<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&AppHandle<R>, &str) + Send + Sync + 'static,
+    {
+        self.hooks.on_instance_created = Some(Box::new(hook));
+        self
+    }
+
+    /// Called for every mpv event, before `plugins.libmpv.eventFilter` is applied.
+    pub fn on_event<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&AppHandle<R>, &str, &serde_json::Value) + Send + Sync + 'static,
+    {
+        self.hooks.on_event = Some(Box::new(hook));
+        self
+    }
+
+    /// Called with the window label whenever a player operation fails.
+    pub fn on_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&AppHandle<R>, &str, &Error) + Send + Sync + 'static,
+    {
+        self.hooks.on_error = Some(Box::new(hook));
+        self
+    }
+
+    /// Rewrites the path or URL passed to `loadfile`, `loadlist` and the `*-add` commands.
+    pub fn path_resolver<F>(mut self, resolver: F) -> Self
+    where
+        F: Fn(&AppHandle<R>, &str) -> Result<String> + Send + Sync + 'static,
+    {
+        self.hooks.path_resolver = Some(Box::new(resolver));
+        self
+    }
+
+    pub fn build(self) -> TauriPlugin<R, Option<Config>> {
+        let hooks = self.hooks;
+
+        tauri::plugin::Builder::<R, Option<Config>>::new("libmpv")
+            .invoke_handler(tauri::generate_handler![
+                commands::init,
+                commands::destroy,
+                commands::command,
+                commands::set_property,
+                commands::get_property,
+                commands::set_video_margin_ratio,
+                commands::get_profiles,
+                commands::apply_profile,
+                commands::reload_config,
+                commands::get_capabilities,
+                commands::start_render,
+                commands::resize_render,
+                commands::screenshot,
+                commands::save_screenshot,
+                commands::get_thumbnail,
+                commands::generate_storyboard,
+                commands::cancel_storyboard,
+                commands::probe,
+                commands::list_tracks,
+                commands::select_track,
+                commands::add_external_track,
+                commands::remove_track,
+                commands::list_editions,
+                commands::select_edition,
+                commands::list_chapters,
+                commands::next_chapter,
+                commands::prev_chapter,
+                commands::seek_chapter,
+                commands::get_playlist,
+                commands::playlist_append,
+                commands::playlist_insert_at,
+                commands::playlist_insert_next,
+                commands::playlist_move,
+                commands::playlist_remove,
+                commands::playlist_clear,
+                commands::playlist_play_index,
+                commands::playlist_play_entry,
+                commands::playlist_export,
+                commands::playlist_import,
+                commands::get_queue,
+                commands::set_repeat,
+                commands::set_shuffle,
+                commands::queue_next,
+                commands::queue_previous,
+                commands::load_directory,
+                commands::get_resume_point,
+                commands::list_resume_points,
+                commands::clear_resume_point,
+            ])
+            .setup(move |app, api| {
+                unsafe {
+                    let locale = std::ffi::CString::new("C").unwrap();
+                    libc::setlocale(libc::LC_NUMERIC, locale.as_ptr());
+                }
+
+                #[cfg(mobile)]
+                let mpv = mobile::init(app, api)?;
+                #[cfg(desktop)]
+                let mpv = desktop::init(app, api, hooks)?;
+                app.manage(mpv);
+                Ok(())
+            })
+            .on_event(|app_handle, run_event| match run_event {
+                RunEvent::WindowEvent {
+                    label,
+                    event: WindowEvent::CloseRequested { api, .. },
+                    ..
+                } => {
+                    let mpv_state = app_handle.state::<Mpv<R>>();
+
+                    let instance_exists = {
+                        let instances_lock = match mpv_state.instances.lock() {
+                            Ok(guard) => guard,
+                            Err(poisoned) => {
+                                log::warn!("Mutex for mpv instances was poisoned. Recovering.");
+                                poisoned.into_inner()
+                            }
+                        };
+                        instances_lock.contains_key(label)
+                    };
+
+                    if instance_exists {
+                        api.prevent_close();
+
+                        let app_handle_clone = app_handle.clone();
+                        let window_label = label.to_string();
+
+                        tauri::async_runtime::spawn(async move {
+                            log::info!(
+                                "Close requested for '{}', destroying mpv instance first...",
+                                &window_label
                             );
-                        }
 
-                        if let Some(window) = app_handle_clone.get_webview_window(&window_label) {
-                            if let Err(e) = window.close() {
-                                log::error!("Failed to close window '{}': {}", &window_label, e);
+                            if let Err(e) = app_handle_clone.mpv().destroy(&window_label) {
+                                log::error!(
+                                    "Failed to destroy mpv for '{}': {}. Still closing.",
+                                    &window_label,
+                                    e
+                                );
                             }
-                        }
-                    });
+
+                            if let Some(window) = app_handle_clone.get_webview_window(&window_label)
+                            {
+                                if let Err(e) = window.close() {
+                                    log::error!(
+                                        "Failed to close window '{}': {}",
+                                        &window_label,
+                                        e
+                                    );
+                                }
+                            }
+                        });
+                    }
                 }
-            }
-        })
-        .build()
+                RunEvent::Exit => {
+                    // `ExitRequested` can still be prevented by the app, so teardown waits for `Exit`.
+                    let failed = app_handle
+                        .mpv()
+                        .destroy_all(app_handle.mpv().exit_timeout());
+                    if !failed.is_empty() {
+                        log::warn!(
+                            "Exiting with {} mpv instance(s) not stopped: {:?}",
+                            failed.len(),
+                            failed
+                        );
+                    }
+                }
+                _ => {}
+            })
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R, Option<Config>> {
+    Builder::new().build()
 }
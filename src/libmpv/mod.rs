@@ -0,0 +1,581 @@
+//! Backend that drives libmpv directly instead of going through `libmpv-wrapper`.
+
+mod node;
+mod render;
+mod sys;
+
+use indexmap::IndexMap;
+use log::{error, trace};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::backend::{BackendCapabilities, BackendInstance, EventSink, FrameSink, MpvBackend};
+use crate::{validation, Error};
+use node::{cstr_to_json, data_to_json, format_from_name, node_to_json, none_node, OwnedNode};
+use render::Renderer;
+pub use sys::Libmpv;
+use sys::*;
+
+#[cfg(target_os = "windows")]
+pub const LIBMPV_LIB_NAMES: &[&str] = &["libmpv-2.dll", "mpv-2.dll", "mpv-1.dll"];
+#[cfg(target_os = "macos")]
+pub const LIBMPV_LIB_NAMES: &[&str] = &["libmpv.2.dylib", "libmpv.dylib"];
+#[cfg(target_os = "linux")]
+pub const LIBMPV_LIB_NAMES: &[&str] = &["libmpv.so.2", "libmpv.so"];
+
+#[derive(Clone, Copy)]
+struct Context(*mut mpv_handle);
+
+unsafe impl Send for Context {}
+unsafe impl Sync for Context {}
+
+/// Runs mpv by calling libmpv directly.
+pub struct LibmpvBackend {
+    lib: Arc<Libmpv>,
+}
+
+impl LibmpvBackend {
+    pub fn new(lib: Libmpv) -> Self {
+        Self { lib: Arc::new(lib) }
+    }
+}
+
+impl MpvBackend for LibmpvBackend {
+    fn create(
+        &self,
+        window_label: &str,
+        initial_options: &IndexMap<String, serde_json::Value>,
+        observed_properties: &IndexMap<String, String>,
+        events: EventSink,
+    ) -> crate::Result<Arc<dyn BackendInstance>> {
+        let instance = LibmpvInstance::create(
+            self.lib.clone(),
+            window_label,
+            initial_options,
+            observed_properties,
+            events,
+        )
+        .map_err(|message| {
+            error!(
+                "Failed to create mpv instance for window '{}'. Validating options...",
+                window_label
+            );
+            Error::CreateInstance {
+                window_label: window_label.to_string(),
+                invalid_options: validation::find_invalid_options(
+                    self,
+                    window_label,
+                    initial_options,
+                ),
+                log: vec![message],
+            }
+        })?;
+        Ok(Arc::new(instance))
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        let (major, minor) = client_api_version(&self.lib);
+        BackendCapabilities {
+            abi_version: Some(format!("{}.{}", major, minor)),
+            async_commands: true,
+            observe_properties: true,
+            hooks: true,
+            render_api: self.lib.render.is_some(),
+        }
+    }
+}
+
+/// A running mpv core with its event thread.
+struct LibmpvInstance {
+    lib: Arc<Libmpv>,
+    ctx: Context,
+    window_label: String,
+    stop: Arc<AtomicBool>,
+    event_thread: Mutex<Option<JoinHandle<()>>>,
+    renderer: Mutex<Option<Renderer>>,
+}
+
+impl std::fmt::Debug for LibmpvInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LibmpvInstance")
+            .field("ctx", &self.ctx.0)
+            .field("window_label", &self.window_label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LibmpvInstance {
+    /// Creates and initializes an mpv core, then starts delivering its events to `on_event`.
+    fn create(
+        lib: Arc<Libmpv>,
+        window_label: &str,
+        initial_options: &IndexMap<String, serde_json::Value>,
+        observed_properties: &IndexMap<String, String>,
+        on_event: EventSink,
+    ) -> Result<Self, String> {
+        let ctx = unsafe { (lib.mpv_create)() };
+        if ctx.is_null() {
+            return Err("mpv_create returned null".to_string());
+        }
+        let ctx = Context(ctx);
+
+        let setup = || -> Result<(), String> {
+            for (name, value) in initial_options {
+                set_option(&lib, ctx, name, value)
+                    .map_err(|e| format!("Failed to set option '{}' to {}: {}", name, value, e))?;
+            }
+
+            check(&lib, unsafe { (lib.mpv_initialize)(ctx.0) })
+                .map_err(|e| format!("Failed to initialize mpv: {}", e))?;
+
+            for (index, (name, format)) in observed_properties.iter().enumerate() {
+                let mpv_format = format_from_name(format)
+                    .ok_or_else(|| format!("Unknown format '{}' for '{}'", format, name))?;
+                let c_name = CString::new(name.as_str()).map_err(|e| e.to_string())?;
+                check(&lib, unsafe {
+                    (lib.mpv_observe_property)(ctx.0, index as u64 + 1, c_name.as_ptr(), mpv_format)
+                })
+                .map_err(|e| format!("Failed to observe '{}': {}", name, e))?;
+            }
+            Ok(())
+        };
+
+        if let Err(e) = setup() {
+            unsafe { (lib.mpv_terminate_destroy)(ctx.0) };
+            return Err(e);
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let event_thread = {
+            let lib = lib.clone();
+            let stop = stop.clone();
+            std::thread::Builder::new()
+                .name("mpv-events".to_string())
+                .spawn(move || event_loop(&lib, ctx, &stop, on_event))
+        };
+
+        let event_thread = match event_thread {
+            Ok(event_thread) => event_thread,
+            Err(e) => {
+                unsafe { (lib.mpv_terminate_destroy)(ctx.0) };
+                return Err(format!("Failed to spawn the mpv event thread: {}", e));
+            }
+        };
+
+        Ok(LibmpvInstance {
+            lib,
+            ctx,
+            window_label: window_label.to_string(),
+            stop,
+            event_thread: Mutex::new(Some(event_thread)),
+            renderer: Mutex::new(None),
+        })
+    }
+
+    fn run_command(
+        &self,
+        name: &str,
+        args: &[serde_json::Value],
+    ) -> Result<serde_json::Value, String> {
+        let mut command = OwnedNode::command(name, args)?;
+        let mut result = none_node();
+
+        check(&self.lib, unsafe {
+            (self.lib.mpv_command_node)(self.ctx.0, command.as_mut_ptr(), &mut result)
+        })?;
+
+        let value = unsafe { node_to_json(&result) };
+        unsafe { (self.lib.mpv_free_node_contents)(&mut result) };
+        Ok(value)
+    }
+
+    fn write_property(&self, name: &str, value: &serde_json::Value) -> Result<(), String> {
+        let c_name = CString::new(name).map_err(|e| e.to_string())?;
+        let mut node = OwnedNode::new(value)?;
+
+        check(&self.lib, unsafe {
+            (self.lib.mpv_set_property)(
+                self.ctx.0,
+                c_name.as_ptr(),
+                MPV_FORMAT_NODE,
+                node.as_mut_ptr() as *mut c_void,
+            )
+        })
+    }
+
+    fn read_property(&self, name: &str, format: &str) -> Result<serde_json::Value, String> {
+        let c_name = CString::new(name).map_err(|e| e.to_string())?;
+        let mpv_format =
+            format_from_name(format).ok_or_else(|| format!("Unknown format '{}'", format))?;
+        let get = |data: *mut c_void| unsafe {
+            (self.lib.mpv_get_property)(self.ctx.0, c_name.as_ptr(), mpv_format, data)
+        };
+
+        match mpv_format {
+            MPV_FORMAT_STRING => {
+                let mut string: *mut c_char = std::ptr::null_mut();
+                check(&self.lib, get(&mut string as *mut _ as *mut c_void))?;
+                let value = unsafe { cstr_to_json(string) };
+                unsafe { (self.lib.mpv_free)(string as *mut c_void) };
+                Ok(value)
+            }
+            MPV_FORMAT_FLAG => {
+                let mut flag: c_int = 0;
+                check(&self.lib, get(&mut flag as *mut _ as *mut c_void))?;
+                Ok(serde_json::Value::Bool(flag != 0))
+            }
+            MPV_FORMAT_INT64 => {
+                let mut int64: i64 = 0;
+                check(&self.lib, get(&mut int64 as *mut _ as *mut c_void))?;
+                Ok(serde_json::json!(int64))
+            }
+            MPV_FORMAT_DOUBLE => {
+                let mut double: f64 = 0.0;
+                check(&self.lib, get(&mut double as *mut _ as *mut c_void))?;
+                Ok(
+                    unsafe {
+                        data_to_json(MPV_FORMAT_DOUBLE, &mut double as *mut _ as *mut c_void)
+                    },
+                )
+            }
+            _ => {
+                let mut node = none_node();
+                check(&self.lib, get(&mut node as *mut _ as *mut c_void))?;
+                let value = unsafe { node_to_json(&node) };
+                unsafe { (self.lib.mpv_free_node_contents)(&mut node) };
+                Ok(value)
+            }
+        }
+    }
+
+    /// Stops the render and event threads and destroys the core, waiting for it to shut down.
+    fn shutdown(&self) {
+        if let Some(renderer) = lock(&self.renderer).take() {
+            renderer.stop();
+        }
+
+        self.stop.store(true, Ordering::SeqCst);
+        unsafe { (self.lib.mpv_wakeup)(self.ctx.0) };
+
+        let event_thread = lock(&self.event_thread).take();
+        let Some(event_thread) = event_thread else {
+            return;
+        };
+        if event_thread.join().is_err() {
+            error!("mpv event thread panicked.");
+        }
+
+        unsafe { (self.lib.mpv_terminate_destroy)(self.ctx.0) };
+    }
+}
+
+impl LibmpvInstance {
+    fn command_result(&self, result: Result<(), String>) -> crate::Result<()> {
+        result.map_err(|message| Error::Command {
+            window_label: self.window_label.clone(),
+            message,
+        })
+    }
+}
+
+impl BackendInstance for LibmpvInstance {
+    fn command(&self, name: &str, args: &[serde_json::Value]) -> crate::Result<serde_json::Value> {
+        self.run_command(name, args)
+            .map_err(|message| Error::Command {
+                window_label: self.window_label.clone(),
+                message,
+            })
+    }
+
+    fn set_property(&self, name: &str, value: &serde_json::Value) -> crate::Result<()> {
+        self.write_property(name, value)
+            .map_err(|message| Error::SetProperty {
+                window_label: self.window_label.clone(),
+                message,
+            })
+    }
+
+    fn get_property(&self, name: &str, format: &str) -> crate::Result<serde_json::Value> {
+        self.read_property(name, format)
+            .map_err(|message| Error::GetProperty {
+                window_label: self.window_label.clone(),
+                message,
+            })
+    }
+
+    fn command_async(
+        &self,
+        reply_id: u64,
+        name: &str,
+        args: &[serde_json::Value],
+    ) -> crate::Result<()> {
+        let result = OwnedNode::command(name, args).and_then(|mut command| {
+            check(&self.lib, unsafe {
+                (self.lib.mpv_command_node_async)(self.ctx.0, reply_id, command.as_mut_ptr())
+            })
+        });
+        self.command_result(result)
+    }
+
+    fn observe_property(&self, reply_id: u64, name: &str, format: &str) -> crate::Result<()> {
+        let result = (|| {
+            let mpv_format =
+                format_from_name(format).ok_or_else(|| format!("Unknown format '{}'", format))?;
+            let c_name = CString::new(name).map_err(|e| e.to_string())?;
+            check(&self.lib, unsafe {
+                (self.lib.mpv_observe_property)(self.ctx.0, reply_id, c_name.as_ptr(), mpv_format)
+            })
+        })();
+        self.command_result(result)
+    }
+
+    fn unobserve_property(&self, reply_id: u64) -> crate::Result<()> {
+        let result = check(&self.lib, unsafe {
+            (self.lib.mpv_unobserve_property)(self.ctx.0, reply_id)
+        });
+        self.command_result(result)
+    }
+
+    fn hook_add(&self, reply_id: u64, name: &str, priority: i32) -> crate::Result<()> {
+        let result = CString::new(name)
+            .map_err(|e| e.to_string())
+            .and_then(|c_name| {
+                check(&self.lib, unsafe {
+                    (self.lib.mpv_hook_add)(self.ctx.0, reply_id, c_name.as_ptr(), priority)
+                })
+            });
+        self.command_result(result)
+    }
+
+    fn hook_continue(&self, hook_id: u64) -> crate::Result<()> {
+        let result = check(&self.lib, unsafe {
+            (self.lib.mpv_hook_continue)(self.ctx.0, hook_id)
+        });
+        self.command_result(result)
+    }
+
+    fn start_render(&self, width: u32, height: u32, frames: FrameSink) -> crate::Result<()> {
+        let mut renderer = lock(&self.renderer);
+        if let Some(renderer) = renderer.as_ref() {
+            renderer.update(width, height, Some(frames));
+            return Ok(());
+        }
+
+        let started = Renderer::start(self.lib.clone(), self.ctx, width, height, frames).map_err(
+            |message| Error::Command {
+                window_label: self.window_label.clone(),
+                message,
+            },
+        )?;
+        *renderer = Some(started);
+        Ok(())
+    }
+
+    fn resize_render(&self, width: u32, height: u32) -> crate::Result<()> {
+        match lock(&self.renderer).as_ref() {
+            Some(renderer) => {
+                renderer.update(width, height, None);
+                Ok(())
+            }
+            None => self.command_result(Err("Rendering has not been started".to_string())),
+        }
+    }
+
+    fn destroy(&self) -> crate::Result<()> {
+        self.shutdown();
+        Ok(())
+    }
+}
+
+fn event_loop(lib: &Libmpv, ctx: Context, stop: &AtomicBool, on_event: EventSink) {
+    loop {
+        let event = unsafe { (lib.mpv_wait_event)(ctx.0, -1.0) };
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        if event.is_null() {
+            continue;
+        }
+
+        let event = unsafe { &*event };
+        if event.event_id == MPV_EVENT_NONE {
+            continue;
+        }
+
+        let json = unsafe { event_to_json(lib, event) };
+        trace!("EVENT {}", json);
+        on_event(json);
+
+        if event.event_id == MPV_EVENT_SHUTDOWN {
+            break;
+        }
+    }
+}
+
+/// Converts an event to the JSON layout of the frontend `MpvEvent` types.
+unsafe fn event_to_json(lib: &Libmpv, event: &mpv_event) -> serde_json::Value {
+    let mut json = serde_json::Map::new();
+    unsafe {
+        json.insert(
+            "event".to_string(),
+            cstr_to_json((lib.mpv_event_name)(event.event_id)),
+        );
+
+        let data = event.data;
+        let id = serde_json::json!(event.reply_userdata);
+        let error = serde_json::json!(event.error);
+
+        match event.event_id {
+            MPV_EVENT_LOG_MESSAGE if !data.is_null() => {
+                let message = &*(data as *const mpv_event_log_message);
+                json.insert("prefix".to_string(), cstr_to_json(message.prefix));
+                json.insert("level".to_string(), cstr_to_json(message.level));
+                json.insert("text".to_string(), cstr_to_json(message.text));
+            }
+            MPV_EVENT_GET_PROPERTY_REPLY | MPV_EVENT_PROPERTY_CHANGE if !data.is_null() => {
+                let property = &*(data as *const mpv_event_property);
+                json.insert("name".to_string(), cstr_to_json(property.name));
+                json.insert(
+                    "data".to_string(),
+                    data_to_json(property.format, property.data),
+                );
+                if event.event_id == MPV_EVENT_GET_PROPERTY_REPLY {
+                    json.insert("error".to_string(), error);
+                }
+                json.insert("id".to_string(), id);
+            }
+            MPV_EVENT_SET_PROPERTY_REPLY => {
+                json.insert("error".to_string(), error);
+                json.insert("id".to_string(), id);
+            }
+            MPV_EVENT_COMMAND_REPLY => {
+                let result = if data.is_null() {
+                    serde_json::Value::Null
+                } else {
+                    node_to_json(&(*(data as *const mpv_event_command)).result)
+                };
+                json.insert("result".to_string(), result);
+                json.insert("error".to_string(), error);
+                json.insert("id".to_string(), id);
+            }
+            MPV_EVENT_START_FILE if !data.is_null() => {
+                let start_file = &*(data as *const mpv_event_start_file);
+                json.insert(
+                    "playlist_entry_id".to_string(),
+                    serde_json::json!(start_file.playlist_entry_id),
+                );
+            }
+            MPV_EVENT_END_FILE if !data.is_null() => {
+                let end_file = &*(data as *const mpv_event_end_file);
+                let reason = match end_file.reason {
+                    MPV_END_FILE_REASON_EOF => "eof",
+                    MPV_END_FILE_REASON_STOP => "stop",
+                    MPV_END_FILE_REASON_QUIT => "quit",
+                    MPV_END_FILE_REASON_ERROR => "error",
+                    MPV_END_FILE_REASON_REDIRECT => "redirect",
+                    _ => "unknown",
+                };
+                json.insert("reason".to_string(), serde_json::json!(reason));
+                json.insert("error".to_string(), serde_json::json!(end_file.error));
+                json.insert(
+                    "playlist_entry_id".to_string(),
+                    serde_json::json!(end_file.playlist_entry_id),
+                );
+                json.insert(
+                    "playlist_insert_id".to_string(),
+                    serde_json::json!(end_file.playlist_insert_id),
+                );
+                json.insert(
+                    "playlist_insert_num_entries".to_string(),
+                    serde_json::json!(end_file.playlist_insert_num_entries),
+                );
+            }
+            MPV_EVENT_CLIENT_MESSAGE if !data.is_null() => {
+                let message = &*(data as *const mpv_event_client_message);
+                let args: Vec<serde_json::Value> = if message.args.is_null() {
+                    Vec::new()
+                } else {
+                    std::slice::from_raw_parts(message.args, message.num_args.max(0) as usize)
+                        .iter()
+                        .map(|arg| cstr_to_json(*arg))
+                        .collect()
+                };
+                json.insert("args".to_string(), serde_json::Value::Array(args));
+            }
+            MPV_EVENT_HOOK if !data.is_null() => {
+                let hook = &*(data as *const mpv_event_hook);
+                json.insert("name".to_string(), cstr_to_json(hook.name));
+                json.insert("hook_id".to_string(), serde_json::json!(hook.id));
+            }
+            _ => {}
+        }
+    }
+    serde_json::Value::Object(json)
+}
+
+fn set_option(
+    lib: &Libmpv,
+    ctx: Context,
+    name: &str,
+    value: &serde_json::Value,
+) -> Result<(), String> {
+    let c_name = CString::new(name).map_err(|e| e.to_string())?;
+
+    let text = match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Bool(flag) => Some(if *flag { "yes" } else { "no" }.to_string()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    };
+
+    match text {
+        Some(text) => {
+            let c_text = CString::new(text).map_err(|e| e.to_string())?;
+            check(lib, unsafe {
+                (lib.mpv_set_option_string)(ctx.0, c_name.as_ptr(), c_text.as_ptr())
+            })
+        }
+        None => {
+            let mut node = OwnedNode::new(value)?;
+            check(lib, unsafe {
+                (lib.mpv_set_option)(
+                    ctx.0,
+                    c_name.as_ptr(),
+                    MPV_FORMAT_NODE,
+                    node.as_mut_ptr() as *mut c_void,
+                )
+            })
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+fn check(lib: &Libmpv, code: c_int) -> Result<(), String> {
+    if code >= 0 {
+        return Ok(());
+    }
+    let message = unsafe { (lib.mpv_error_string)(code) };
+    if message.is_null() {
+        Err(format!("mpv error {}", code))
+    } else {
+        Err(unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned())
+    }
+}
+
+/// Returns the client API version of the loaded library as `(major, minor)`.
+pub fn client_api_version(lib: &Libmpv) -> (u32, u32) {
+    // `c_ulong` is 32 bits on Windows.
+    #[allow(clippy::unnecessary_cast)]
+    let version = unsafe { (lib.mpv_client_api_version)() } as u64;
+    ((version >> 16) as u32, (version & 0xffff) as u32)
+}
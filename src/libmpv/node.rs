@@ -0,0 +1,239 @@
+use base64::Engine;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+
+use super::sys::*;
+
+/// An `mpv_node` tree built from JSON, owning every allocation it points to.
+pub struct OwnedNode {
+    node: mpv_node,
+    _strings: Vec<CString>,
+    _values: Vec<Vec<mpv_node>>,
+    _keys: Vec<Vec<*mut c_char>>,
+    // Boxed so the pointers handed to libmpv stay valid when the vector grows.
+    #[allow(clippy::vec_box)]
+    _lists: Vec<Box<mpv_node_list>>,
+}
+
+impl OwnedNode {
+    pub fn new(value: &serde_json::Value) -> Result<Self, String> {
+        let mut owned = OwnedNode {
+            node: none_node(),
+            _strings: Vec::new(),
+            _values: Vec::new(),
+            _keys: Vec::new(),
+            _lists: Vec::new(),
+        };
+        owned.node = owned.build(value)?;
+        Ok(owned)
+    }
+
+    /// Builds the node array `mpv_command_node` expects: the command name followed by its arguments.
+    pub fn command(name: &str, args: &[serde_json::Value]) -> Result<Self, String> {
+        let mut command = Vec::with_capacity(args.len() + 1);
+        command.push(serde_json::Value::String(name.to_string()));
+        command.extend_from_slice(args);
+        Self::new(&serde_json::Value::Array(command))
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut mpv_node {
+        &mut self.node
+    }
+
+    fn build(&mut self, value: &serde_json::Value) -> Result<mpv_node, String> {
+        let node = match value {
+            serde_json::Value::Null => none_node(),
+            serde_json::Value::Bool(flag) => mpv_node {
+                u: mpv_node_u {
+                    flag: *flag as c_int,
+                },
+                format: MPV_FORMAT_FLAG,
+            },
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(int64) => mpv_node {
+                    u: mpv_node_u { int64 },
+                    format: MPV_FORMAT_INT64,
+                },
+                None => mpv_node {
+                    u: mpv_node_u {
+                        double_: number.as_f64().unwrap_or_default(),
+                    },
+                    format: MPV_FORMAT_DOUBLE,
+                },
+            },
+            serde_json::Value::String(text) => mpv_node {
+                u: mpv_node_u {
+                    string: self.string(text)?,
+                },
+                format: MPV_FORMAT_STRING,
+            },
+            serde_json::Value::Array(items) => {
+                let values = items
+                    .iter()
+                    .map(|item| self.build(item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.list(values, None, MPV_FORMAT_NODE_ARRAY)
+            }
+            serde_json::Value::Object(entries) => {
+                let mut keys = Vec::with_capacity(entries.len());
+                let mut values = Vec::with_capacity(entries.len());
+                for (key, item) in entries {
+                    keys.push(self.string(key)?);
+                    values.push(self.build(item)?);
+                }
+                self.list(values, Some(keys), MPV_FORMAT_NODE_MAP)
+            }
+        };
+        Ok(node)
+    }
+
+    fn string(&mut self, text: &str) -> Result<*mut c_char, String> {
+        let c_text = CString::new(text).map_err(|e| e.to_string())?;
+        let ptr = c_text.as_ptr() as *mut c_char;
+        self._strings.push(c_text);
+        Ok(ptr)
+    }
+
+    fn list(
+        &mut self,
+        mut values: Vec<mpv_node>,
+        keys: Option<Vec<*mut c_char>>,
+        format: mpv_format,
+    ) -> mpv_node {
+        let mut list = Box::new(mpv_node_list {
+            num: values.len() as c_int,
+            values: values.as_mut_ptr(),
+            keys: std::ptr::null_mut(),
+        });
+        if let Some(mut keys) = keys {
+            list.keys = keys.as_mut_ptr();
+            self._keys.push(keys);
+        }
+        self._values.push(values);
+
+        let list_ptr: *mut mpv_node_list = &mut *list;
+        self._lists.push(list);
+
+        mpv_node {
+            u: mpv_node_u { list: list_ptr },
+            format,
+        }
+    }
+}
+
+pub fn none_node() -> mpv_node {
+    mpv_node {
+        u: mpv_node_u { int64: 0 },
+        format: MPV_FORMAT_NONE,
+    }
+}
+
+/// Maps the format names used by the plugin API to `mpv_format`.
+pub fn format_from_name(name: &str) -> Option<mpv_format> {
+    match name {
+        "string" => Some(MPV_FORMAT_STRING),
+        "flag" => Some(MPV_FORMAT_FLAG),
+        "int64" => Some(MPV_FORMAT_INT64),
+        "double" => Some(MPV_FORMAT_DOUBLE),
+        "node" => Some(MPV_FORMAT_NODE),
+        _ => None,
+    }
+}
+
+/// Converts a node to JSON. Byte arrays become base64 strings.
+///
+/// # Safety
+///
+/// `node` must be a valid node returned by libmpv.
+pub unsafe fn node_to_json(node: &mpv_node) -> serde_json::Value {
+    unsafe {
+        match node.format {
+            MPV_FORMAT_STRING => cstr_to_json(node.u.string),
+            MPV_FORMAT_FLAG => serde_json::Value::Bool(node.u.flag != 0),
+            MPV_FORMAT_INT64 => serde_json::json!(node.u.int64),
+            MPV_FORMAT_DOUBLE => double_to_json(node.u.double_),
+            MPV_FORMAT_NODE_ARRAY => {
+                let list = &*node.u.list;
+                let values = list_values(list);
+                serde_json::Value::Array(values.iter().map(|value| node_to_json(value)).collect())
+            }
+            MPV_FORMAT_NODE_MAP => {
+                let list = &*node.u.list;
+                let values = list_values(list);
+                let keys: &[*mut c_char] = if list.keys.is_null() || list.num <= 0 {
+                    &[]
+                } else {
+                    std::slice::from_raw_parts(list.keys, list.num as usize)
+                };
+                let map = keys
+                    .iter()
+                    .zip(values)
+                    .map(|(key, value)| {
+                        let key = CStr::from_ptr(*key).to_string_lossy().into_owned();
+                        (key, node_to_json(value))
+                    })
+                    .collect();
+                serde_json::Value::Object(map)
+            }
+            MPV_FORMAT_BYTE_ARRAY => {
+                let ba = &*node.u.ba;
+                let bytes = if ba.data.is_null() {
+                    &[][..]
+                } else {
+                    std::slice::from_raw_parts(ba.data as *const u8, ba.size)
+                };
+                serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(bytes))
+            }
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+/// Converts property data delivered in `format` to JSON.
+///
+/// # Safety
+///
+/// `data` must point to a value of `format`, as in `mpv_event_property`.
+pub unsafe fn data_to_json(format: mpv_format, data: *mut c_void) -> serde_json::Value {
+    if data.is_null() {
+        return serde_json::Value::Null;
+    }
+    unsafe {
+        match format {
+            MPV_FORMAT_STRING => cstr_to_json(*(data as *const *mut c_char)),
+            MPV_FORMAT_FLAG => serde_json::Value::Bool(*(data as *const c_int) != 0),
+            MPV_FORMAT_INT64 => serde_json::json!(*(data as *const i64)),
+            MPV_FORMAT_DOUBLE => double_to_json(*(data as *const f64)),
+            MPV_FORMAT_NODE => node_to_json(&*(data as *const mpv_node)),
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must be null or a valid C string.
+pub unsafe fn cstr_to_json(ptr: *const c_char) -> serde_json::Value {
+    if ptr.is_null() {
+        serde_json::Value::Null
+    } else {
+        serde_json::Value::String(
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+fn double_to_json(value: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(value)
+        .map(serde_json::Value::Number)
+        .unwrap_or(serde_json::Value::Null)
+}
+
+unsafe fn list_values(list: &mpv_node_list) -> &[mpv_node] {
+    if list.values.is_null() || list.num <= 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(list.values, list.num as usize) }
+    }
+}
@@ -0,0 +1,246 @@
+//! Software rendering with the render API, on a thread per instance.
+
+use log::error;
+use std::ffi::{c_int, c_void};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use super::sys::*;
+use super::{check, lock, Context};
+use crate::backend::{FrameSink, VideoFrame};
+
+/// Pixel format rendered by mpv. The padding byte is made opaque before delivery.
+const SW_FORMAT: &std::ffi::CStr = c"rgb0";
+
+struct RenderContext(*mut mpv_render_context);
+
+struct Shared {
+    state: Mutex<State>,
+    wakeup: Condvar,
+}
+
+struct State {
+    width: u32,
+    height: u32,
+    frames: FrameSink,
+    /// mpv signalled that the render context needs an update.
+    update: bool,
+    /// The size changed, so the current frame must be rendered again.
+    redraw: bool,
+    stop: bool,
+}
+
+/// A `sw` render context and the thread rendering its frames.
+pub(super) struct Renderer {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Renderer {
+    /// Starts a thread that creates a `sw` render context for `ctx` and renders its frames.
+    pub fn start(
+        lib: Arc<Libmpv>,
+        ctx: Context,
+        width: u32,
+        height: u32,
+        frames: FrameSink,
+    ) -> Result<Self, String> {
+        if lib.render.is_none() {
+            return Err("The loaded libmpv has no render API".to_string());
+        }
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                width,
+                height,
+                frames,
+                update: true,
+                redraw: false,
+                stop: false,
+            }),
+            wakeup: Condvar::new(),
+        });
+
+        let (tx, rx) = mpsc::channel();
+        let thread = {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name("mpv-render".to_string())
+                .spawn(move || match create_context(&lib, ctx, &shared) {
+                    Ok(render_ctx) => {
+                        let _ = tx.send(Ok(()));
+                        render_loop(&lib, render_ctx, &shared);
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                    }
+                })
+                .map_err(|e| format!("Failed to spawn the mpv render thread: {}", e))?
+        };
+
+        match rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                shared,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err("The mpv render thread exited early".to_string())
+            }
+        }
+    }
+
+    /// Changes the frame size, and the sink if given, and renders the current frame again.
+    pub fn update(&self, width: u32, height: u32, frames: Option<FrameSink>) {
+        let mut state = lock(&self.shared.state);
+        state.width = width;
+        state.height = height;
+        if let Some(frames) = frames {
+            state.frames = frames;
+        }
+        state.redraw = true;
+        self.shared.wakeup.notify_one();
+    }
+
+    /// Frees the render context. Must happen before the core is destroyed.
+    pub fn stop(mut self) {
+        lock(&self.shared.state).stop = true;
+        self.shared.wakeup.notify_one();
+
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("mpv render thread panicked.");
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn on_update(data: *mut c_void) {
+    let shared = unsafe { &*(data as *const Shared) };
+    lock(&shared.state).update = true;
+    shared.wakeup.notify_one();
+}
+
+fn create_context(
+    lib: &Libmpv,
+    ctx: Context,
+    shared: &Arc<Shared>,
+) -> Result<RenderContext, String> {
+    let Some(render) = &lib.render else {
+        return Err("The loaded libmpv has no render API".to_string());
+    };
+
+    let mut params = [
+        param(
+            MPV_RENDER_PARAM_API_TYPE,
+            MPV_RENDER_API_TYPE_SW.as_ptr() as *mut c_void,
+        ),
+        param(MPV_RENDER_PARAM_INVALID, std::ptr::null_mut()),
+    ];
+    let mut render_ctx = std::ptr::null_mut();
+    check(lib, unsafe {
+        (render.mpv_render_context_create)(&mut render_ctx, ctx.0, params.as_mut_ptr())
+    })
+    .map_err(|e| format!("Failed to create the sw render context: {}", e))?;
+
+    // `render_loop` unsets the callback before its caller drops `shared`.
+    unsafe {
+        (render.mpv_render_context_set_update_callback)(
+            render_ctx,
+            Some(on_update),
+            Arc::as_ptr(shared) as *mut c_void,
+        )
+    };
+    Ok(RenderContext(render_ctx))
+}
+
+fn render_loop(lib: &Libmpv, render_ctx: RenderContext, shared: &Shared) {
+    let Some(render) = &lib.render else {
+        return;
+    };
+
+    loop {
+        let (width, height, frames, redraw) = {
+            let mut state = lock(&shared.state);
+            while !state.update && !state.redraw && !state.stop {
+                state = match shared.wakeup.wait(state) {
+                    Ok(guard) => guard,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+            }
+            if state.stop {
+                break;
+            }
+            state.update = false;
+            let redraw = std::mem::take(&mut state.redraw);
+            (state.width, state.height, state.frames.clone(), redraw)
+        };
+
+        let flags = unsafe { (render.mpv_render_context_update)(render_ctx.0) };
+        if flags & MPV_RENDER_UPDATE_FRAME == 0 && !redraw {
+            continue;
+        }
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        match render_frame(lib, render, &render_ctx, width, height) {
+            Ok(pixels) => frames(VideoFrame {
+                width,
+                height,
+                pixels,
+            }),
+            Err(e) => error!("Failed to render an mpv frame: {}", e),
+        }
+    }
+
+    unsafe {
+        (render.mpv_render_context_set_update_callback)(render_ctx.0, None, std::ptr::null_mut());
+        (render.mpv_render_context_free)(render_ctx.0);
+    }
+}
+
+fn render_frame(
+    lib: &Libmpv,
+    render: &RenderApi,
+    render_ctx: &RenderContext,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, String> {
+    let mut size = [width as c_int, height as c_int];
+    let mut stride = width as usize * 4;
+    let mut pixels = vec![0u8; stride * height as usize];
+
+    let mut params = [
+        param(MPV_RENDER_PARAM_SW_SIZE, size.as_mut_ptr() as *mut c_void),
+        param(
+            MPV_RENDER_PARAM_SW_FORMAT,
+            SW_FORMAT.as_ptr() as *mut c_void,
+        ),
+        param(
+            MPV_RENDER_PARAM_SW_STRIDE,
+            &mut stride as *mut usize as *mut c_void,
+        ),
+        param(
+            MPV_RENDER_PARAM_SW_POINTER,
+            pixels.as_mut_ptr() as *mut c_void,
+        ),
+        param(MPV_RENDER_PARAM_INVALID, std::ptr::null_mut()),
+    ];
+    check(lib, unsafe {
+        (render.mpv_render_context_render)(render_ctx.0, params.as_mut_ptr())
+    })?;
+
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel[3] = 0xff;
+    }
+    Ok(pixels)
+}
+
+fn param(type_: mpv_render_param_type, data: *mut c_void) -> mpv_render_param {
+    mpv_render_param { type_, data }
+}
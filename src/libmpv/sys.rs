@@ -0,0 +1,299 @@
+//! Hand-written subset of the libmpv client API (`client.h`), loaded at runtime.
+
+#![allow(non_camel_case_types)]
+
+use std::ffi::{c_char, c_double, c_int, c_ulong, c_void, CStr};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_handle {
+    _unused: [u8; 0],
+}
+
+pub type mpv_format = c_int;
+pub const MPV_FORMAT_NONE: mpv_format = 0;
+pub const MPV_FORMAT_STRING: mpv_format = 1;
+pub const MPV_FORMAT_FLAG: mpv_format = 3;
+pub const MPV_FORMAT_INT64: mpv_format = 4;
+pub const MPV_FORMAT_DOUBLE: mpv_format = 5;
+pub const MPV_FORMAT_NODE: mpv_format = 6;
+pub const MPV_FORMAT_NODE_ARRAY: mpv_format = 7;
+pub const MPV_FORMAT_NODE_MAP: mpv_format = 8;
+pub const MPV_FORMAT_BYTE_ARRAY: mpv_format = 9;
+
+pub type mpv_event_id = c_int;
+pub const MPV_EVENT_NONE: mpv_event_id = 0;
+pub const MPV_EVENT_SHUTDOWN: mpv_event_id = 1;
+pub const MPV_EVENT_LOG_MESSAGE: mpv_event_id = 2;
+pub const MPV_EVENT_GET_PROPERTY_REPLY: mpv_event_id = 3;
+pub const MPV_EVENT_SET_PROPERTY_REPLY: mpv_event_id = 4;
+pub const MPV_EVENT_COMMAND_REPLY: mpv_event_id = 5;
+pub const MPV_EVENT_START_FILE: mpv_event_id = 6;
+pub const MPV_EVENT_END_FILE: mpv_event_id = 7;
+pub const MPV_EVENT_CLIENT_MESSAGE: mpv_event_id = 16;
+pub const MPV_EVENT_PROPERTY_CHANGE: mpv_event_id = 22;
+pub const MPV_EVENT_HOOK: mpv_event_id = 25;
+
+pub const MPV_END_FILE_REASON_EOF: c_int = 0;
+pub const MPV_END_FILE_REASON_STOP: c_int = 2;
+pub const MPV_END_FILE_REASON_QUIT: c_int = 3;
+pub const MPV_END_FILE_REASON_ERROR: c_int = 4;
+pub const MPV_END_FILE_REASON_REDIRECT: c_int = 5;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union mpv_node_u {
+    pub string: *mut c_char,
+    pub flag: c_int,
+    pub int64: i64,
+    pub double_: c_double,
+    pub list: *mut mpv_node_list,
+    pub ba: *mut mpv_byte_array,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct mpv_node {
+    pub u: mpv_node_u,
+    pub format: mpv_format,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_node_list {
+    pub num: c_int,
+    pub values: *mut mpv_node,
+    pub keys: *mut *mut c_char,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_byte_array {
+    pub data: *mut c_void,
+    pub size: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_event {
+    pub event_id: mpv_event_id,
+    pub error: c_int,
+    pub reply_userdata: u64,
+    pub data: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_event_property {
+    pub name: *const c_char,
+    pub format: mpv_format,
+    pub data: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_event_log_message {
+    pub prefix: *const c_char,
+    pub level: *const c_char,
+    pub text: *const c_char,
+    pub log_level: c_int,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_event_start_file {
+    pub playlist_entry_id: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_event_end_file {
+    pub reason: c_int,
+    pub error: c_int,
+    pub playlist_entry_id: i64,
+    pub playlist_insert_id: i64,
+    pub playlist_insert_num_entries: c_int,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_event_client_message {
+    pub num_args: c_int,
+    pub args: *mut *const c_char,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_event_hook {
+    pub name: *const c_char,
+    pub id: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct mpv_event_command {
+    pub result: mpv_node,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_render_context {
+    _unused: [u8; 0],
+}
+
+pub type mpv_render_param_type = c_int;
+pub const MPV_RENDER_PARAM_INVALID: mpv_render_param_type = 0;
+pub const MPV_RENDER_PARAM_API_TYPE: mpv_render_param_type = 1;
+pub const MPV_RENDER_PARAM_SW_SIZE: mpv_render_param_type = 17;
+pub const MPV_RENDER_PARAM_SW_FORMAT: mpv_render_param_type = 18;
+pub const MPV_RENDER_PARAM_SW_STRIDE: mpv_render_param_type = 19;
+pub const MPV_RENDER_PARAM_SW_POINTER: mpv_render_param_type = 20;
+
+pub const MPV_RENDER_API_TYPE_SW: &CStr = c"sw";
+pub const MPV_RENDER_UPDATE_FRAME: u64 = 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct mpv_render_param {
+    pub type_: mpv_render_param_type,
+    pub data: *mut c_void,
+}
+
+pub type mpv_render_update_fn = Option<unsafe extern "C" fn(cb_ctx: *mut c_void)>;
+
+/// Function pointers of the render API (`render.h`).
+pub struct RenderApi {
+    pub mpv_render_context_create: unsafe extern "C" fn(
+        res: *mut *mut mpv_render_context,
+        mpv: *mut mpv_handle,
+        params: *mut mpv_render_param,
+    ) -> c_int,
+    pub mpv_render_context_set_update_callback: unsafe extern "C" fn(
+        ctx: *mut mpv_render_context,
+        callback: mpv_render_update_fn,
+        callback_ctx: *mut c_void,
+    ),
+    pub mpv_render_context_update: unsafe extern "C" fn(ctx: *mut mpv_render_context) -> u64,
+    pub mpv_render_context_render:
+        unsafe extern "C" fn(ctx: *mut mpv_render_context, params: *mut mpv_render_param) -> c_int,
+    pub mpv_render_context_free: unsafe extern "C" fn(ctx: *mut mpv_render_context),
+}
+
+/// Function pointers resolved from a libmpv shared library.
+pub struct Libmpv {
+    _library: libloading::Library,
+    pub mpv_client_api_version: unsafe extern "C" fn() -> c_ulong,
+    pub mpv_error_string: unsafe extern "C" fn(error: c_int) -> *const c_char,
+    pub mpv_free: unsafe extern "C" fn(data: *mut c_void),
+    pub mpv_create: unsafe extern "C" fn() -> *mut mpv_handle,
+    pub mpv_initialize: unsafe extern "C" fn(ctx: *mut mpv_handle) -> c_int,
+    pub mpv_terminate_destroy: unsafe extern "C" fn(ctx: *mut mpv_handle),
+    pub mpv_set_option_string: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        name: *const c_char,
+        data: *const c_char,
+    ) -> c_int,
+    pub mpv_set_option: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        name: *const c_char,
+        format: mpv_format,
+        data: *mut c_void,
+    ) -> c_int,
+    pub mpv_command_node: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        args: *mut mpv_node,
+        result: *mut mpv_node,
+    ) -> c_int,
+    pub mpv_set_property: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        name: *const c_char,
+        format: mpv_format,
+        data: *mut c_void,
+    ) -> c_int,
+    pub mpv_get_property: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        name: *const c_char,
+        format: mpv_format,
+        data: *mut c_void,
+    ) -> c_int,
+    pub mpv_observe_property: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        reply_userdata: u64,
+        name: *const c_char,
+        format: mpv_format,
+    ) -> c_int,
+    pub mpv_unobserve_property:
+        unsafe extern "C" fn(ctx: *mut mpv_handle, registered_reply_userdata: u64) -> c_int,
+    pub mpv_command_node_async: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        reply_userdata: u64,
+        args: *mut mpv_node,
+    ) -> c_int,
+    pub mpv_hook_add: unsafe extern "C" fn(
+        ctx: *mut mpv_handle,
+        reply_userdata: u64,
+        name: *const c_char,
+        priority: c_int,
+    ) -> c_int,
+    pub mpv_hook_continue: unsafe extern "C" fn(ctx: *mut mpv_handle, id: u64) -> c_int,
+    pub mpv_event_name: unsafe extern "C" fn(event: mpv_event_id) -> *const c_char,
+    pub mpv_wait_event:
+        unsafe extern "C" fn(ctx: *mut mpv_handle, timeout: c_double) -> *mut mpv_event,
+    pub mpv_wakeup: unsafe extern "C" fn(ctx: *mut mpv_handle),
+    pub mpv_free_node_contents: unsafe extern "C" fn(node: *mut mpv_node),
+    /// `None` for builds without the render API.
+    pub render: Option<RenderApi>,
+}
+
+impl Libmpv {
+    /// Loads libmpv from `path`.
+    ///
+    /// # Safety
+    ///
+    /// `path` must point to a libmpv build exposing the client API version 2.
+    pub unsafe fn new<P>(path: P) -> Result<Self, libloading::Error>
+    where
+        P: AsRef<std::ffi::OsStr>,
+    {
+        let library = unsafe { libloading::Library::new(path) }?;
+        unsafe {
+            Ok(Libmpv {
+                mpv_client_api_version: *library.get(b"mpv_client_api_version\0")?,
+                mpv_error_string: *library.get(b"mpv_error_string\0")?,
+                mpv_free: *library.get(b"mpv_free\0")?,
+                mpv_create: *library.get(b"mpv_create\0")?,
+                mpv_initialize: *library.get(b"mpv_initialize\0")?,
+                mpv_terminate_destroy: *library.get(b"mpv_terminate_destroy\0")?,
+                mpv_set_option_string: *library.get(b"mpv_set_option_string\0")?,
+                mpv_set_option: *library.get(b"mpv_set_option\0")?,
+                mpv_command_node: *library.get(b"mpv_command_node\0")?,
+                mpv_set_property: *library.get(b"mpv_set_property\0")?,
+                mpv_get_property: *library.get(b"mpv_get_property\0")?,
+                mpv_observe_property: *library.get(b"mpv_observe_property\0")?,
+                mpv_unobserve_property: *library.get(b"mpv_unobserve_property\0")?,
+                mpv_command_node_async: *library.get(b"mpv_command_node_async\0")?,
+                mpv_hook_add: *library.get(b"mpv_hook_add\0")?,
+                mpv_hook_continue: *library.get(b"mpv_hook_continue\0")?,
+                mpv_event_name: *library.get(b"mpv_event_name\0")?,
+                mpv_wait_event: *library.get(b"mpv_wait_event\0")?,
+                mpv_wakeup: *library.get(b"mpv_wakeup\0")?,
+                mpv_free_node_contents: *library.get(b"mpv_free_node_contents\0")?,
+                render: load_render_api(&library).ok(),
+                _library: library,
+            })
+        }
+    }
+}
+
+unsafe fn load_render_api(library: &libloading::Library) -> Result<RenderApi, libloading::Error> {
+    unsafe {
+        Ok(RenderApi {
+            mpv_render_context_create: *library.get(b"mpv_render_context_create\0")?,
+            mpv_render_context_set_update_callback: *library
+                .get(b"mpv_render_context_set_update_callback\0")?,
+            mpv_render_context_update: *library.get(b"mpv_render_context_update\0")?,
+            mpv_render_context_render: *library.get(b"mpv_render_context_render\0")?,
+            mpv_render_context_free: *library.get(b"mpv_render_context_free\0")?,
+        })
+    }
+}
@@ -0,0 +1,367 @@
+use log::{info, trace};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::backend::WrapperBackend;
+#[cfg(feature = "libmpv")]
+use crate::libmpv::{self, Libmpv, LIBMPV_LIB_NAMES};
+use crate::{Error, Result};
+
+/// Environment variable pointing at a `libmpv-wrapper` library file.
+pub const WRAPPER_ENV: &str = "TAURI_PLUGIN_LIBMPV_WRAPPER";
+
+/// Environment variable pointing at a libmpv library file, used by the `libmpv` backend.
+#[cfg(feature = "libmpv")]
+pub const LIBMPV_ENV: &str = "TAURI_PLUGIN_LIBMPV_LIBRARY";
+
+#[cfg(target_os = "windows")]
+pub const WRAPPER_LIB_NAME: &str = "libmpv-wrapper.dll";
+#[cfg(target_os = "macos")]
+pub const WRAPPER_LIB_NAME: &str = "libmpv-wrapper.dylib";
+#[cfg(target_os = "linux")]
+pub const WRAPPER_LIB_NAME: &str = "libmpv-wrapper.so";
+
+/// A place a library may be loaded from.
+#[derive(Debug, Clone)]
+struct Candidate {
+    source: &'static str,
+    path: PathBuf,
+}
+
+/// Loads `libmpv-wrapper` from the first candidate that works.
+///
+/// Candidates are tried in this order: `configured_paths`, [`WRAPPER_ENV`],
+/// `extra_dirs`, the Tauri resource directory, the executable directory and
+/// the system library paths. Wrappers with an unsupported interface version
+/// are skipped. The error lists every attempt.
+pub fn load_wrapper<R: Runtime>(
+    app: &AppHandle<R>,
+    configured_paths: &[PathBuf],
+    extra_dirs: &[PathBuf],
+) -> Result<WrapperBackend> {
+    let candidates = candidates(
+        app,
+        configured_paths,
+        WRAPPER_ENV,
+        extra_dirs,
+        &[WRAPPER_LIB_NAME],
+    );
+    load_first("libmpv-wrapper", candidates, |path| {
+        unsafe { WrapperBackend::load(path) }.map_err(|e| e.to_string())
+    })
+}
+
+/// Finds the first existing `libmpv-wrapper` of the [`load_wrapper`] candidates, for the
+/// `process` backend.
+///
+/// Nothing is loaded into this process. The host checks the wrapper when it loads it.
+pub fn locate_wrapper<R: Runtime>(
+    app: &AppHandle<R>,
+    configured_paths: &[PathBuf],
+    extra_dirs: &[PathBuf],
+) -> Result<PathBuf> {
+    let candidates = candidates(
+        app,
+        configured_paths,
+        WRAPPER_ENV,
+        extra_dirs,
+        &[WRAPPER_LIB_NAME],
+    );
+    load_first("libmpv-wrapper", candidates, |path| Ok(path.to_path_buf()))
+}
+
+/// Loads libmpv itself, searching the same places as [`load_wrapper`] with [`LIBMPV_ENV`]
+/// and its own `extra_dirs`.
+#[cfg(feature = "libmpv")]
+pub fn load_libmpv<R: Runtime>(
+    app: &AppHandle<R>,
+    configured_paths: &[PathBuf],
+    extra_dirs: &[PathBuf],
+) -> Result<Libmpv> {
+    let candidates = candidates(
+        app,
+        configured_paths,
+        LIBMPV_ENV,
+        extra_dirs,
+        LIBMPV_LIB_NAMES,
+    );
+    load_first("libmpv", candidates, |path| {
+        let lib = unsafe { Libmpv::new(path) }.map_err(|e| e.to_string())?;
+        match libmpv::client_api_version(&lib) {
+            (2, _) => Ok(lib),
+            (major, minor) => Err(format!(
+                "unsupported client API version {}.{}, expected 2.x",
+                major, minor
+            )),
+        }
+    })
+}
+
+fn load_first<T>(
+    name: &str,
+    candidates: Vec<Candidate>,
+    open: impl Fn(&Path) -> std::result::Result<T, String>,
+) -> Result<T> {
+    let mut failures = Vec::new();
+
+    for candidate in candidates {
+        // Bare names are resolved by the dynamic loader, so they can't be checked up front.
+        let is_bare_name = candidate.path.parent() == Some(Path::new(""));
+        if !is_bare_name && !candidate.path.exists() {
+            trace!(
+                "{} not found at '{}' ({}).",
+                name,
+                candidate.path.display(),
+                candidate.source
+            );
+            failures.push(format!(
+                "{} '{}': not found",
+                candidate.source,
+                candidate.path.display()
+            ));
+            continue;
+        }
+
+        info!(
+            "Attempting to load {} from: {} ({})",
+            name,
+            candidate.path.display(),
+            candidate.source
+        );
+        match open(&candidate.path) {
+            Ok(library) => {
+                info!("Successfully loaded {}.", name);
+                return Ok(library);
+            }
+            Err(e) => failures.push(format!(
+                "{} '{}': {}",
+                candidate.source,
+                candidate.path.display(),
+                e
+            )),
+        }
+    }
+
+    Err(Error::FFI(format!(
+        "Failed to load {}. Tried:\n  {}",
+        name,
+        failures.join("\n  ")
+    )))
+}
+
+fn candidates<R: Runtime>(
+    app: &AppHandle<R>,
+    configured_paths: &[PathBuf],
+    env_var: &'static str,
+    extra_dirs: &[PathBuf],
+    lib_names: &[&str],
+) -> Vec<Candidate> {
+    let locations = Locations {
+        configured_paths,
+        env_var,
+        env_path: std::env::var_os(env_var)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from),
+        extra_dirs,
+        resource_dir: app.path().resource_dir().ok(),
+        exe_dir: std::env::current_exe()
+            .ok()
+            .and_then(|exe_path| exe_path.parent().map(|dir| dir.to_path_buf())),
+        system_dirs: system_dirs(),
+    };
+    locations.candidates(lib_names)
+}
+
+/// Everything a library is searched in, looked up once so the order can be built without an app.
+struct Locations<'a> {
+    configured_paths: &'a [PathBuf],
+    env_var: &'static str,
+    env_path: Option<PathBuf>,
+    extra_dirs: &'a [PathBuf],
+    resource_dir: Option<PathBuf>,
+    exe_dir: Option<PathBuf>,
+    system_dirs: Vec<PathBuf>,
+}
+
+impl Locations<'_> {
+    /// The candidates in the order they are tried, without duplicates.
+    fn candidates(&self, lib_names: &[&str]) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+
+        for configured_path in self.configured_paths {
+            let path = match &self.exe_dir {
+                Some(exe_dir) if configured_path.is_relative() => exe_dir.join(configured_path),
+                _ => configured_path.clone(),
+            };
+            candidates.push(Candidate {
+                source: "config",
+                path,
+            });
+        }
+
+        if let Some(path) = &self.env_path {
+            candidates.push(Candidate {
+                source: self.env_var,
+                path: path.clone(),
+            });
+        }
+
+        let mut push_dir = |source: &'static str, dir: &Path| {
+            for lib_name in lib_names {
+                candidates.push(Candidate {
+                    source,
+                    path: dir.join(lib_name),
+                });
+            }
+        };
+
+        for dir in self.extra_dirs {
+            push_dir("builder", dir);
+        }
+
+        if let Some(resource_dir) = &self.resource_dir {
+            push_dir("resource dir", &resource_dir.join("lib"));
+            push_dir("resource dir", resource_dir);
+        }
+
+        if let Some(exe_dir) = &self.exe_dir {
+            let mut dirs = vec![exe_dir.clone(), exe_dir.join("lib")];
+            // `cargo tauri dev` runs from `src-tauri/target/<profile>`.
+            if let Some(src_tauri_dir) = exe_dir.parent().and_then(|dir| dir.parent()) {
+                dirs.push(src_tauri_dir.join("lib"));
+            }
+            // AppImage bundles libraries in `usr/lib` next to `usr/bin`.
+            if let Some(prefix) = exe_dir.parent() {
+                dirs.push(prefix.join("lib"));
+            }
+            for dir in dirs {
+                push_dir("exe dir", &dir);
+            }
+        }
+
+        for dir in &self.system_dirs {
+            push_dir("system", dir);
+        }
+
+        for lib_name in lib_names {
+            candidates.push(Candidate {
+                source: "dynamic loader",
+                path: PathBuf::from(lib_name),
+            });
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        candidates.retain(|candidate| seen.insert(candidate.path.clone()));
+        candidates
+    }
+}
+
+fn system_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(app_dir) = std::env::var_os("APPDIR") {
+            dirs.push(PathBuf::from(app_dir).join("usr/lib"));
+        }
+        // Flatpak installs app libraries in `/app/lib`.
+        dirs.push(PathBuf::from("/app/lib"));
+        dirs.push(PathBuf::from("/usr/local/lib"));
+        dirs.push(PathBuf::from("/usr/lib"));
+        dirs.push(PathBuf::from(format!(
+            "/usr/lib/{}-linux-gnu",
+            std::env::consts::ARCH
+        )));
+        dirs.push(PathBuf::from("/usr/lib64"));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        dirs.push(PathBuf::from("/opt/homebrew/lib"));
+        dirs.push(PathBuf::from("/usr/local/lib"));
+    }
+
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locations;
+    use std::path::PathBuf;
+
+    fn candidates(locations: &Locations, lib_names: &[&str]) -> Vec<(&'static str, PathBuf)> {
+        locations
+            .candidates(lib_names)
+            .into_iter()
+            .map(|candidate| (candidate.source, candidate.path))
+            .collect()
+    }
+
+    #[test]
+    fn orders_candidates_by_source() {
+        let configured_paths = [
+            PathBuf::from("lib/libmpv-wrapper.so"),
+            PathBuf::from("/opt/wrapper.so"),
+        ];
+        let extra_dirs = [PathBuf::from("/extra")];
+        let locations = Locations {
+            configured_paths: &configured_paths,
+            env_var: "WRAPPER",
+            env_path: Some(PathBuf::from("/env/wrapper.so")),
+            extra_dirs: &extra_dirs,
+            resource_dir: Some(PathBuf::from("/res")),
+            exe_dir: Some(PathBuf::from("/app/src-tauri/target/debug")),
+            system_dirs: vec![PathBuf::from("/usr/lib")],
+        };
+
+        let expected: Vec<(&str, PathBuf)> = [
+            (
+                "config",
+                "/app/src-tauri/target/debug/lib/libmpv-wrapper.so",
+            ),
+            ("config", "/opt/wrapper.so"),
+            ("WRAPPER", "/env/wrapper.so"),
+            ("builder", "/extra/w.so"),
+            ("resource dir", "/res/lib/w.so"),
+            ("resource dir", "/res/w.so"),
+            ("exe dir", "/app/src-tauri/target/debug/w.so"),
+            ("exe dir", "/app/src-tauri/target/debug/lib/w.so"),
+            ("exe dir", "/app/src-tauri/lib/w.so"),
+            ("exe dir", "/app/src-tauri/target/lib/w.so"),
+            ("system", "/usr/lib/w.so"),
+            ("dynamic loader", "w.so"),
+        ]
+        .into_iter()
+        .map(|(source, path)| (source, PathBuf::from(path)))
+        .collect();
+        assert_eq!(candidates(&locations, &["w.so"]), expected);
+    }
+
+    #[test]
+    fn skips_missing_locations_and_duplicates() {
+        let configured_paths = [PathBuf::from("/usr/lib/a.so")];
+        let extra_dirs = [PathBuf::from("/usr/lib"), PathBuf::from("/usr/lib")];
+        let locations = Locations {
+            configured_paths: &configured_paths,
+            env_var: "WRAPPER",
+            env_path: None,
+            extra_dirs: &extra_dirs,
+            resource_dir: None,
+            exe_dir: None,
+            system_dirs: vec![PathBuf::from("/usr/lib"), PathBuf::from("/lib")],
+        };
+
+        let expected: Vec<(&str, PathBuf)> = [
+            ("config", "/usr/lib/a.so"),
+            ("builder", "/usr/lib/b.so"),
+            ("system", "/lib/a.so"),
+            ("system", "/lib/b.so"),
+            ("dynamic loader", "a.so"),
+            ("dynamic loader", "b.so"),
+        ]
+        .into_iter()
+        .map(|(source, path)| (source, PathBuf::from(path)))
+        .collect();
+        assert_eq!(candidates(&locations, &["a.so", "b.so"]), expected);
+    }
+}
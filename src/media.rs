@@ -0,0 +1,128 @@
+//! Typed views of the `node` properties mpv describes media with.
+
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::models::{Chapter, Edition, PlaylistEntry, Track, TrackKind};
+
+/// Parses `track-list`, skipping entries of unknown types.
+pub(crate) fn parse_tracks(node: &Value) -> Vec<Track> {
+    entries(node).filter_map(parse_track).collect()
+}
+
+/// Parses `chapter-list`, computing each end from the next start or `duration`.
+pub(crate) fn parse_chapters(node: &Value, duration: Option<f64>) -> Vec<Chapter> {
+    let starts: Vec<(Option<String>, f64)> = entries(node)
+        .map(|entry| {
+            (
+                string(entry, "title"),
+                entry["time"].as_f64().unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, (title, start))| Chapter {
+            index,
+            title: title.clone(),
+            start: *start,
+            end: starts.get(index + 1).map(|(_, next)| *next).or(duration),
+        })
+        .collect()
+}
+
+/// Parses `edition-list`, marking `current` as selected.
+pub(crate) fn parse_editions(node: &Value, current: Option<i64>) -> Vec<Edition> {
+    entries(node)
+        .filter_map(|entry| {
+            let id = entry["id"].as_i64()?;
+            Some(Edition {
+                id,
+                title: string(entry, "title"),
+                default: flag(entry, "default"),
+                selected: current == Some(id),
+            })
+        })
+        .collect()
+}
+
+/// Parses `playlist`, skipping entries without a file name.
+pub(crate) fn parse_playlist(node: &Value) -> Vec<PlaylistEntry> {
+    entries(node)
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            Some(PlaylistEntry {
+                // mpv before 0.33 has no entry IDs.
+                id: entry["id"].as_i64().unwrap_or_default(),
+                index,
+                filename: string(entry, "filename")?,
+                title: string(entry, "title"),
+                current: flag(entry, "current"),
+                playing: flag(entry, "playing"),
+            })
+        })
+        .collect()
+}
+
+/// Parses `metadata`, turning non-string values into their JSON text.
+pub(crate) fn parse_metadata(node: &Value) -> IndexMap<String, String> {
+    node.as_object()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+fn parse_track(entry: &Value) -> Option<Track> {
+    let kind = match entry["type"].as_str()? {
+        "video" => TrackKind::Video,
+        "audio" => TrackKind::Audio,
+        "sub" => TrackKind::Sub,
+        _ => return None,
+    };
+
+    Some(Track {
+        id: entry["id"].as_i64()?,
+        kind,
+        title: string(entry, "title"),
+        lang: string(entry, "lang"),
+        codec: string(entry, "codec"),
+        default: flag(entry, "default"),
+        forced: flag(entry, "forced"),
+        selected: flag(entry, "selected"),
+        external: flag(entry, "external"),
+        external_filename: string(entry, "external-filename"),
+        bitrate: entry["demux-bitrate"].as_u64(),
+        channels: number(entry, "demux-channel-count"),
+        samplerate: number(entry, "demux-samplerate"),
+        width: number(entry, "demux-w"),
+        height: number(entry, "demux-h"),
+        fps: entry["demux-fps"].as_f64(),
+    })
+}
+
+fn entries(node: &Value) -> impl Iterator<Item = &Value> {
+    node.as_array().into_iter().flatten()
+}
+
+fn string(entry: &Value, key: &str) -> Option<String> {
+    entry[key].as_str().map(str::to_string)
+}
+
+fn flag(entry: &Value, key: &str) -> bool {
+    entry[key].as_bool().unwrap_or_default()
+}
+
+fn number(entry: &Value, key: &str) -> Option<u32> {
+    entry[key]
+        .as_u64()
+        .and_then(|value| u32::try_from(value).ok())
+}
@@ -1,19 +1,21 @@
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::ffi::{c_char, c_void};
-use tauri::{AppHandle, Runtime};
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::wrapper::MpvHandle;
+use crate::backend::BackendInstance;
+use crate::queue::Queue;
+use crate::resume::ResumeTracker;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct MpvInstance {
-    pub handle: *mut MpvHandle,
-    pub event_userdata: *mut c_void,
+    pub backend: Arc<dyn BackendInstance>,
+    pub close_policy: ClosePolicy,
+    pub config_dir: Option<PathBuf>,
+    pub(crate) queue: Arc<Queue>,
+    pub(crate) resume: Option<Arc<ResumeTracker>>,
 }
 
-unsafe impl Send for MpvInstance {}
-unsafe impl Sync for MpvInstance {}
-
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MpvConfig {
@@ -21,6 +23,366 @@ pub struct MpvConfig {
     pub initial_options: IndexMap<String, serde_json::Value>,
     #[serde(default)]
     pub observed_properties: IndexMap<String, String>,
+    #[serde(default)]
+    pub close_policy: Option<ClosePolicy>,
+    /// Name of a built-in or registered preset applied before `initial_options`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Directory mpv loads `mpv.conf`, `input.conf` and profiles from.
+    #[serde(default)]
+    pub config_dir: Option<ConfigDir>,
+    #[serde(default)]
+    pub render_mode: RenderMode,
+}
+
+/// How the video of an instance gets into the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderMode {
+    /// mpv draws into the native window through `wid`, behind a transparent webview.
+    #[default]
+    Window,
+    /// mpv renders frames in software with `vo=libmpv`, which the frontend draws itself.
+    Software,
+}
+
+/// Where an instance's mpv config directory is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigDir {
+    /// `mpv` inside the app config directory.
+    AppConfig,
+    /// `mpv` inside the bundled resource directory.
+    Resource,
+    /// An explicit directory.
+    Path(PathBuf),
+}
+
+/// An entry of mpv's `profile-list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub profile_desc: Option<String>,
+    #[serde(default)]
+    pub profile_cond: Option<String>,
+    #[serde(default)]
+    pub profile_restore: Option<String>,
+    #[serde(default)]
+    pub options: Vec<ProfileOption>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileOption {
+    pub key: String,
+    pub value: String,
+}
+
+/// How an mpv core is shut down when the plugin tears an instance down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClosePolicy {
+    /// Terminate the core without saving state.
+    #[default]
+    Quit,
+    /// Run `quit-watch-later` first so the playback position is saved.
+    QuitWatchLater,
+}
+
+/// What a screenshot shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScreenshotMode {
+    /// The video frame with subtitles.
+    #[default]
+    Subtitles,
+    /// The video frame only.
+    Video,
+    /// The scaled window content, including the OSD.
+    Window,
+}
+
+impl ScreenshotMode {
+    /// The flag passed to mpv's `screenshot-to-file`.
+    pub fn flag(self) -> &'static str {
+        match self {
+            ScreenshotMode::Subtitles => "subtitles",
+            ScreenshotMode::Video => "video",
+            ScreenshotMode::Window => "window",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    /// The file extension mpv picks the encoder from.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenshotOptions {
+    #[serde(default)]
+    pub mode: ScreenshotMode,
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// File name template for saved screenshots, overriding `plugins.libmpv.screenshotTemplate`.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// Layout of the storyboard made by `generate_storyboard`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StoryboardOptions {
+    /// Seconds between two thumbnails.
+    pub interval: f64,
+    /// Thumbnails per row of a sprite sheet.
+    pub columns: u32,
+    /// Rows per sprite sheet. Long videos get several sheets.
+    pub rows: u32,
+    /// Width of one thumbnail in pixels. The height keeps the aspect ratio.
+    pub width: u32,
+}
+
+impl Default for StoryboardOptions {
+    fn default() -> Self {
+        Self {
+            interval: 10.0,
+            columns: 10,
+            rows: 10,
+            width: 160,
+        }
+    }
+}
+
+/// Sprite sheets and the WebVTT file mapping time ranges to their regions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Storyboard {
+    /// JPEG sprite sheets, in order.
+    pub sheets: Vec<PathBuf>,
+    /// WebVTT file whose cues point to `sheet.jpg#xywh=x,y,w,h`, relative to it.
+    pub vtt: PathBuf,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Number of thumbnails over all sheets.
+    pub count: u32,
+}
+
+/// The kind of a [`Track`], as in mpv's `track-list`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrackKind {
+    Video,
+    Audio,
+    Sub,
+}
+
+impl TrackKind {
+    /// The property selecting a track of this kind.
+    pub fn property(self) -> &'static str {
+        match self {
+            TrackKind::Video => "vid",
+            TrackKind::Audio => "aid",
+            TrackKind::Sub => "sid",
+        }
+    }
+
+    /// The command loading an external track of this kind.
+    pub fn add_command(self) -> &'static str {
+        match self {
+            TrackKind::Video => "video-add",
+            TrackKind::Audio => "audio-add",
+            TrackKind::Sub => "sub-add",
+        }
+    }
+
+    /// The command removing an external track of this kind.
+    pub fn remove_command(self) -> &'static str {
+        match self {
+            TrackKind::Video => "video-remove",
+            TrackKind::Audio => "audio-remove",
+            TrackKind::Sub => "sub-remove",
+        }
+    }
+}
+
+/// An entry of mpv's `track-list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Track {
+    /// The ID selected through `vid`, `aid` or `sid`.
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: TrackKind,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+    pub codec: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+    pub selected: bool,
+    pub external: bool,
+    /// The file an external track was loaded from.
+    pub external_filename: Option<String>,
+    /// Bits per second, as reported by the demuxer.
+    pub bitrate: Option<u64>,
+    pub channels: Option<u32>,
+    pub samplerate: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+}
+
+/// How `add_external_track` loads a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExternalTrackOptions {
+    /// Select the track once it is added. Defaults to `true`.
+    pub select: bool,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+}
+
+impl Default for ExternalTrackOptions {
+    fn default() -> Self {
+        Self {
+            select: true,
+            title: None,
+            lang: None,
+        }
+    }
+}
+
+/// An entry of mpv's `edition-list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Edition {
+    /// The ID selected through `edition`.
+    pub id: i64,
+    pub title: Option<String>,
+    pub default: bool,
+    /// Whether it is `current-edition`.
+    pub selected: bool,
+}
+
+/// An entry of mpv's `chapter-list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    pub index: usize,
+    pub title: Option<String>,
+    /// Start in seconds.
+    pub start: f64,
+    /// Start of the next chapter, or the duration for the last one if known.
+    pub end: Option<f64>,
+}
+
+/// An entry of mpv's `playlist`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistEntry {
+    /// Stable while the entry is in the playlist, unlike its index.
+    pub id: i64,
+    pub index: usize,
+    pub filename: String,
+    pub title: Option<String>,
+    /// The entry playback is on or moving to.
+    pub current: bool,
+    /// The entry that is actually playing.
+    pub playing: bool,
+}
+
+/// What a media file contains, by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MediaKind {
+    Video,
+    Audio,
+    Image,
+    Subtitle,
+    /// An archive mpv opens as a playlist of its contents, if built with libarchive.
+    Archive,
+}
+
+/// A file `load_directory` added to the playlist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaFile {
+    pub path: PathBuf,
+    pub kind: MediaKind,
+    /// Subtitle files with the same name, attached as external tracks.
+    pub subtitles: Vec<PathBuf>,
+}
+
+/// What happens when the last entry of the queue ends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepeatMode {
+    /// Stop after the last entry.
+    #[default]
+    Off,
+    /// Play the current entry again when it ends.
+    One,
+    /// Start over after the last entry.
+    All,
+}
+
+/// The play order the queue engine follows on top of the playlist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueState {
+    pub repeat: RepeatMode,
+    pub shuffle: bool,
+    /// Seed of the shuffled order, which is the same for the same seed and entries.
+    pub seed: Option<u64>,
+    /// Playlist entry IDs in play order.
+    pub order: Vec<i64>,
+    /// Playlist entry IDs played so far, oldest first, including the current one.
+    pub history: Vec<i64>,
+}
+
+/// Where playback of a file stopped, saved by the plugin to resume from later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumePoint {
+    /// The canonical path, or the URL.
+    pub path: String,
+    /// Position in seconds.
+    pub position: f64,
+    /// Duration in seconds.
+    pub duration: f64,
+    /// When the file was last played, as a Unix time in seconds.
+    pub last_played: u64,
+}
+
+/// What `probe` found out about a file without playing it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    /// Duration in seconds. Unknown for some streams.
+    pub duration: Option<f64>,
+    /// The demuxer's name for the container, like `mp4` or `matroska,webm`.
+    pub file_format: Option<String>,
+    /// Size in bytes.
+    pub file_size: Option<u64>,
+    /// Tags of the file, like `title` and `artist`.
+    pub metadata: IndexMap<String, String>,
+    pub tracks: Vec<Track>,
+    pub chapters: Vec<Chapter>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -31,13 +393,6 @@ pub struct VideoMarginRatio {
     pub bottom: Option<f64>,
 }
 
-#[derive(Debug, Clone)]
-pub struct EventUserData<R: Runtime> {
-    pub app: AppHandle<R>,
-    pub free_fn: unsafe extern "C" fn(*mut c_char),
-    pub window_label: String,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FfiResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -0,0 +1,270 @@
+//! Typed access to the playlist of one instance.
+
+use log::warn;
+use serde_json::json;
+use std::path::Path;
+use tauri::Runtime;
+
+use crate::desktop::Mpv;
+use crate::directory;
+use crate::media;
+use crate::models::{MediaFile, MediaKind, PlaylistEntry, QueueState, RepeatMode};
+use crate::playlist_file::{read_playlist_file, write_playlist_file, PlaylistFileEntry};
+use crate::queue::QueueAction;
+use crate::{Error, Result};
+
+/// The playlist of the mpv instance of one window, returned by [`Mpv::playlist`].
+///
+/// Indices are positions in the playlist and change with it, IDs stay with their entry.
+pub struct PlaylistHandle<'a, R: Runtime> {
+    mpv: &'a Mpv<R>,
+    window_label: String,
+}
+
+impl<'a, R: Runtime> PlaylistHandle<'a, R> {
+    pub(crate) fn new(mpv: &'a Mpv<R>, window_label: &str) -> Self {
+        Self {
+            mpv,
+            window_label: window_label.to_string(),
+        }
+    }
+
+    /// The entries, in playlist order.
+    pub fn entries(&self) -> Result<Vec<PlaylistEntry>> {
+        let value = self.mpv.get_property(
+            "playlist".to_string(),
+            "node".to_string(),
+            &self.window_label,
+        )?;
+        Ok(media::parse_playlist(&value))
+    }
+
+    /// Adds `path` at the end, without starting playback.
+    pub fn append(&self, path: &str) -> Result<()> {
+        self.command("loadfile", vec![json!(path), json!("append")])
+    }
+
+    /// Adds `path` so that it ends up at `index`, or at the end if `index` is past it.
+    pub fn insert_at(&self, path: &str, index: usize) -> Result<()> {
+        self.command(
+            "loadfile",
+            vec![json!(path), json!("insert-at"), json!(index)],
+        )
+    }
+
+    /// Adds `path` right after the current entry.
+    pub fn insert_next(&self, path: &str) -> Result<()> {
+        self.command("loadfile", vec![json!(path), json!("insert-next")])
+    }
+
+    /// Moves the entry at `from` so that it ends up at `to`.
+    pub fn move_entry(&self, from: usize, to: usize) -> Result<()> {
+        // `playlist-move` puts the entry before the one at its second index.
+        let target = if to > from { to + 1 } else { to };
+        self.command("playlist-move", vec![json!(from), json!(target)])
+    }
+
+    /// Removes the entry at `index`, stopping playback if it is playing.
+    pub fn remove(&self, index: usize) -> Result<()> {
+        self.command("playlist-remove", vec![json!(index)])
+    }
+
+    /// Removes every entry except the current one, like mpv's `playlist-clear`.
+    pub fn clear(&self) -> Result<()> {
+        self.command("playlist-clear", vec![])
+    }
+
+    /// Starts playing the entry at `index`.
+    pub fn play_index(&self, index: usize) -> Result<()> {
+        self.command("playlist-play-index", vec![json!(index)])
+    }
+
+    /// Starts playing the entry with ID `id`, wherever it was moved.
+    pub fn play_entry(&self, id: i64) -> Result<()> {
+        let entry = self
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| Error::Command {
+                window_label: self.window_label.clone(),
+                message: format!("no playlist entry with ID {}", id),
+            })?;
+        self.play_index(entry.index)
+    }
+
+    /// Writes the entries to an M3U/M3U8, PLS or XSPF file, chosen by the extension of `path`.
+    pub fn export(&self, path: &Path) -> Result<()> {
+        let entries: Vec<PlaylistFileEntry> = self
+            .entries()?
+            .into_iter()
+            .map(|entry| PlaylistFileEntry {
+                location: entry.filename,
+                title: entry.title,
+                duration: None,
+            })
+            .collect();
+        write_playlist_file(path, &entries)
+    }
+
+    /// Adds the entries of an M3U/M3U8, PLS or XSPF file, returning how many there were.
+    ///
+    /// With `replace`, the playlist is replaced and the first entry starts playing.
+    pub fn import(&self, path: &Path, replace: bool) -> Result<usize> {
+        let entries = read_playlist_file(path)?;
+        if entries.is_empty() {
+            return Err(Error::PlaylistFile(format!(
+                "'{}' has no entries",
+                path.display()
+            )));
+        }
+
+        let mut per_file_options = true;
+        for (index, entry) in entries.iter().enumerate() {
+            let flag = if replace && index == 0 {
+                "replace"
+            } else {
+                "append"
+            };
+            let options: Vec<_> = entry
+                .title
+                .iter()
+                .map(|title| ("force-media-title", title.as_str()))
+                .collect();
+            self.loadfile(&entry.location, flag, &options, &mut per_file_options)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Appends the media files in `dir` of `kinds`, or video and audio if empty, in natural order.
+    ///
+    /// Subtitle files named like a video or audio file are attached to it as external tracks.
+    /// Returns the appended files.
+    pub fn load_directory(
+        &self,
+        dir: &Path,
+        recursive: bool,
+        kinds: &[MediaKind],
+    ) -> Result<Vec<MediaFile>> {
+        let kinds = if kinds.is_empty() {
+            directory::DEFAULT_KINDS
+        } else {
+            kinds
+        };
+        let files = directory::scan_directory(dir, recursive, kinds)?;
+        let mut per_file_options = true;
+        for file in &files {
+            let path = file.path.to_string_lossy();
+            let subtitles: Vec<_> = file
+                .subtitles
+                .iter()
+                .map(|subtitle| subtitle.to_string_lossy())
+                .collect();
+            let options: Vec<_> = subtitles
+                .iter()
+                .map(|subtitle| ("sub-files-append", subtitle.as_ref()))
+                .collect();
+            self.loadfile(&path, "append", &options, &mut per_file_options)?;
+        }
+        Ok(files)
+    }
+
+    /// The play order of the queue engine, which picks the entry to play when one ends.
+    pub fn queue(&self) -> Result<QueueState> {
+        Ok(self.mpv.queue(&self.window_label)?.state())
+    }
+
+    /// Sets what happens when an entry or the last entry ends.
+    pub fn set_repeat(&self, repeat: RepeatMode) -> Result<()> {
+        self.mpv.queue(&self.window_label)?.set_repeat(repeat);
+        Ok(())
+    }
+
+    /// Turns shuffling on or off, without reordering the playlist.
+    ///
+    /// The same `seed` gives the same order for the same entries. Without one, a random seed is
+    /// used. The current entry comes first in the order.
+    pub fn set_shuffle(&self, enabled: bool, seed: Option<u64>) -> Result<()> {
+        self.mpv
+            .queue(&self.window_label)?
+            .set_shuffle(enabled, seed);
+        Ok(())
+    }
+
+    /// Plays the next entry of the queue, returning `false` if there is none.
+    pub fn next(&self) -> Result<bool> {
+        match self.mpv.queue(&self.window_label)?.next() {
+            Some(action) => self.run(action).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Plays the previous entry of the queue, returning `false` if there is none.
+    ///
+    /// Goes back through the entries played before, even if they were reordered or the
+    /// playlist was edited since.
+    pub fn previous(&self) -> Result<bool> {
+        match self.mpv.queue(&self.window_label)?.previous() {
+            Some(action) => self.run(action).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Carries out a decision of the queue engine.
+    pub(crate) fn run(&self, action: QueueAction) -> Result<()> {
+        let result = match action {
+            QueueAction::Play(index) => self.play_index(index),
+            QueueAction::Stop => self.command("stop", vec![json!("keep-playlist")]),
+        };
+        if result.is_err() {
+            self.mpv.queue(&self.window_label)?.clear_pending();
+        }
+        result
+    }
+
+    /// Runs `loadfile` with per-file options, or without them once mpv rejected them.
+    ///
+    /// mpv older than 0.38 doesn't take them, so `per_file_options` is cleared on the first
+    /// rejection and the following entries of the same import are loaded without options.
+    fn loadfile(
+        &self,
+        path: &str,
+        flag: &str,
+        options: &[(&str, &str)],
+        per_file_options: &mut bool,
+    ) -> Result<()> {
+        if options.is_empty() || !*per_file_options {
+            return self.command("loadfile", loadfile_args(path, flag, &[]));
+        }
+        match self.command("loadfile", loadfile_args(path, flag, options)) {
+            Err(Error::Command { message, .. }) => {
+                warn!(
+                    "mpv of window '{}' rejected per-file options ({}), loading without them. \
+                     They need mpv 0.38 or newer.",
+                    self.window_label, message
+                );
+                *per_file_options = false;
+                self.command("loadfile", loadfile_args(path, flag, &[]))
+            }
+            result => result,
+        }
+    }
+
+    fn command(&self, name: &str, args: Vec<serde_json::Value>) -> Result<()> {
+        self.mpv.command(name, &args, &self.window_label)
+    }
+}
+
+/// Arguments of `loadfile` with per-file options, which need mpv 0.38 or newer.
+fn loadfile_args(path: &str, flag: &str, options: &[(&str, &str)]) -> Vec<serde_json::Value> {
+    let mut args = vec![json!(path), json!(flag)];
+    if !options.is_empty() {
+        // `%n%` quotes a value of n bytes, which may contain commas.
+        let options: Vec<String> = options
+            .iter()
+            .map(|(name, value)| format!("{}=%{}%{}", name, value.len(), value))
+            .collect();
+        args.push(json!(-1));
+        args.push(json!(options.join(",")));
+    }
+    args
+}
@@ -0,0 +1,364 @@
+//! Reading and writing M3U/M3U8, PLS and XSPF playlist files.
+//!
+//! The formats are parsed here instead of by mpv, so the entries of a file are known up front
+//! and relative locations resolve against the playlist file rather than the working directory.
+
+use quick_xml::escape::{escape, unescape};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::BTreeMap;
+use std::path::Path;
+use url::Url;
+
+use crate::{Error, Result};
+
+/// A playlist file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    /// M3U and M3U8, with `#EXTINF` titles and durations. Always written as UTF-8.
+    M3u,
+    Pls,
+    Xspf,
+}
+
+/// An entry of a playlist file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistFileEntry {
+    /// A path or URL.
+    pub location: String,
+    pub title: Option<String>,
+    /// Duration in seconds.
+    pub duration: Option<f64>,
+}
+
+impl PlaylistFileEntry {
+    pub fn new(location: impl Into<String>) -> Self {
+        Self {
+            location: location.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl PlaylistFormat {
+    /// The format of `path` by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormat::M3u),
+            "pls" => Some(PlaylistFormat::Pls),
+            "xspf" => Some(PlaylistFormat::Xspf),
+            _ => None,
+        }
+    }
+
+    /// Parses `content`, resolving relative locations against `base_dir`.
+    pub fn parse(self, content: &str, base_dir: &Path) -> Result<Vec<PlaylistFileEntry>> {
+        let content = content.trim_start_matches('\u{feff}');
+        match self {
+            PlaylistFormat::M3u => Ok(parse_m3u(content, base_dir)),
+            PlaylistFormat::Pls => Ok(parse_pls(content, base_dir)),
+            PlaylistFormat::Xspf => parse_xspf(content, base_dir),
+        }
+    }
+
+    /// Writes `entries` in this format.
+    pub fn write(self, entries: &[PlaylistFileEntry]) -> String {
+        match self {
+            PlaylistFormat::M3u => write_m3u(entries),
+            PlaylistFormat::Pls => write_pls(entries),
+            PlaylistFormat::Xspf => write_xspf(entries),
+        }
+    }
+}
+
+/// Reads the playlist file at `path`, in the format of its extension.
+pub fn read_playlist_file(path: &Path) -> Result<Vec<PlaylistFileEntry>> {
+    let format = format_of(path)?;
+    let content = std::fs::read(path)?;
+    // Old M3U and PLS files are often in a legacy encoding, most likely Windows-1252.
+    let content = match String::from_utf8(content) {
+        Ok(content) => content,
+        Err(e) => decode_windows_1252(e.as_bytes()),
+    };
+    let path = std::env::current_dir()?.join(path);
+    let base_dir = path.parent().unwrap_or(&path);
+    format.parse(&content, base_dir)
+}
+
+/// Writes `entries` to `path`, in the format of its extension.
+pub fn write_playlist_file(path: &Path, entries: &[PlaylistFileEntry]) -> Result<()> {
+    let format = format_of(path)?;
+    std::fs::write(path, format.write(entries))?;
+    Ok(())
+}
+
+/// Characters of the bytes 0x80 to 0x9F in Windows-1252, where it differs from Latin-1.
+///
+/// The five bytes it leaves undefined keep their Latin-1 control characters.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9f => WINDOWS_1252_HIGH[(byte - 0x80) as usize],
+            byte => char::from(byte),
+        })
+        .collect()
+}
+
+fn format_of(path: &Path) -> Result<PlaylistFormat> {
+    PlaylistFormat::from_path(path).ok_or_else(|| {
+        Error::PlaylistFile(format!(
+            "unknown format of '{}', expected .m3u, .m3u8, .pls or .xspf",
+            path.display()
+        ))
+    })
+}
+
+fn parse_m3u(content: &str, base_dir: &Path) -> Vec<PlaylistFileEntry> {
+    let mut entries = Vec::new();
+    let mut info = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // `#EXTINF:<duration> [attributes],<title>`
+            let (head, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let duration = head
+                .split_whitespace()
+                .next()
+                .and_then(|duration| duration.parse().ok());
+            info = Some((non_empty(title), known_duration(duration)));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let (title, duration) = info.take().unwrap_or_default();
+            entries.push(PlaylistFileEntry {
+                location: resolve_location(line, base_dir),
+                title,
+                duration,
+            });
+        }
+    }
+    entries
+}
+
+fn write_m3u(entries: &[PlaylistFileEntry]) -> String {
+    let mut content = String::from("#EXTM3U\n");
+    for entry in entries {
+        if entry.title.is_some() || entry.duration.is_some() {
+            content.push_str(&format!(
+                "#EXTINF:{},{}\n",
+                written_duration(entry.duration),
+                single_line(entry.title.as_deref().unwrap_or_default())
+            ));
+        }
+        content.push_str(&single_line(&entry.location));
+        content.push('\n');
+    }
+    content
+}
+
+fn parse_pls(content: &str, base_dir: &Path) -> Vec<PlaylistFileEntry> {
+    // `FileN`, `TitleN` and `LengthN` by N, which needn't be in order.
+    let mut numbered: BTreeMap<u32, PlaylistFileEntry> = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let split = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+        let Ok(number) = key[split..].parse() else {
+            continue;
+        };
+        let entry = numbered.entry(number).or_default();
+        match &key[..split] {
+            "file" if !value.is_empty() => entry.location = resolve_location(value, base_dir),
+            "title" => entry.title = non_empty(value),
+            "length" => entry.duration = known_duration(value.parse().ok()),
+            _ => {}
+        }
+    }
+
+    numbered
+        .into_values()
+        .filter(|entry| !entry.location.is_empty())
+        .collect()
+}
+
+fn write_pls(entries: &[PlaylistFileEntry]) -> String {
+    let mut content = String::from("[playlist]\n");
+    for (index, entry) in entries.iter().enumerate() {
+        let number = index + 1;
+        content.push_str(&format!(
+            "File{}={}\n",
+            number,
+            single_line(&entry.location)
+        ));
+        if let Some(title) = &entry.title {
+            content.push_str(&format!("Title{}={}\n", number, single_line(title)));
+        }
+        content.push_str(&format!(
+            "Length{}={}\n",
+            number,
+            written_duration(entry.duration)
+        ));
+    }
+    content.push_str(&format!("NumberOfEntries={}\nVersion=2\n", entries.len()));
+    content
+}
+
+fn parse_xspf(content: &str, base_dir: &Path) -> Result<Vec<PlaylistFileEntry>> {
+    let invalid = |e: quick_xml::Error| Error::PlaylistFile(format!("invalid XSPF: {}", e));
+    let mut reader = Reader::from_str(content);
+    let mut entries = Vec::new();
+    let mut track: Option<PlaylistFileEntry> = None;
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                if name == "track" {
+                    track = Some(PlaylistFileEntry::default());
+                    continue;
+                }
+                let Some(track) = &mut track else {
+                    continue;
+                };
+                if !matches!(name.as_str(), "location" | "title" | "duration") {
+                    // Skips unknown elements with their children, like `<extension>`.
+                    reader.read_to_end(element.name()).map_err(invalid)?;
+                    continue;
+                }
+                let text = reader.read_text(element.name()).map_err(invalid)?;
+                let text = unescape(&text)
+                    .map_err(|e| invalid(e.into()))?
+                    .trim()
+                    .to_string();
+                match name.as_str() {
+                    // A track may list alternatives, of which the first is used.
+                    "location" if track.location.is_empty() => {
+                        track.location = resolve_uri(&text, base_dir)
+                    }
+                    "title" => track.title = non_empty(&text),
+                    "duration" => {
+                        track.duration = text.parse::<f64>().ok().map(|millis| millis / 1000.0)
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(element) if element.local_name().as_ref() == b"track" => {
+                if let Some(track) = track.take().filter(|track| !track.location.is_empty()) {
+                    entries.push(track);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+fn write_xspf(entries: &[PlaylistFileEntry]) -> String {
+    let mut content = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n",
+    );
+    for entry in entries {
+        content.push_str("    <track>\n");
+        content.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape(location_uri(&entry.location))
+        ));
+        if let Some(title) = &entry.title {
+            content.push_str(&format!("      <title>{}</title>\n", escape(title)));
+        }
+        if let Some(duration) = entry.duration {
+            content.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                (duration * 1000.0).round() as u64
+            ));
+        }
+        content.push_str("    </track>\n");
+    }
+    content.push_str("  </trackList>\n</playlist>\n");
+    content
+}
+
+/// Resolves an M3U or PLS location: URLs are kept, `file://` URLs become paths and relative
+/// paths are joined to `base_dir`.
+fn resolve_location(location: &str, base_dir: &Path) -> String {
+    if is_url(location) {
+        return match Url::parse(location) {
+            Ok(url) if url.scheme() == "file" => file_url_to_path(&url),
+            _ => location.to_string(),
+        };
+    }
+    let path = Path::new(location);
+    if path.is_absolute() {
+        location.to_string()
+    } else {
+        base_dir.join(path).to_string_lossy().into_owned()
+    }
+}
+
+/// Resolves an XSPF location, a URI that may be relative to the playlist file.
+fn resolve_uri(location: &str, base_dir: &Path) -> String {
+    let url = Url::from_directory_path(base_dir)
+        .ok()
+        .and_then(|base| base.join(location).ok());
+    match url {
+        Some(url) if url.scheme() == "file" => file_url_to_path(&url),
+        Some(url) => url.to_string(),
+        None => resolve_location(location, base_dir),
+    }
+}
+
+/// An XSPF location for a path or URL, which must be a URI.
+fn location_uri(location: &str) -> String {
+    if is_url(location) {
+        return location.to_string();
+    }
+    match Url::from_file_path(location) {
+        Ok(url) => url.to_string(),
+        Err(()) => location.to_string(),
+    }
+}
+
+fn file_url_to_path(url: &Url) -> String {
+    match url.to_file_path() {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(()) => url.to_string(),
+    }
+}
+
+/// Whether `location` starts with a URL scheme. One letter is a Windows drive instead.
+fn is_url(location: &str) -> bool {
+    location.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Negative durations mean unknown in M3U and PLS.
+fn known_duration(duration: Option<f64>) -> Option<f64> {
+    duration.filter(|duration| *duration >= 0.0)
+}
+
+fn written_duration(duration: Option<f64>) -> i64 {
+    duration.map_or(-1, |duration| duration.round() as i64)
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Line-based formats can't hold line breaks.
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
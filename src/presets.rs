@@ -0,0 +1,67 @@
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+
+/// Names of the presets shipped with the plugin.
+pub const BUILTIN_PRESETS: &[&str] = &[
+    "audio-only",
+    "low-latency",
+    "high-quality",
+    "thumbnailer",
+    "background-music",
+];
+
+/// Returns the options of a preset shipped with the plugin.
+pub fn builtin_preset(name: &str) -> Option<IndexMap<String, Value>> {
+    let options: &[(&str, Value)] = match name {
+        // Plays the audio track only and skips window embedding.
+        "audio-only" => &[
+            ("video", json!("no")),
+            ("audio-display", json!("no")),
+            ("force-window", json!("no")),
+        ],
+        // Live streams and cameras, trading smoothness for delay.
+        "low-latency" => &[
+            ("profile", json!("low-latency")),
+            ("cache", json!("no")),
+            ("hwdec", json!("auto-safe")),
+        ],
+        // Local files on a capable GPU.
+        "high-quality" => &[
+            ("vo", json!("gpu-next")),
+            ("profile", json!("high-quality")),
+            ("hwdec", json!("auto-safe")),
+            ("keep-open", json!("yes")),
+            ("force-window", json!("yes")),
+        ],
+        // Hidden instances that only decode single frames.
+        "thumbnailer" => &[
+            ("vo", json!("null")),
+            ("ao", json!("null")),
+            ("audio", json!("no")),
+            ("sid", json!("no")),
+            ("pause", json!("yes")),
+            ("hr-seek", json!("yes")),
+            ("hwdec", json!("no")),
+            ("idle", json!("yes")),
+            ("load-scripts", json!("no")),
+            ("ytdl", json!("no")),
+        ],
+        // Long-running music playback without a window.
+        "background-music" => &[
+            ("video", json!("no")),
+            ("audio-display", json!("no")),
+            ("force-window", json!("no")),
+            ("idle", json!("yes")),
+            ("gapless-audio", json!("yes")),
+            ("replaygain", json!("track")),
+        ],
+        _ => return None,
+    };
+
+    Some(
+        options
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+    )
+}
@@ -0,0 +1,72 @@
+//! Reading media information with hidden instances that open files without playing them.
+
+use indexmap::IndexMap;
+use std::time::Duration;
+
+use crate::backend::MpvBackend;
+use crate::config::ProbeConfig;
+use crate::headless::{HeadlessInstance, HeadlessPool};
+use crate::media;
+use crate::models::MediaInfo;
+use crate::Result;
+
+pub(crate) struct Probes {
+    pool: HeadlessPool,
+    timeout: Duration,
+}
+
+impl Probes {
+    pub fn new(config: &ProbeConfig) -> Self {
+        Self {
+            pool: HeadlessPool::new("probe", config.workers),
+            timeout: Duration::from_millis(config.timeout),
+        }
+    }
+
+    /// Opens `path` on a free instance and collects what mpv knows about it.
+    ///
+    /// Waits while all instances are busy, so many files can be probed at once.
+    pub fn probe(
+        &self,
+        backend: &dyn MpvBackend,
+        options: &IndexMap<String, serde_json::Value>,
+        path: &str,
+    ) -> Result<MediaInfo> {
+        let mut instance = self.pool.acquire(backend, options, &|| false)?;
+        let result = instance
+            .open(path, self.timeout)
+            .map(|()| collect(&instance))
+            .and_then(|info| instance.close().map(|()| info));
+        if result.is_err() {
+            instance.discard();
+        }
+        result
+    }
+
+    /// Destroys the hidden instances.
+    pub fn shutdown(&self) {
+        self.pool.shutdown();
+    }
+}
+
+/// Reads the properties of the loaded file. Unavailable ones are left empty.
+fn collect(instance: &HeadlessInstance) -> MediaInfo {
+    let property = |name: &str, format: &str| instance.backend.get_property(name, format).ok();
+
+    let duration = property("duration", "double").and_then(|value| value.as_f64());
+    MediaInfo {
+        duration,
+        file_format: property("file-format", "string")
+            .and_then(|value| value.as_str().map(str::to_string)),
+        file_size: property("file-size", "int64").and_then(|value| value.as_u64()),
+        metadata: property("metadata", "node")
+            .map(|node| media::parse_metadata(&node))
+            .unwrap_or_default(),
+        tracks: property("track-list", "node")
+            .map(|node| media::parse_tracks(&node))
+            .unwrap_or_default(),
+        chapters: property("chapter-list", "node")
+            .map(|node| media::parse_chapters(&node, duration))
+            .unwrap_or_default(),
+    }
+}
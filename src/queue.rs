@@ -0,0 +1,291 @@
+//! A queue engine on top of the playlist of an instance: repeat modes, a seeded shuffle and
+//! back and forward history.
+//!
+//! mpv's `shuffle` and `loop-playlist` reorder the playlist itself and have no notion of going
+//! back. The engine keeps its own play order of playlist entry IDs instead, so it survives
+//! edits of the playlist, and picks the entry to play when one ends.
+
+use serde_json::Value;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::models::{QueueState, RepeatMode};
+
+/// Older entries are dropped from the history beyond this length.
+const MAX_HISTORY: usize = 1000;
+
+/// What the instance has to do for the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QueueAction {
+    /// Play the entry at this playlist index.
+    Play(usize),
+    /// Stop, keeping the playlist.
+    Stop,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Queue {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    repeat: RepeatMode,
+    shuffle: Option<Shuffle>,
+    /// Entry IDs in playlist order.
+    entries: Vec<i64>,
+    history: Vec<i64>,
+    /// Index of the current entry in `history`.
+    position: usize,
+    pending: Option<Pending>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Shuffle {
+    seed: u64,
+    /// Current when shuffling started, so it comes first.
+    first: Option<i64>,
+}
+
+/// A decision of the queue that mpv hasn't carried out yet.
+///
+/// mpv advances to the next playlist entry on its own when one ends, so entries may start
+/// in between. Those starts aren't recorded in the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    Entry(i64),
+    Stop,
+}
+
+impl Queue {
+    pub fn state(&self) -> QueueState {
+        let inner = self.lock();
+        QueueState {
+            repeat: inner.repeat,
+            shuffle: inner.shuffle.is_some(),
+            seed: inner.shuffle.map(|shuffle| shuffle.seed),
+            order: inner.order(),
+            history: inner.history.clone(),
+        }
+    }
+
+    pub fn set_repeat(&self, repeat: RepeatMode) {
+        self.lock().repeat = repeat;
+    }
+
+    /// Shuffles with `seed`, or a random one, starting from the current entry.
+    pub fn set_shuffle(&self, enabled: bool, seed: Option<u64>) {
+        let mut inner = self.lock();
+        inner.shuffle = enabled.then(|| Shuffle {
+            seed: seed.unwrap_or_else(random_seed),
+            first: inner.current(),
+        });
+        // The upcoming entries change with the order.
+        let len = inner.position + 1;
+        inner.history.truncate(len);
+    }
+
+    /// Moves to the entry after the current one, wrapping around unless repeat is off.
+    pub fn next(&self) -> Option<QueueAction> {
+        let mut inner = self.lock();
+        let wrap = inner.repeat != RepeatMode::Off;
+        let target = inner.forward(wrap)?;
+        inner.play(target)
+    }
+
+    /// Moves back through the history, then to the entry before the first one in the order.
+    pub fn previous(&self) -> Option<QueueAction> {
+        let mut inner = self.lock();
+        let wrap = inner.repeat != RepeatMode::Off;
+        let target = inner.backward(wrap)?;
+        inner.play(target)
+    }
+
+    /// Forgets a decision mpv failed to carry out.
+    pub fn clear_pending(&self) {
+        self.lock().pending = None;
+    }
+
+    /// Updates the queue from an event of the instance, returning what to do for it.
+    pub fn observe(&self, event: &Value) -> Option<QueueAction> {
+        let mut inner = self.lock();
+        match event["event"].as_str()? {
+            "playlist-changed" => {
+                inner.entries = event["entries"]
+                    .as_array()?
+                    .iter()
+                    .filter_map(|entry| entry["id"].as_i64())
+                    .collect();
+                None
+            }
+            "start-file" => {
+                inner.started(event["playlist_entry_id"].as_i64()?);
+                None
+            }
+            "end-file" => {
+                if inner.pending == Some(Pending::Stop) {
+                    inner.pending = None;
+                }
+                if event["reason"] != "eof" {
+                    return None;
+                }
+                inner.ended(event["playlist_entry_id"].as_i64()?)
+            }
+            "idle" => {
+                if inner.pending == Some(Pending::Stop) {
+                    inner.pending = None;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Inner {
+    fn current(&self) -> Option<i64> {
+        self.history.get(self.position).copied()
+    }
+
+    /// The entry IDs in play order.
+    fn order(&self) -> Vec<i64> {
+        let mut order = self.entries.clone();
+        if let Some(shuffle) = self.shuffle {
+            // Keyed by ID, so added and removed entries leave the others in place.
+            order.sort_by_key(|id| (Some(*id) != shuffle.first, shuffle_key(shuffle.seed, *id)));
+        }
+        order
+    }
+
+    fn started(&mut self, id: i64) {
+        match self.pending {
+            Some(Pending::Entry(pending)) if pending == id => self.pending = None,
+            Some(_) => {}
+            None if self.current() != Some(id) => self.record(id),
+            None => {}
+        }
+    }
+
+    fn ended(&mut self, id: i64) -> Option<QueueAction> {
+        // It may have started before the queue saw the playlist.
+        if self.current() != Some(id) {
+            self.record(id);
+        }
+
+        let target = match self.repeat {
+            RepeatMode::One => Some(id),
+            RepeatMode::Off => self.forward(false),
+            RepeatMode::All => self.forward(true),
+        };
+        // What mpv plays by itself.
+        let successor = self
+            .entries
+            .iter()
+            .position(|entry| *entry == id)
+            .and_then(|index| self.entries.get(index + 1))
+            .copied();
+
+        match target {
+            Some(target) => {
+                let action = self.play(target)?;
+                (Some(target) != successor).then_some(action)
+            }
+            None => successor.map(|_| {
+                self.pending = Some(Pending::Stop);
+                QueueAction::Stop
+            }),
+        }
+    }
+
+    fn play(&mut self, id: i64) -> Option<QueueAction> {
+        let index = self.entries.iter().position(|entry| *entry == id)?;
+        self.pending = Some(Pending::Entry(id));
+        Some(QueueAction::Play(index))
+    }
+
+    /// The next entry, from the forward history or else the order, made current.
+    fn forward(&mut self, wrap: bool) -> Option<i64> {
+        while let Some(&id) = self.history.get(self.position + 1) {
+            if self.entries.contains(&id) {
+                self.position += 1;
+                return Some(id);
+            }
+            self.history.remove(self.position + 1);
+        }
+
+        let order = self.order();
+        let next = match self
+            .current()
+            .and_then(|current| order.iter().position(|id| *id == current))
+        {
+            Some(index) => order
+                .get(index + 1)
+                .or(if wrap { order.first() } else { None }),
+            None => order.first(),
+        };
+        let next = *next?;
+        self.record(next);
+        Some(next)
+    }
+
+    /// The previous entry, from the history or else the order, made current.
+    fn backward(&mut self, wrap: bool) -> Option<i64> {
+        while self.position > 0 {
+            self.position -= 1;
+            let id = self.history[self.position];
+            if self.entries.contains(&id) {
+                return Some(id);
+            }
+            self.history.remove(self.position);
+        }
+
+        let order = self.order();
+        let index = self
+            .current()
+            .and_then(|current| order.iter().position(|id| *id == current))?;
+        let previous = match index {
+            0 if wrap => order.last(),
+            0 => None,
+            index => order.get(index - 1),
+        };
+        let previous = *previous?;
+        self.history.insert(0, previous);
+        self.history.truncate(MAX_HISTORY);
+        self.position = 0;
+        Some(previous)
+    }
+
+    /// Makes `id` current, dropping the forward history.
+    fn record(&mut self, id: i64) {
+        if !self.history.is_empty() {
+            self.history.truncate(self.position + 1);
+        }
+        self.history.push(id);
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.position = self.history.len() - 1;
+    }
+}
+
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// SplitMix64 of the seed and ID, stable across builds and platforms.
+fn shuffle_key(seed: u64, id: i64) -> u64 {
+    let mut z = (seed ^ id as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,347 @@
+//! Resume positions the plugin keeps itself, instead of mpv's watch-later files.
+//!
+//! Each instance tracks the path, position and duration of what it plays and saves them
+//! periodically and when a file ends or the instance is destroyed. All instances share one
+//! JSON file in the app data directory, most recently played first.
+
+use log::warn;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::models::ResumePoint;
+use crate::Result;
+
+/// Observed on every instance while resume positions are enabled, with their formats.
+pub(crate) const RESUME_PROPERTIES: &[(&str, &str)] = &[("path", "string"), ("time-pos", "double")];
+
+/// Positions this close to the start, in seconds, aren't worth resuming from.
+const MIN_POSITION: f64 = 5.0;
+
+/// A change of the stored resume positions.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ResumeUpdate {
+    Save(ResumePoint),
+    /// Forget the position of this path, which was played to its end.
+    Clear(String),
+}
+
+/// The resume positions of all instances, stored in one file.
+pub(crate) struct ResumeStore {
+    file: PathBuf,
+    max_entries: usize,
+    /// Most recently played first. Read from the file on first use.
+    points: Mutex<Option<Vec<ResumePoint>>>,
+}
+
+impl ResumeStore {
+    pub fn new(file: PathBuf, max_entries: usize) -> Self {
+        Self {
+            file,
+            max_entries,
+            points: Mutex::new(None),
+        }
+    }
+
+    /// The position saved for `path`, if it wasn't played to its end.
+    pub fn get(&self, path: &str) -> Option<ResumePoint> {
+        let key = resume_key(path);
+        self.lock()
+            .as_ref()
+            .and_then(|points| points.iter().find(|point| point.path == key).cloned())
+    }
+
+    /// All saved positions, most recently played first.
+    pub fn list(&self) -> Vec<ResumePoint> {
+        self.lock().clone().unwrap_or_default()
+    }
+
+    /// Forgets the position of `path`, returning whether there was one.
+    pub fn remove(&self, path: &str) -> Result<bool> {
+        let key = resume_key(path);
+        let mut points = self.lock();
+        let points = points.get_or_insert_with(Vec::new);
+        let len = points.len();
+        points.retain(|point| point.path != key);
+        if points.len() == len {
+            return Ok(false);
+        }
+        self.write(points)?;
+        Ok(true)
+    }
+
+    pub fn apply(&self, update: ResumeUpdate) -> Result<()> {
+        match update {
+            ResumeUpdate::Save(point) => {
+                let mut points = self.lock();
+                let points = points.get_or_insert_with(Vec::new);
+                points.retain(|saved| saved.path != point.path);
+                points.insert(0, point);
+                points.truncate(self.max_entries);
+                self.write(points)
+            }
+            ResumeUpdate::Clear(path) => self.remove(&path).map(|_| ()),
+        }
+    }
+
+    /// The points, read from the file if this is the first use.
+    fn lock(&self) -> MutexGuard<'_, Option<Vec<ResumePoint>>> {
+        let mut points = match self.points.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if points.is_none() {
+            *points = Some(read_points(&self.file));
+        }
+        points
+    }
+
+    /// Replaces the file, through a temporary file so a crash can't leave half of it.
+    fn write(&self, points: &[ResumePoint]) -> Result<()> {
+        if let Some(dir) = self.file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temporary = self.file.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(points)?)?;
+        std::fs::rename(&temporary, &self.file)?;
+        Ok(())
+    }
+}
+
+/// Applies the changes of all instances on one thread, in the order they were sent.
+///
+/// Writing the file would block the event thread, and changes applied on separate threads
+/// could finish out of order and leave an older position stored.
+#[derive(Debug)]
+pub(crate) struct ResumeWriter {
+    sender: Sender<Write>,
+}
+
+#[derive(Debug)]
+enum Write {
+    Update(ResumeUpdate),
+    /// Answered once everything sent before is applied.
+    Flush(Sender<()>),
+}
+
+impl ResumeWriter {
+    pub fn spawn(apply: impl Fn(ResumeUpdate) + Send + 'static) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("mpv-resume".to_string())
+            .spawn(move || {
+                for write in receiver {
+                    match write {
+                        Write::Update(update) => apply(update),
+                        Write::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            })?;
+        Ok(Self { sender })
+    }
+
+    pub fn send(&self, update: ResumeUpdate) {
+        let _ = self.sender.send(Write::Update(update));
+    }
+
+    /// Waits until every change sent so far is applied.
+    pub fn flush(&self) {
+        let (done, applied) = mpsc::channel();
+        if self.sender.send(Write::Flush(done)).is_ok() {
+            let _ = applied.recv();
+        }
+    }
+}
+
+/// Follows what one instance plays, deciding when its position is saved or cleared.
+#[derive(Debug)]
+pub(crate) struct ResumeTracker {
+    save_interval: Duration,
+    writer: Arc<ResumeWriter>,
+    inner: Mutex<Playing>,
+}
+
+#[derive(Debug, Default)]
+struct Playing {
+    path: Option<String>,
+    position: Option<f64>,
+    duration: Option<f64>,
+    /// When the position was last saved, or the file started.
+    saved_at: Option<Instant>,
+    saved: Option<ResumeUpdate>,
+}
+
+impl ResumeTracker {
+    pub fn new(save_interval: Duration, writer: Arc<ResumeWriter>) -> Self {
+        Self {
+            save_interval,
+            writer,
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Updates the tracker from a raw event of the instance, sending the change to store.
+    pub fn observe(&self, event: &Value) {
+        // Sending under the lock keeps the changes of concurrent events in order.
+        let mut playing = self.lock();
+        if let Some(update) = self.change(&mut playing, event) {
+            self.writer.send(update);
+        }
+    }
+
+    /// Stores the position of the file playing when the instance is destroyed.
+    ///
+    /// Returns once it and every change sent before it are written.
+    pub fn finish(&self) {
+        {
+            let mut playing = self.lock();
+            if let Some(update) = playing.update() {
+                self.writer.send(update);
+            }
+            *playing = Playing::default();
+        }
+        self.writer.flush();
+    }
+
+    fn change(&self, playing: &mut Playing, event: &Value) -> Option<ResumeUpdate> {
+        match event["event"].as_str()? {
+            "start-file" => {
+                *playing = Playing {
+                    saved_at: Some(Instant::now()),
+                    ..Default::default()
+                };
+                None
+            }
+            "property-change" => {
+                // Properties become unavailable while a file unloads, which keeps the last values.
+                let data = &event["data"];
+                match event["name"].as_str()? {
+                    "path" => playing.path = Some(resume_key(data.as_str()?)),
+                    "duration" => playing.duration = Some(number(data)?),
+                    "time-pos" => {
+                        playing.position = Some(number(data)?);
+                        let due = playing
+                            .saved_at
+                            .map_or(true, |saved_at| saved_at.elapsed() >= self.save_interval);
+                        if due {
+                            playing.saved_at = Some(Instant::now());
+                            return playing.update();
+                        }
+                    }
+                    _ => {}
+                }
+                None
+            }
+            "end-file" => {
+                let update = match event["reason"].as_str() {
+                    Some("eof") => playing.path.clone().map(ResumeUpdate::Clear),
+                    // The position of a file that failed to open means nothing.
+                    Some("error") => None,
+                    _ => playing.update(),
+                };
+                *playing = Playing::default();
+                update
+            }
+            _ => None,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Playing> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Playing {
+    /// The change for the current position, unless it is the one stored last.
+    fn update(&mut self) -> Option<ResumeUpdate> {
+        let path = self.path.clone()?;
+        let position = self.position?;
+        // Streams without a duration can't be resumed.
+        let duration = self.duration.filter(|duration| *duration > 0.0)?;
+
+        let update = if is_finished(position, duration) {
+            ResumeUpdate::Clear(path)
+        } else if position >= MIN_POSITION {
+            ResumeUpdate::Save(ResumePoint {
+                path,
+                position,
+                duration,
+                last_played: unix_time(),
+            })
+        } else {
+            // Opening a file and leaving right away keeps the position saved before.
+            return None;
+        };
+
+        let unchanged = match (&update, &self.saved) {
+            (ResumeUpdate::Save(new), Some(ResumeUpdate::Save(old))) => {
+                new.position == old.position
+            }
+            (new, old) => Some(new) == old.as_ref(),
+        };
+        if unchanged {
+            return None;
+        }
+        self.saved = Some(update.clone());
+        Some(update)
+    }
+}
+
+/// A number, also if the app observes the property in the `string` format.
+fn number(data: &Value) -> Option<f64> {
+    data.as_f64().or_else(|| data.as_str()?.parse().ok())
+}
+
+/// Whether `position` is in the last 5% of `duration`, or its last 10 seconds if that is longer.
+fn is_finished(position: f64, duration: f64) -> bool {
+    duration - position <= (duration * 0.05).max(10.0)
+}
+
+/// Files are stored by their canonical path, so different spellings of a path share a position.
+pub(crate) fn resume_key(path: &str) -> String {
+    if path.contains("://") {
+        return path.to_string();
+    }
+    match std::fs::canonicalize(path) {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(_) => path.to_string(),
+    }
+}
+
+fn read_points(file: &Path) -> Vec<ResumePoint> {
+    let content = match std::fs::read(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            warn!(
+                "Failed to read resume positions '{}': {}",
+                file.display(),
+                e
+            );
+            return Vec::new();
+        }
+    };
+    // A damaged file only loses the positions, it is replaced on the next save.
+    serde_json::from_slice(&content).unwrap_or_else(|e| {
+        warn!(
+            "Ignoring invalid resume positions '{}': {}",
+            file.display(),
+            e
+        );
+        Vec::new()
+    })
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
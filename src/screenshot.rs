@@ -0,0 +1,115 @@
+//! File names for screenshots saved into the screenshot directory.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Template used when neither the call nor `plugins.libmpv.screenshotTemplate` sets one.
+pub const DEFAULT_SCREENSHOT_TEMPLATE: &str = "{name}-{pos}";
+
+/// Values substituted into a screenshot template.
+pub(crate) struct TemplateValues<'a> {
+    /// File name of the playing media without extension.
+    pub name: &'a str,
+    /// Playback position in seconds.
+    pub pos: f64,
+    pub window: &'a str,
+    /// Seconds since the Unix epoch.
+    pub unix: u64,
+}
+
+/// Replaces `{name}`, `{pos}`, `{window}` and `{unix}` in `template`.
+///
+/// Substituted values can't add path separators, so the result is a plain file name.
+pub(crate) fn expand_template(template: &str, values: &TemplateValues) -> Result<String, String> {
+    if template.contains(['/', '\\']) {
+        return Err(format!("'{}' contains a path separator", template));
+    }
+
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("'{}' has an unclosed '{{'", template))?;
+
+        let value = match &rest[start + 1..end] {
+            "name" => values.name.to_string(),
+            "pos" => format_pos(values.pos),
+            "window" => values.window.to_string(),
+            "unix" => values.unix.to_string(),
+            placeholder => {
+                return Err(format!(
+                    "'{}' has unknown placeholder '{{{}}}'",
+                    template, placeholder
+                ))
+            }
+        };
+        name.push_str(&sanitize(&value));
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+
+    if name.trim().is_empty() {
+        return Err(format!("'{}' expands to an empty file name", template));
+    }
+    Ok(name)
+}
+
+/// Checks that `template` expands without errors.
+pub(crate) fn check_template(template: &str) -> Result<(), String> {
+    let values = TemplateValues {
+        name: "video",
+        pos: 0.0,
+        window: "main",
+        unix: 0,
+    };
+    expand_template(template, &values).map(|_| ())
+}
+
+/// `dir/name.extension`, or `dir/name-2.extension` and so on if that file exists.
+pub(crate) fn unique_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", name, extension));
+    let mut counter = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", name, counter, extension));
+        counter += 1;
+    }
+    path
+}
+
+/// A path in the temp directory no other screenshot of this process uses.
+pub(crate) fn temp_path(extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "tauri-plugin-libmpv-{}-{}.{}",
+        std::process::id(),
+        NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed),
+        extension
+    ))
+}
+
+/// Formats a position as `HH-MM-SS.mmm`, which is valid in file names everywhere.
+fn format_pos(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}-{:02}-{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
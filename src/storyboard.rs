@@ -0,0 +1,335 @@
+//! Storyboards: sprite sheets of thumbnails at a fixed interval, indexed by a WebVTT file.
+//!
+//! They are rendered by a hidden instance and cached on disk, keyed by the file path,
+//! size and modification time and the layout.
+
+use image::{imageops, RgbImage};
+use indexmap::IndexMap;
+use scopeguard::defer;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use crate::backend::MpvBackend;
+use crate::headless::{HeadlessInstance, HeadlessPool};
+use crate::models::{Storyboard, StoryboardOptions};
+use crate::{Error, Result};
+
+/// Written last, so a cache directory with it is complete.
+const MANIFEST: &str = "storyboard.json";
+const VTT: &str = "storyboard.vtt";
+
+/// The most thumbnails one storyboard may have, so a tiny interval can't run for hours.
+const MAX_THUMBNAILS: u32 = 10_000;
+
+pub(crate) struct Storyboards {
+    /// A single instance, so background jobs run one after another.
+    pool: HeadlessPool,
+    /// Cancellation flags of the running jobs, by window and path.
+    jobs: Mutex<HashMap<(String, String), Arc<AtomicBool>>>,
+}
+
+impl Storyboards {
+    pub fn new() -> Self {
+        Self {
+            pool: HeadlessPool::new("storyboards", 1),
+            jobs: Mutex::default(),
+        }
+    }
+
+    /// Returns the cached storyboard of `path`, or renders it into `cache_dir`.
+    ///
+    /// `progress` is called with the number of rendered and total thumbnails.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate(
+        &self,
+        backend: &dyn MpvBackend,
+        options: &IndexMap<String, serde_json::Value>,
+        path: &str,
+        layout: &StoryboardOptions,
+        cache_dir: &Path,
+        window_label: &str,
+        timeout: Duration,
+        progress: &dyn Fn(u32, u32),
+    ) -> Result<Storyboard> {
+        check_layout(layout)?;
+        let metadata = std::fs::metadata(path)
+            .map_err(|e| Error::Storyboard(format!("can't read '{}': {}", path, e)))?;
+        let dir = cache_dir.join(format!("{:016x}", cache_key(path, &metadata, layout)));
+        if let Some(storyboard) = read_manifest(&dir) {
+            return Ok(storyboard);
+        }
+
+        let job = (window_label.to_string(), path.to_string());
+        let cancelled = Arc::new(AtomicBool::new(false));
+        {
+            let mut jobs = lock(&self.jobs);
+            if jobs.contains_key(&job) {
+                return Err(Error::Storyboard(format!(
+                    "a storyboard of '{}' is already being generated for window '{}'",
+                    path, window_label
+                )));
+            }
+            jobs.insert(job.clone(), cancelled.clone());
+        }
+        defer! {
+            lock(&self.jobs).remove(&job);
+        }
+
+        let is_cancelled = || cancelled.load(Ordering::Relaxed);
+        let mut instance = self.pool.acquire(backend, options, &is_cancelled)?;
+        // Another job may have rendered it while this one waited.
+        if let Some(storyboard) = read_manifest(&dir) {
+            return Ok(storyboard);
+        }
+        let job = Job {
+            path,
+            layout,
+            dir: &dir,
+            timeout,
+            cancelled: &is_cancelled,
+            progress,
+        };
+        let result = job.run(&mut instance);
+        if let Err(e) = &result {
+            if !matches!(e, Error::Cancelled(_)) {
+                instance.discard();
+            }
+            let _ = std::fs::remove_dir_all(partial_dir(&dir));
+        }
+        result
+    }
+
+    /// Cancels the job generating the storyboard of `path` for `window_label`.
+    ///
+    /// Returns `false` if there is none.
+    pub fn cancel(&self, window_label: &str, path: &str) -> bool {
+        let job = (window_label.to_string(), path.to_string());
+        match lock(&self.jobs).get(&job) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancels every job and destroys the hidden instance.
+    pub fn shutdown(&self) {
+        for cancelled in lock(&self.jobs).values() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        self.pool.shutdown();
+    }
+}
+
+struct Job<'a> {
+    path: &'a str,
+    layout: &'a StoryboardOptions,
+    dir: &'a Path,
+    timeout: Duration,
+    cancelled: &'a dyn Fn() -> bool,
+    progress: &'a dyn Fn(u32, u32),
+}
+
+impl Job<'_> {
+    /// Renders the storyboard into a temporary directory, then moves it into place.
+    fn run(&self, instance: &mut HeadlessInstance) -> Result<Storyboard> {
+        let layout = self.layout;
+        let partial = partial_dir(self.dir);
+        let _ = std::fs::remove_dir_all(&partial);
+        std::fs::create_dir_all(&partial)?;
+
+        instance.backend.set_property(
+            "vf",
+            &serde_json::json!(format!("scale={}:-2", layout.width)),
+        )?;
+        if instance.loaded.as_deref() == Some(self.path) {
+            instance.seek(0.0, self.timeout, self.cancelled)?;
+        } else {
+            instance.load(
+                self.path,
+                &[("start", serde_json::json!("0"))],
+                self.timeout,
+                self.cancelled,
+            )?;
+        }
+
+        let duration = instance
+            .backend
+            .get_property("duration", "double")?
+            .as_f64()
+            .filter(|duration| *duration > 0.0)
+            .ok_or_else(|| Error::Storyboard(format!("'{}' has no duration", self.path)))?;
+        let count = (duration / layout.interval).ceil().max(1.0);
+        if count > MAX_THUMBNAILS as f64 {
+            return Err(Error::Storyboard(format!(
+                "an interval of {}s gives {} thumbnails for '{}', at most {} are allowed",
+                layout.interval, count, self.path, MAX_THUMBNAILS
+            )));
+        }
+        let count = count as u32;
+        let per_sheet = layout.columns * layout.rows;
+
+        let mut sheets = Vec::new();
+        let mut sheet: Option<RgbImage> = None;
+        let mut tile_size = None;
+        let mut vtt = String::from("WEBVTT\n");
+
+        for index in 0..count {
+            if (self.cancelled)() {
+                return Err(Error::Cancelled(format!(
+                    "the storyboard of '{}' was cancelled",
+                    self.path
+                )));
+            }
+
+            let start = index as f64 * layout.interval;
+            if index > 0 {
+                instance.seek(start, self.timeout, self.cancelled)?;
+            }
+            let frame = image::load_from_memory(&instance.capture("png")?)
+                .map_err(|e| Error::Storyboard(format!("can't decode a frame: {}", e)))?
+                .to_rgb8();
+
+            let (tile_width, tile_height) = *tile_size.get_or_insert(frame.dimensions());
+            let frame = if frame.dimensions() == (tile_width, tile_height) {
+                frame
+            } else {
+                imageops::resize(
+                    &frame,
+                    tile_width,
+                    tile_height,
+                    imageops::FilterType::Triangle,
+                )
+            };
+
+            let slot = index % per_sheet;
+            let canvas = sheet.get_or_insert_with(|| {
+                let rows = (count - index).min(per_sheet).div_ceil(layout.columns);
+                RgbImage::new(layout.columns * tile_width, rows * tile_height)
+            });
+            let (x, y) = (
+                slot % layout.columns * tile_width,
+                slot / layout.columns * tile_height,
+            );
+            imageops::replace(canvas, &frame, x as i64, y as i64);
+
+            let sheet_name = format!("sheet-{}.jpg", sheets.len() + 1);
+            vtt.push_str(&format!(
+                "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+                format_timestamp(start),
+                format_timestamp((start + layout.interval).min(duration)),
+                sheet_name,
+                x,
+                y,
+                tile_width,
+                tile_height
+            ));
+
+            if slot + 1 == per_sheet || index + 1 == count {
+                if let Some(canvas) = sheet.take() {
+                    canvas
+                        .save_with_format(partial.join(&sheet_name), image::ImageFormat::Jpeg)
+                        .map_err(|e| {
+                            Error::Storyboard(format!("can't write '{}': {}", sheet_name, e))
+                        })?;
+                }
+                sheets.push(self.dir.join(&sheet_name));
+            }
+            (self.progress)(index + 1, count);
+        }
+
+        std::fs::write(partial.join(VTT), vtt)?;
+        let (tile_width, tile_height) = tile_size.unwrap_or_default();
+        let storyboard = Storyboard {
+            sheets,
+            vtt: self.dir.join(VTT),
+            tile_width,
+            tile_height,
+            count,
+        };
+        std::fs::write(partial.join(MANIFEST), serde_json::to_vec(&storyboard)?)?;
+
+        let _ = std::fs::remove_dir_all(self.dir);
+        std::fs::rename(&partial, self.dir)?;
+        Ok(storyboard)
+    }
+}
+
+fn check_layout(layout: &StoryboardOptions) -> Result<()> {
+    if !(layout.interval.is_finite() && layout.interval > 0.0) {
+        return Err(Error::Storyboard(format!(
+            "`interval` must be positive, got {}",
+            layout.interval
+        )));
+    }
+    if layout.columns == 0 || layout.rows == 0 || layout.width == 0 {
+        return Err(Error::Storyboard(
+            "`columns`, `rows` and `width` must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The storyboard in `dir`, if it was completed and its files still exist.
+fn read_manifest(dir: &Path) -> Option<Storyboard> {
+    let manifest = std::fs::read(dir.join(MANIFEST)).ok()?;
+    let storyboard: Storyboard = serde_json::from_slice(&manifest).ok()?;
+    let complete =
+        storyboard.vtt.is_file() && storyboard.sheets.iter().all(|sheet| sheet.is_file());
+    complete.then_some(storyboard)
+}
+
+fn partial_dir(dir: &Path) -> PathBuf {
+    dir.with_extension("partial")
+}
+
+/// FNV-1a over the path, size, modification time and layout, stable across builds.
+fn cache_key(path: &str, metadata: &std::fs::Metadata, layout: &StoryboardOptions) -> u64 {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|modified| modified.as_nanos())
+        .unwrap_or_default();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let fields: [&[u8]; 7] = [
+        path.as_bytes(),
+        &metadata.len().to_le_bytes(),
+        &modified.to_le_bytes(),
+        &layout.interval.to_le_bytes(),
+        &layout.columns.to_le_bytes(),
+        &layout.rows.to_le_bytes(),
+        &layout.width.to_le_bytes(),
+    ];
+    for field in fields {
+        for byte in field.iter().chain([&0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Formats seconds as a WebVTT timestamp, `HH:MM:SS.mmm`.
+fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
@@ -0,0 +1,159 @@
+//! Seek-bar thumbnails rendered by hidden instances, with an in-memory cache.
+
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::backend::MpvBackend;
+use crate::config::ThumbnailConfig;
+use crate::headless::{HeadlessInstance, HeadlessPool};
+use crate::{Error, Result};
+
+/// Cache key: path, time in milliseconds and width.
+type CacheKey = (String, i64, u32);
+
+pub(crate) struct Thumbnails {
+    pool: HeadlessPool,
+    cache_size: usize,
+    timeout: Duration,
+    /// Least recently used first.
+    cache: Mutex<IndexMap<CacheKey, Arc<Vec<u8>>>>,
+    /// The newest request for each window and path. Older ones waiting for an instance give up.
+    latest: Mutex<HashMap<(String, String), u64>>,
+    next_request: AtomicU64,
+}
+
+impl Thumbnails {
+    pub fn new(config: &ThumbnailConfig) -> Self {
+        Self {
+            pool: HeadlessPool::new("thumbnails", config.workers),
+            cache_size: config.cache_size,
+            timeout: Duration::from_millis(config.timeout),
+            cache: Mutex::default(),
+            latest: Mutex::default(),
+            next_request: AtomicU64::new(1),
+        }
+    }
+
+    /// A JPEG of the frame at `time` in `path`, scaled to `width`.
+    ///
+    /// Fails with [`Error::Cancelled`] if a newer request for the same window and path
+    /// arrives before this one is rendered, also while its frame is being decoded.
+    pub fn get(
+        &self,
+        backend: &dyn MpvBackend,
+        options: &IndexMap<String, serde_json::Value>,
+        path: &str,
+        time: f64,
+        width: u32,
+        window_label: &str,
+    ) -> Result<Arc<Vec<u8>>> {
+        let key = (path.to_string(), (time * 1000.0).round() as i64, width);
+        if let Some(image) = self.cached(&key) {
+            return Ok(image);
+        }
+
+        let request = self.next_request.fetch_add(1, Ordering::Relaxed);
+        let group = (window_label.to_string(), path.to_string());
+        lock(&self.latest).insert(group.clone(), request);
+        let superseded = || lock(&self.latest).get(&group) != Some(&request);
+
+        let result = self
+            .pool
+            .acquire(backend, options, &superseded)
+            .and_then(|mut instance| {
+                // Another request may have rendered it while this one waited.
+                if let Some(image) = self.cached(&key) {
+                    return Ok(image);
+                }
+                match render(&mut instance, path, time, width, self.timeout, &superseded) {
+                    Ok(image) => Ok(Arc::new(image)),
+                    // An instance interrupted while waiting is discarded by the pool.
+                    Err(e @ Error::Cancelled(_)) => Err(e),
+                    Err(e) => {
+                        instance.discard();
+                        Err(e)
+                    }
+                }
+            });
+
+        {
+            let mut latest = lock(&self.latest);
+            if latest.get(&group) == Some(&request) {
+                latest.remove(&group);
+            }
+        }
+
+        let image = result?;
+        let mut cache = lock(&self.cache);
+        cache.insert(key, image.clone());
+        while cache.len() > self.cache_size {
+            cache.shift_remove_index(0);
+        }
+        Ok(image)
+    }
+
+    /// Destroys the hidden instances.
+    pub fn shutdown(&self) {
+        self.pool.shutdown();
+    }
+
+    fn cached(&self, key: &CacheKey) -> Option<Arc<Vec<u8>>> {
+        let mut cache = lock(&self.cache);
+        let index = cache.get_index_of(key)?;
+        let last = cache.len() - 1;
+        cache.move_index(index, last);
+        cache.get(key).cloned()
+    }
+}
+
+/// Renders the frame, giving up between the steps and while decoding once `superseded` is true.
+fn render(
+    instance: &mut HeadlessInstance,
+    path: &str,
+    time: f64,
+    width: u32,
+    timeout: Duration,
+    superseded: &dyn Fn() -> bool,
+) -> Result<Vec<u8>> {
+    let check = || {
+        if superseded() {
+            Err(Error::Cancelled(
+                "superseded by a newer request".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    };
+
+    // Set before seeking, so the frame decoded next is already scaled.
+    check()?;
+    instance
+        .backend
+        .set_property("vf", &serde_json::json!(format!("scale={}:-2", width)))?;
+
+    check()?;
+    if instance.loaded.as_deref() == Some(path) {
+        instance.seek(time, timeout, superseded)?;
+    } else {
+        // `start` is a time option, which mpv only parses from strings.
+        instance.load(
+            path,
+            &[("start", serde_json::json!(time.to_string()))],
+            timeout,
+            superseded,
+        )?;
+    }
+
+    check()?;
+    instance.capture("jpg")
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
@@ -0,0 +1,298 @@
+use indexmap::IndexMap;
+use log::{info, warn};
+use scopeguard::defer;
+use std::ffi::CString;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::backend::MpvBackend;
+use crate::error::InvalidOption;
+use crate::wrapper::LibmpvWrapper;
+
+/// Options the plugin sets itself, which mpv doesn't list in `options`.
+const IGNORED_OPTIONS: &[&str] = &["wid"];
+
+/// How many log lines are kept from a failed creation.
+const MAX_LOG_LINES: usize = 50;
+
+/// Checks `initial_options` against a bare instance of `backend`, returning every invalid option.
+pub fn find_invalid_options(
+    backend: &dyn MpvBackend,
+    window_label: &str,
+    initial_options: &IndexMap<String, serde_json::Value>,
+) -> Vec<InvalidOption> {
+    let probe = match backend.create(
+        window_label,
+        &IndexMap::new(),
+        &IndexMap::new(),
+        Arc::new(|_| {}),
+    ) {
+        Ok(probe) => probe,
+        Err(e) => {
+            warn!(
+                "Failed to create an mpv instance for option validation: {}",
+                e
+            );
+            return Vec::new();
+        }
+    };
+
+    defer! {
+        if let Err(e) = probe.destroy() {
+            warn!("Failed to destroy the option validation instance: {}", e);
+        }
+    }
+
+    let known_options: Vec<String> = match probe.get_property("options", "node") {
+        Ok(value) => serde_json::from_value(value).unwrap_or_default(),
+        Err(e) => {
+            warn!("Failed to read the mpv option list: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut invalid_options = Vec::new();
+
+    for (name, value) in initial_options {
+        if IGNORED_OPTIONS.contains(&name.as_str()) {
+            continue;
+        }
+
+        if !known_options.iter().any(|known| known == name) {
+            invalid_options.push(InvalidOption {
+                name: name.clone(),
+                value: value.clone(),
+                reason: "unknown option".to_string(),
+            });
+            continue;
+        }
+
+        let info = match probe.get_property(&format!("option-info/{}", name), "node") {
+            Ok(info) => info,
+            Err(_) => continue,
+        };
+
+        if let Some(reason) = check_option_value(&info, value) {
+            invalid_options.push(InvalidOption {
+                name: name.clone(),
+                value: value.clone(),
+                reason,
+            });
+        }
+    }
+
+    invalid_options
+}
+
+/// Retries creation with `log-file` set and returns the log lines mpv wrote.
+///
+/// The retry is not embedded in the window and opens none of its own.
+pub fn capture_creation_log(
+    wrapper: &LibmpvWrapper,
+    initial_options: &IndexMap<String, serde_json::Value>,
+    window_label: &str,
+) -> Vec<String> {
+    let log_path = log_file_path(window_label);
+
+    let mut options = initial_options.clone();
+    options.shift_remove("wid");
+    // Set after the app's options, which could name another log file.
+    for (name, value) in [
+        ("vo", serde_json::json!("null")),
+        ("force-window", serde_json::json!("no")),
+        ("log-file", serde_json::json!(log_path.to_string_lossy())),
+    ] {
+        options.shift_remove(name);
+        options.insert(name.to_string(), value);
+    }
+
+    let handle = serde_json::to_string(&options)
+        .ok()
+        .and_then(|options| CString::new(options).ok())
+        .map(|c_options| unsafe {
+            let c_observed_properties = c"{}";
+            wrapper.mpv_wrapper_create(
+                c_options.as_ptr(),
+                c_observed_properties.as_ptr(),
+                None,
+                std::ptr::null_mut(),
+            )
+        })
+        .unwrap_or(std::ptr::null_mut());
+
+    if !handle.is_null() {
+        info!(
+            "mpv instance for window '{}' was created when retried with logging. Discarding it.",
+            window_label
+        );
+        unsafe { wrapper.mpv_wrapper_destroy(handle) };
+    }
+
+    let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+    let _ = std::fs::remove_file(&log_path);
+
+    let lines: Vec<&str> = log.lines().filter(|line| !line.trim().is_empty()).collect();
+    let problems: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| line.contains("[e]") || line.contains("[f]") || line.contains("[w]"))
+        .collect();
+    let selected = if problems.is_empty() { lines } else { problems };
+
+    selected
+        .iter()
+        .skip(selected.len().saturating_sub(MAX_LOG_LINES))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Checks a value against mpv's `option-info/<name>`, returning why it is invalid.
+fn check_option_value(info: &serde_json::Value, value: &serde_json::Value) -> Option<String> {
+    let option_type = info["type"].as_str().unwrap_or_default();
+    let choices: Vec<&str> = info["choices"]
+        .as_array()
+        .map(|choices| {
+            choices
+                .iter()
+                .filter_map(|choice| choice.as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if let Some(text) = value.as_str() {
+        if choices.contains(&text) {
+            return None;
+        }
+    }
+
+    match option_type {
+        "Flag" => match value {
+            serde_json::Value::Bool(_) => None,
+            serde_json::Value::String(text) if text == "yes" || text == "no" => None,
+            _ => Some("expected a flag (true/false, \"yes\"/\"no\")".to_string()),
+        },
+        "Integer" | "Integer64" | "Double" | "Float" | "Aspect" => {
+            let number = match value {
+                serde_json::Value::Number(number) => number.as_f64(),
+                serde_json::Value::String(text) => text.trim().parse::<f64>().ok(),
+                _ => None,
+            };
+
+            let Some(number) = number else {
+                return Some(if choices.is_empty() {
+                    "expected a number".to_string()
+                } else {
+                    format!("expected a number or one of {:?}", choices)
+                });
+            };
+
+            if option_type.starts_with("Integer") && number.fract() != 0.0 {
+                return Some("expected an integer".to_string());
+            }
+            if let Some(min) = info["min"].as_f64() {
+                if number < min {
+                    return Some(format!("must be at least {}", min));
+                }
+            }
+            if let Some(max) = info["max"].as_f64() {
+                if number > max {
+                    return Some(format!("must be at most {}", max));
+                }
+            }
+            None
+        }
+        "Choice" if !choices.is_empty() => {
+            let number = match value {
+                serde_json::Value::Number(number) => number.as_f64(),
+                serde_json::Value::String(text) => text.trim().parse::<f64>().ok(),
+                _ => None,
+            };
+            let in_range = match (number, info["min"].as_f64(), info["max"].as_f64()) {
+                (Some(number), Some(min), Some(max)) => number >= min && number <= max,
+                _ => false,
+            };
+            (!in_range).then(|| format!("expected one of {:?}", choices))
+        }
+        _ => None,
+    }
+}
+
+fn log_file_path(window_label: &str) -> PathBuf {
+    let label: String = window_label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    std::env::temp_dir().join(format!(
+        "tauri-plugin-libmpv-{}-{}.log",
+        std::process::id(),
+        label
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_option_value;
+    use serde_json::json;
+
+    #[test]
+    fn checks_flags() {
+        let info = json!({ "type": "Flag" });
+        assert_eq!(check_option_value(&info, &json!(true)), None);
+        assert_eq!(check_option_value(&info, &json!("no")), None);
+        assert!(check_option_value(&info, &json!("maybe")).is_some());
+        assert!(check_option_value(&info, &json!(1)).is_some());
+    }
+
+    #[test]
+    fn checks_choices() {
+        let info = json!({ "type": "Choice", "choices": ["auto", "no"], "min": 0, "max": 100 });
+        assert_eq!(check_option_value(&info, &json!("auto")), None);
+        assert_eq!(check_option_value(&info, &json!(50)), None);
+        assert_eq!(check_option_value(&info, &json!("75")), None);
+        assert_eq!(
+            check_option_value(&info, &json!("sometimes")),
+            Some("expected one of [\"auto\", \"no\"]".to_string())
+        );
+        assert!(check_option_value(&info, &json!(101)).is_some());
+
+        let without_range = json!({ "type": "Choice", "choices": ["a", "b"] });
+        assert!(check_option_value(&without_range, &json!(1)).is_some());
+    }
+
+    #[test]
+    fn checks_numeric_ranges() {
+        let volume = json!({ "type": "Float", "min": 0, "max": 1000 });
+        assert_eq!(check_option_value(&volume, &json!(80)), None);
+        assert_eq!(check_option_value(&volume, &json!(" 80.5 ")), None);
+        assert_eq!(
+            check_option_value(&volume, &json!(-1)),
+            Some("must be at least 0".to_string())
+        );
+        assert_eq!(
+            check_option_value(&volume, &json!(1001)),
+            Some("must be at most 1000".to_string())
+        );
+        assert_eq!(
+            check_option_value(&volume, &json!("loud")),
+            Some("expected a number".to_string())
+        );
+
+        let cache = json!({ "type": "Integer", "choices": ["auto"] });
+        assert_eq!(check_option_value(&cache, &json!("auto")), None);
+        assert_eq!(
+            check_option_value(&cache, &json!(1.5)),
+            Some("expected an integer".to_string())
+        );
+        assert_eq!(
+            check_option_value(&cache, &json!(true)),
+            Some("expected a number or one of [\"auto\"]".to_string())
+        );
+    }
+
+    #[test]
+    fn accepts_any_string_value() {
+        let info = json!({ "type": "String" });
+        assert_eq!(check_option_value(&info, &json!("gpu-next")), None);
+        assert_eq!(check_option_value(&info, &json!("")), None);
+    }
+}
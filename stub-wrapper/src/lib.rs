@@ -0,0 +1,336 @@
+//! A stand-in for `libmpv-wrapper` that exports the same symbols without running mpv.
+//!
+//! Used by the plugin's FFI tests. Responses are scripted through options and commands:
+//!
+//! - `stub-fail-create` in the initial options makes `mpv_wrapper_create` return null.
+//! - `stub-event <json>` delivers `<json>` to the event callback from another thread.
+//! - `stub-error <message>` responds with `{"error": message}`.
+//! - `stub-null` returns a null pointer.
+//! - `stub-invalid-json` returns a response that isn't JSON.
+//! - `stub-crash` aborts the process.
+//! - Every other command responds with `{"data": {"name": ..., "args": [...]}}`.
+//!
+//! Initial options are readable as properties. Observed properties get an initial
+//! `property-change` event, and another one whenever they are set.
+//!
+//! The stub reports interface version 1.1, or `STUB_WRAPPER_VERSION` (`major.minor`) if set.
+//! It exports the optional async command and observe symbols, but no hooks or render API.
+//!
+//! `mpv_wrapper_stub_live_strings` returns how many strings handed out are not freed yet.
+
+use serde_json::{json, Map, Value};
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+pub type EventCallback = Option<unsafe extern "C" fn(event: *const c_char, userdata: *mut c_void)>;
+
+static LIVE_STRINGS: AtomicI64 = AtomicI64::new(0);
+
+pub struct MpvHandle {
+    callback: EventCallback,
+    userdata: usize,
+    observed: Mutex<Vec<(u64, String)>>,
+    properties: Mutex<Map<String, Value>>,
+    event_threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl MpvHandle {
+    /// Delivers `event` from a new thread, like mpv's event loop does.
+    fn fire(&self, event: Value) {
+        let Some(callback) = self.callback else {
+            return;
+        };
+        let userdata = self.userdata;
+        let thread = std::thread::spawn(move || {
+            let event = into_raw(event.to_string());
+            unsafe { callback(event, userdata as *mut c_void) };
+        });
+        lock(&self.event_threads).push(thread);
+    }
+
+    fn property_changed(&self, name: &str, value: Value) {
+        let ids: Vec<u64> = lock(&self.observed)
+            .iter()
+            .filter(|(_, observed)| observed == name)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.fire(json!({
+                "event": "property-change",
+                "name": name,
+                "data": value,
+                "id": id,
+            }));
+        }
+    }
+}
+
+/// # Safety
+///
+/// Both strings must be valid JSON C strings.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_create(
+    initial_options: *const c_char,
+    observed_properties: *const c_char,
+    event_callback: EventCallback,
+    event_userdata: *mut c_void,
+) -> *mut MpvHandle {
+    let (Some(Value::Object(options)), Some(Value::Object(observed))) =
+        (parse(initial_options), parse(observed_properties))
+    else {
+        return std::ptr::null_mut();
+    };
+
+    if options.contains_key("stub-fail-create") {
+        return std::ptr::null_mut();
+    }
+
+    let handle = MpvHandle {
+        callback: event_callback,
+        userdata: event_userdata as usize,
+        observed: Mutex::new(
+            observed
+                .keys()
+                .enumerate()
+                .map(|(index, name)| (index as u64 + 1, name.clone()))
+                .collect(),
+        ),
+        properties: Mutex::new(options),
+        event_threads: Mutex::new(Vec::new()),
+    };
+
+    let observed: Vec<String> = lock(&handle.observed)
+        .iter()
+        .map(|(_, name)| name.clone())
+        .collect();
+    for name in &observed {
+        let value = lock(&handle.properties)
+            .get(name)
+            .cloned()
+            .unwrap_or(Value::Null);
+        handle.property_changed(name, value);
+    }
+
+    Box::into_raw(Box::new(handle))
+}
+
+/// Waits for pending events, then frees the handle. No events are delivered afterwards.
+///
+/// # Safety
+///
+/// `handle` must come from `mpv_wrapper_create` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_destroy(handle: *mut MpvHandle) {
+    if handle.is_null() {
+        return;
+    }
+    let handle = unsafe { Box::from_raw(handle) };
+    let threads = std::mem::take(&mut *lock(&handle.event_threads));
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+/// # Safety
+///
+/// `handle` must be live and both strings valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_command(
+    handle: *mut MpvHandle,
+    name: *const c_char,
+    args: *const c_char,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { handle.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+    let args = match parse(args) {
+        Some(Value::Array(args)) => args,
+        _ => return respond(json!({ "error": "invalid args" })),
+    };
+
+    match name.as_ref() {
+        "stub-event" => {
+            let event = args
+                .first()
+                .and_then(Value::as_str)
+                .and_then(|event| serde_json::from_str(event).ok())
+                .unwrap_or(Value::Null);
+            handle.fire(event);
+            respond(json!({}))
+        }
+        "stub-error" => respond(json!({ "error": args.first().cloned().unwrap_or_default() })),
+        "stub-null" => std::ptr::null_mut(),
+        "stub-invalid-json" => into_raw("not json".to_string()),
+        "stub-crash" => std::process::abort(),
+        _ => respond(json!({ "data": { "name": name, "args": args } })),
+    }
+}
+
+/// # Safety
+///
+/// `handle` must be live and both strings valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_set_property(
+    handle: *mut MpvHandle,
+    name: *const c_char,
+    value: *const c_char,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { handle.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned();
+    let Some(value) = parse(value) else {
+        return respond(json!({ "error": "invalid value" }));
+    };
+
+    lock(&handle.properties).insert(name.clone(), value.clone());
+    handle.property_changed(&name, value);
+    respond(json!({}))
+}
+
+/// # Safety
+///
+/// `handle` must be live and both strings valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_get_property(
+    handle: *mut MpvHandle,
+    name: *const c_char,
+    _format: *const c_char,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { handle.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+
+    match lock(&handle.properties).get(name.as_ref()) {
+        Some(value) => respond(json!({ "data": value })),
+        None => respond(json!({ "error": "property not found" })),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn mpv_wrapper_version() -> u32 {
+    let version = std::env::var("STUB_WRAPPER_VERSION").unwrap_or_else(|_| "1.1".to_string());
+    let (major, minor) = version.split_once('.').unwrap_or((&version, "0"));
+    (major.parse::<u32>().unwrap_or(0) << 16) | minor.parse::<u32>().unwrap_or(0)
+}
+
+/// Responds like `mpv_wrapper_command`, then sends the response as a `command-reply` event.
+///
+/// # Safety
+///
+/// `handle` must be live and both strings valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_command_async(
+    handle: *mut MpvHandle,
+    reply_userdata: u64,
+    name: *const c_char,
+    args: *const c_char,
+) -> *mut c_char {
+    let Some(handle_ref) = (unsafe { handle.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+    let args = parse(args).unwrap_or_default();
+    handle_ref.fire(json!({
+        "event": "command-reply",
+        "id": reply_userdata,
+        "error": 0,
+        "result": { "name": name, "args": args },
+    }));
+    respond(json!({}))
+}
+
+/// # Safety
+///
+/// `handle` must be live and both strings valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_observe_property(
+    handle: *mut MpvHandle,
+    reply_userdata: u64,
+    name: *const c_char,
+    _format: *const c_char,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { handle.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned();
+    lock(&handle.observed).push((reply_userdata, name.clone()));
+
+    // Like mpv, report the current value right away.
+    let value = lock(&handle.properties)
+        .get(&name)
+        .cloned()
+        .unwrap_or(Value::Null);
+    handle.fire(json!({
+        "event": "property-change",
+        "name": name,
+        "data": value,
+        "id": reply_userdata,
+    }));
+    respond(json!({}))
+}
+
+/// # Safety
+///
+/// `handle` must be live.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_unobserve_property(
+    handle: *mut MpvHandle,
+    reply_userdata: u64,
+) -> *mut c_char {
+    let Some(handle) = (unsafe { handle.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    lock(&handle.observed).retain(|(id, _)| *id != reply_userdata);
+    respond(json!({}))
+}
+
+/// # Safety
+///
+/// `s` must be null or a string returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn mpv_wrapper_free(s: *mut c_char) {
+    if s.is_null() {
+        return;
+    }
+    drop(unsafe { CString::from_raw(s) });
+    LIVE_STRINGS.fetch_sub(1, Ordering::SeqCst);
+}
+
+/// Number of strings returned or passed to callbacks that haven't been freed.
+#[no_mangle]
+pub extern "C" fn mpv_wrapper_stub_live_strings() -> i64 {
+    LIVE_STRINGS.load(Ordering::SeqCst)
+}
+
+fn respond(response: Value) -> *mut c_char {
+    into_raw(response.to_string())
+}
+
+fn into_raw(text: String) -> *mut c_char {
+    LIVE_STRINGS.fetch_add(1, Ordering::SeqCst);
+    CString::new(text).unwrap_or_default().into_raw()
+}
+
+fn parse(text: *const c_char) -> Option<Value> {
+    if text.is_null() {
+        return None;
+    }
+    let text = unsafe { CStr::from_ptr(text) }.to_str().ok()?;
+    serde_json::from_str(text).ok()
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
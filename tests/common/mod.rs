@@ -0,0 +1,37 @@
+//! Helpers shared by the tests that run against the stub wrapper in `stub-wrapper`.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+const STUB_LIB_NAME: &str = "libmpv_wrapper_stub.so";
+
+/// Builds the stub once and returns its path.
+pub fn stub_path() -> &'static PathBuf {
+    static STUB_PATH: OnceLock<PathBuf> = OnceLock::new();
+    STUB_PATH.get_or_init(|| {
+        // Test binaries live in `<target>/<profile>/deps`.
+        let exe = std::env::current_exe().unwrap();
+        let profile_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+        let path = profile_dir.join(STUB_LIB_NAME);
+
+        if !path.exists() {
+            let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+            let status = Command::new(cargo)
+                .args(["build", "-p", "libmpv-wrapper-stub"])
+                .current_dir(env!("CARGO_MANIFEST_DIR"))
+                .status()
+                .expect("failed to run cargo");
+            assert!(status.success(), "failed to build libmpv-wrapper-stub");
+        }
+        path
+    })
+}
+
+/// The stub counts strings across the whole process, so tests run one at a time.
+pub fn serial() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
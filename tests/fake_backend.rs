@@ -0,0 +1,1500 @@
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use serde_json::json;
+use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+use tauri::App;
+use tauri_plugin_libmpv::{
+    Builder, Error, ExternalTrackOptions, FakeBackend, ImageFormat, MediaKind, MpvConfig, MpvExt,
+    RecordedCommand, RepeatMode, ScreenshotMode, ScreenshotOptions, StoryboardOptions, Track,
+    TrackKind, VideoFrame,
+};
+
+fn app(fake: &FakeBackend, builder: Builder<MockRuntime>) -> App<MockRuntime> {
+    mock_builder()
+        .plugin(builder.backend(fake.clone()).build())
+        .build(mock_context(noop_assets()))
+        .expect("failed to build the app")
+}
+
+fn mpv_config(config: serde_json::Value) -> MpvConfig {
+    serde_json::from_value(config).unwrap()
+}
+
+#[test]
+fn creates_instances_with_merged_options() {
+    let fake = FakeBackend::new();
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({ "initialOptions": { "vo": "gpu-next", "ao": "alsa", "volume": 60 } }),
+    );
+    let preset = [("ao", json!("null")), ("volume", json!(70))]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    let builder = Builder::new()
+        .default_option("hwdec", "auto-safe")
+        .default_option("vo", "gpu")
+        .default_option("ao", "pulse")
+        .default_option("volume", 50)
+        .preset("quiet", preset);
+    let app = mock_builder()
+        .plugin(builder.backend(fake.clone()).build())
+        .build(context)
+        .expect("failed to build the app");
+
+    // Builder defaults < config `initialOptions` < preset < per-call `initialOptions`.
+    let config = mpv_config(json!({
+        "preset": "quiet",
+        "initialOptions": { "vid": "no", "volume": 80 },
+        "observedProperties": { "pause": "flag" },
+    }));
+    app.mpv().init(config, "main").unwrap();
+
+    assert_eq!(fake.instances(), vec!["main".to_string()]);
+    let options = fake.initial_options("main").unwrap();
+    assert_eq!(options["hwdec"], "auto-safe");
+    assert_eq!(options["vo"], "gpu-next");
+    assert_eq!(options["ao"], "null");
+    assert_eq!(options["volume"], 80);
+    assert_eq!(options["vid"], "no");
+
+    let error = app
+        .mpv()
+        .init(mpv_config(json!({ "preset": "loud" })), "other")
+        .unwrap_err();
+    assert!(
+        matches!(&error, Error::UnknownPreset(name) if name == "loud"),
+        "{:?}",
+        error
+    );
+    assert_eq!(fake.instances(), vec!["main".to_string()]);
+}
+
+#[test]
+fn loads_config_from_a_config_dir() {
+    let dir = std::env::temp_dir().join(format!("libmpv-config-dir-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("mpv.conf"), "volume=40\n").unwrap();
+    std::fs::write(dir.join("input.conf"), "q quit\n").unwrap();
+
+    let fake = FakeBackend::new();
+    let app = app(&fake, Builder::new());
+    let mpv = app.mpv();
+    mpv.init(
+        mpv_config(json!({
+            "initialOptions": { "config": "no" },
+            "configDir": { "path": dir },
+        })),
+        "main",
+    )
+    .unwrap();
+
+    let options = fake.initial_options("main").unwrap();
+    assert_eq!(options["config"], "yes");
+    assert_eq!(options["config-dir"], json!(dir.to_string_lossy()));
+
+    mpv.reload_config("main").unwrap();
+    let commands: Vec<_> = fake
+        .commands()
+        .into_iter()
+        .map(|command| (command.name, command.args))
+        .collect();
+    assert_eq!(
+        commands,
+        vec![
+            (
+                "load-config-file".to_string(),
+                vec![json!(dir.join("mpv.conf").to_string_lossy())]
+            ),
+            (
+                "load-input-conf".to_string(),
+                vec![json!(dir.join("input.conf").to_string_lossy())]
+            ),
+        ]
+    );
+
+    mpv.init(mpv_config(json!({})), "other").unwrap();
+    let options = fake.initial_options("other").unwrap();
+    assert!(!options.contains_key("config"));
+    assert!(!options.contains_key("config-dir"));
+    assert!(matches!(
+        mpv.reload_config("other"),
+        Err(Error::NoConfigDir(label)) if label == "other"
+    ));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn lists_and_applies_profiles() {
+    let fake = FakeBackend::new();
+    fake.set_default_property(
+        "profile-list",
+        json!([
+            {
+                "name": "night",
+                "profile-desc": "Quiet playback",
+                "options": [{ "key": "volume", "value": "30" }],
+            },
+            { "name": "gpu-hq", "options": [] },
+        ]),
+    );
+    let app = app(&fake, Builder::new());
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+
+    let profiles = mpv.get_profiles("main").unwrap();
+    assert_eq!(profiles.len(), 2);
+    assert_eq!(profiles[0].name, "night");
+    assert_eq!(profiles[0].profile_desc.as_deref(), Some("Quiet playback"));
+    assert_eq!(profiles[0].options[0].key, "volume");
+    assert_eq!(profiles[0].options[0].value, "30");
+    assert_eq!(profiles[1].profile_desc, None);
+
+    mpv.apply_profile("night", "main").unwrap();
+    let commands = fake.commands();
+    assert_eq!(commands[0].name, "apply-profile");
+    assert_eq!(commands[0].args, vec![json!("night")]);
+
+    fake.update_property("main", "profile-list", json!({ "name": "broken" }));
+    assert!(matches!(
+        mpv.get_profiles("main"),
+        Err(Error::GetProperty { .. })
+    ));
+}
+
+#[test]
+fn forwards_commands_and_properties() {
+    let fake = FakeBackend::new();
+    fake.set_default_property("duration", 42.5);
+    let app = app(&fake, Builder::new());
+
+    let mpv = app.mpv();
+    mpv.init(
+        mpv_config(json!({ "initialOptions": { "vid": "no" } })),
+        "main",
+    )
+    .unwrap();
+
+    mpv.command("loadfile", &vec![json!("video.mp4")], "main")
+        .unwrap();
+    mpv.set_property("pause", &json!(true), "main").unwrap();
+
+    let commands = fake.commands();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].name, "loadfile");
+    assert_eq!(commands[0].args, vec![json!("video.mp4")]);
+    assert_eq!(fake.property("main", "pause"), Some(json!(true)));
+    assert_eq!(
+        mpv.get_property("duration".into(), "double".into(), "main")
+            .unwrap(),
+        json!(42.5)
+    );
+}
+
+#[test]
+fn reports_command_errors() {
+    let fake = FakeBackend::new();
+    fake.set_command_result("loadfile", Err("invalid parameter".to_string()));
+    let app = app(&fake, Builder::new());
+
+    let mpv = app.mpv();
+    mpv.init(
+        mpv_config(json!({ "initialOptions": { "vid": "no" } })),
+        "main",
+    )
+    .unwrap();
+
+    let error = mpv
+        .command("loadfile", &vec![json!("missing.mp4")], "main")
+        .unwrap_err();
+    assert!(matches!(error, Error::Command { message, .. } if message == "invalid parameter"));
+}
+
+#[test]
+fn delivers_events_to_the_event_hook() {
+    let fake = FakeBackend::new();
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let app = app(
+        &fake,
+        Builder::new().on_event(move |_app, window_label, event| {
+            let _ = tx
+                .lock()
+                .unwrap()
+                .send((window_label.to_string(), event.clone()));
+        }),
+    );
+
+    let config = mpv_config(json!({
+        "initialOptions": { "vid": "no" },
+        "observedProperties": { "pause": "flag" },
+    }));
+    app.mpv().init(config, "main").unwrap();
+
+    assert!(fake.update_property("main", "pause", true));
+    let (window_label, event) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(window_label, "main");
+    assert_eq!(event["event"], "property-change");
+    assert_eq!(event["name"], "pause");
+    assert_eq!(event["data"], true);
+}
+
+#[test]
+fn destroys_with_the_close_policy() {
+    let fake = FakeBackend::new();
+    let app = app(&fake, Builder::new());
+
+    let config = mpv_config(json!({
+        "initialOptions": { "vid": "no" },
+        "closePolicy": "quit-watch-later",
+    }));
+    app.mpv().init(config, "main").unwrap();
+    app.mpv().destroy("main").unwrap();
+
+    assert!(fake.instances().is_empty());
+    assert_eq!(fake.destroyed(), vec!["main".to_string()]);
+    assert_eq!(fake.commands()[0].name, "quit-watch-later");
+}
+
+#[test]
+fn reports_creation_failures() {
+    let fake = FakeBackend::new();
+    fake.fail_next_create("option not found");
+    let app = app(&fake, Builder::new());
+
+    let error = app
+        .mpv()
+        .init(
+            mpv_config(json!({ "initialOptions": { "vid": "no" } })),
+            "main",
+        )
+        .unwrap_err();
+
+    assert!(matches!(error, Error::CreateInstance { log, .. } if log == ["option not found"]));
+    assert!(fake.instances().is_empty());
+}
+
+#[test]
+fn renders_in_software_mode() {
+    let fake = FakeBackend::new();
+    let app = app(&fake, Builder::new());
+
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({ "renderMode": "software" })), "main")
+        .unwrap();
+    let options = fake.initial_options("main").unwrap();
+    assert_eq!(options["vo"], "libmpv");
+    assert!(!options.contains_key("wid"));
+
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    mpv.start_render(
+        4,
+        2,
+        Arc::new(move |frame: VideoFrame| {
+            let _ = tx.lock().unwrap().send(frame);
+        }),
+        "main",
+    )
+    .unwrap();
+    mpv.resize_render(8, 4, "main").unwrap();
+    assert_eq!(fake.render_size("main"), Some((8, 4)));
+
+    let frame = VideoFrame {
+        width: 8,
+        height: 4,
+        pixels: vec![0xff; 8 * 4 * 4],
+    };
+    assert!(fake.render_frame("main", frame.clone()));
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), frame);
+}
+
+#[test]
+fn takes_and_saves_screenshots() {
+    let dir = std::env::temp_dir().join(format!("libmpv-screenshots-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let fake = FakeBackend::new();
+    fake.set_default_property("filename/no-ext", "clip");
+    fake.set_default_property("time-pos", 83.5);
+    fake.on_command("screenshot-to-file", |command| {
+        std::fs::write(command.args[0].as_str().unwrap(), b"image").map_err(|e| e.to_string())?;
+        Ok(serde_json::Value::Null)
+    });
+
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({ "screenshotDir": dir, "screenshotTemplate": "{window}-{name}-{pos}" }),
+    );
+    let app = mock_builder()
+        .plugin(
+            Builder::new()
+                .backend(fake.clone())
+                .on_event(move |_app, _window_label, event| {
+                    let _ = tx.lock().unwrap().send(event.clone());
+                })
+                .build(),
+        )
+        .build(context)
+        .expect("failed to build the app");
+
+    let mpv = app.mpv();
+    mpv.init(
+        mpv_config(json!({ "initialOptions": { "vid": "no" } })),
+        "main",
+    )
+    .unwrap();
+
+    let options = ScreenshotOptions {
+        mode: ScreenshotMode::Video,
+        format: ImageFormat::Jpeg,
+        ..Default::default()
+    };
+    assert_eq!(mpv.screenshot(&options, "main").unwrap(), b"image");
+    let args = &fake.commands()[0].args;
+    assert!(args[0].as_str().unwrap().ends_with(".jpg"));
+    assert_eq!(args[1], "video");
+    assert!(!std::path::Path::new(args[0].as_str().unwrap()).exists());
+
+    let first = mpv
+        .save_screenshot(&ScreenshotOptions::default(), "main")
+        .unwrap();
+    let second = mpv
+        .save_screenshot(&ScreenshotOptions::default(), "main")
+        .unwrap();
+    assert_eq!(first, dir.join("main-clip-00-01-23.500.png"));
+    assert_eq!(second, dir.join("main-clip-00-01-23.500-2.png"));
+
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event["event"], "screenshot-saved");
+    assert_eq!(event["path"], json!(first));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn renders_and_caches_thumbnails() {
+    let fake = FakeBackend::new();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = std::sync::Mutex::new(release_rx);
+    {
+        let fake = fake.clone();
+        fake.clone().on_command("loadfile", move |command| {
+            if command.args[0] == "slow.mp4" {
+                release_rx.lock().unwrap().recv().unwrap();
+            }
+            fake.emit(
+                &command.window_label,
+                json!({ "event": "playback-restart" }),
+            );
+            Ok(serde_json::Value::Null)
+        });
+    }
+    {
+        let fake = fake.clone();
+        fake.clone().on_command("seek", move |command| {
+            fake.emit(
+                &command.window_label,
+                json!({ "event": "playback-restart" }),
+            );
+            Ok(serde_json::Value::Null)
+        });
+    }
+    fake.on_command("screenshot-to-file", |command| {
+        std::fs::write(command.args[0].as_str().unwrap(), b"thumb").map_err(|e| e.to_string())?;
+        Ok(serde_json::Value::Null)
+    });
+
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({ "thumbnails": { "workers": 1 } }),
+    );
+    let app = mock_builder()
+        .plugin(Builder::new().backend(fake.clone()).build())
+        .build(context)
+        .expect("failed to build the app");
+
+    let mpv = app.mpv();
+    assert_eq!(
+        mpv.thumbnail("a.mp4", 10.0, 160, "main")
+            .unwrap()
+            .as_slice(),
+        b"thumb"
+    );
+    let options = fake.initial_options("thumbnails-1").unwrap();
+    assert_eq!(options["vo"], "null");
+    assert_eq!(options["keep-open"], "always");
+    assert_eq!(fake.property("thumbnails-1", "vf").unwrap(), "scale=160:-2");
+    assert_eq!(fake.property("thumbnails-1", "start").unwrap(), "10");
+
+    let command_count = fake.commands().len();
+    mpv.thumbnail("a.mp4", 10.0, 160, "main").unwrap();
+    assert_eq!(fake.commands().len(), command_count);
+
+    mpv.thumbnail("a.mp4", 20.0, 160, "main").unwrap();
+    let commands = fake.commands();
+    assert_eq!(commands[command_count].name, "seek");
+    assert_eq!(
+        commands[command_count].args,
+        vec![json!(20.0), json!("absolute+exact")]
+    );
+
+    // The only worker is busy with the first request, so the second waits for it and
+    // is cancelled by the third. The first gives up once its file is loaded.
+    let request = |time: f64| {
+        let app = app.handle().clone();
+        std::thread::spawn(move || app.mpv().thumbnail("slow.mp4", time, 160, "main"))
+    };
+    let first = request(1.0);
+    while !fake
+        .commands()
+        .iter()
+        .any(|command| command.args.first() == Some(&json!("slow.mp4")))
+    {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let second = request(2.0);
+    std::thread::sleep(Duration::from_millis(200));
+    let third = request(3.0);
+
+    assert!(matches!(second.join().unwrap(), Err(Error::Cancelled(_))));
+    release_tx.send(()).unwrap();
+    assert!(matches!(first.join().unwrap(), Err(Error::Cancelled(_))));
+    assert!(third.join().unwrap().is_ok());
+    assert_eq!(fake.instances(), vec!["thumbnails-1".to_string()]);
+
+    mpv.destroy_all(Duration::from_secs(5));
+    assert_eq!(fake.destroyed(), vec!["thumbnails-1".to_string()]);
+}
+
+#[test]
+fn cancels_superseded_thumbnails_while_decoding() {
+    let fake = FakeBackend::new();
+    let loads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    {
+        let fake = fake.clone();
+        let loads = loads.clone();
+        fake.clone().on_command("loadfile", move |command| {
+            // The first load never finishes decoding.
+            if loads.fetch_add(1, std::sync::atomic::Ordering::SeqCst) > 0 {
+                fake.emit(
+                    &command.window_label,
+                    json!({ "event": "playback-restart" }),
+                );
+            }
+            Ok(serde_json::Value::Null)
+        });
+    }
+    fake.on_command("screenshot-to-file", |command| {
+        std::fs::write(command.args[0].as_str().unwrap(), b"thumb").map_err(|e| e.to_string())?;
+        Ok(serde_json::Value::Null)
+    });
+
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({ "thumbnails": { "workers": 1, "timeout": 30_000 } }),
+    );
+    let app = mock_builder()
+        .plugin(Builder::new().backend(fake.clone()).build())
+        .build(context)
+        .expect("failed to build the app");
+
+    let started = std::time::Instant::now();
+    let first = {
+        let app = app.handle().clone();
+        std::thread::spawn(move || app.mpv().thumbnail("a.mp4", 1.0, 160, "main"))
+    };
+    while loads.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let second = app.mpv().thumbnail("a.mp4", 2.0, 160, "main");
+
+    assert!(matches!(first.join().unwrap(), Err(Error::Cancelled(_))));
+    assert_eq!(second.unwrap().as_slice(), b"thumb");
+    assert!(started.elapsed() < Duration::from_secs(10));
+    // The interrupted instance may still send the events of its load, so it isn't reused.
+    assert_eq!(fake.destroyed(), vec!["thumbnails-1".to_string()]);
+    assert!(fake.instances().contains(&"thumbnails-2".to_string()));
+}
+
+#[test]
+fn generates_and_caches_storyboards() {
+    let dir = std::env::temp_dir().join(format!("libmpv-storyboards-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let video = dir.join("video.mp4");
+    let slow = dir.join("slow.mp4");
+    std::fs::write(&video, b"video").unwrap();
+    std::fs::write(&slow, b"slow").unwrap();
+
+    let fake = FakeBackend::new();
+    fake.set_default_property("duration", 25.0);
+    let block_seeks = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = std::sync::Mutex::new(release_rx);
+    {
+        let fake = fake.clone();
+        fake.clone().on_command("loadfile", move |command| {
+            fake.emit(
+                &command.window_label,
+                json!({ "event": "playback-restart" }),
+            );
+            Ok(serde_json::Value::Null)
+        });
+    }
+    {
+        let fake = fake.clone();
+        let block_seeks = block_seeks.clone();
+        fake.clone().on_command("seek", move |command| {
+            if block_seeks.load(std::sync::atomic::Ordering::Relaxed) {
+                release_rx.lock().unwrap().recv().unwrap();
+            }
+            fake.emit(
+                &command.window_label,
+                json!({ "event": "playback-restart" }),
+            );
+            Ok(serde_json::Value::Null)
+        });
+    }
+    fake.on_command("screenshot-to-file", |command| {
+        image::RgbImage::from_pixel(160, 90, image::Rgb([200, 100, 50]))
+            .save(command.args[0].as_str().unwrap())
+            .map_err(|e| e.to_string())?;
+        Ok(serde_json::Value::Null)
+    });
+
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({ "storyboardDir": dir.join("cache") }),
+    );
+    let app = mock_builder()
+        .plugin(
+            Builder::new()
+                .backend(fake.clone())
+                .on_event(move |_app, _window_label, event| {
+                    let _ = tx.lock().unwrap().send(event.clone());
+                })
+                .build(),
+        )
+        .build(context)
+        .expect("failed to build the app");
+
+    let mpv = app.mpv();
+    let layout = StoryboardOptions {
+        interval: 10.0,
+        columns: 2,
+        rows: 1,
+        width: 160,
+    };
+    let video_path = video.to_str().unwrap();
+    let storyboard = mpv
+        .generate_storyboard(video_path, &layout, "main")
+        .unwrap();
+    assert_eq!(storyboard.count, 3);
+    assert_eq!((storyboard.tile_width, storyboard.tile_height), (160, 90));
+    assert_eq!(storyboard.sheets.len(), 2);
+    for sheet in &storyboard.sheets {
+        assert_eq!(image::image_dimensions(sheet).unwrap(), (320, 90));
+    }
+    assert_eq!(
+        std::fs::read_to_string(&storyboard.vtt).unwrap(),
+        "WEBVTT\n\
+         \n00:00:00.000 --> 00:00:10.000\nsheet-1.jpg#xywh=0,0,160,90\n\
+         \n00:00:10.000 --> 00:00:20.000\nsheet-1.jpg#xywh=160,0,160,90\n\
+         \n00:00:20.000 --> 00:00:25.000\nsheet-2.jpg#xywh=0,0,160,90\n"
+    );
+    assert_eq!(
+        fake.property("storyboards-1", "vf").unwrap(),
+        "scale=160:-2"
+    );
+
+    let progress: Vec<_> = (0..3)
+        .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+        .collect();
+    assert_eq!(progress[2]["event"], "storyboard-progress");
+    assert_eq!(progress[2]["path"], video_path);
+    assert_eq!(
+        (progress[2]["done"].clone(), progress[2]["total"].clone()),
+        (json!(3), json!(3))
+    );
+
+    let command_count = fake.commands().len();
+    assert_eq!(
+        mpv.generate_storyboard(video_path, &layout, "main")
+            .unwrap(),
+        storyboard
+    );
+    assert_eq!(fake.commands().len(), command_count);
+
+    block_seeks.store(true, std::sync::atomic::Ordering::Relaxed);
+    let job = {
+        let app = app.handle().clone();
+        let slow = slow.to_str().unwrap().to_string();
+        let layout = layout.clone();
+        std::thread::spawn(move || app.mpv().generate_storyboard(&slow, &layout, "main"))
+    };
+    let first = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(first["done"], 1);
+    assert!(mpv
+        .cancel_storyboard(slow.to_str().unwrap(), "main")
+        .unwrap());
+    release_tx.send(()).unwrap();
+    assert!(matches!(job.join().unwrap(), Err(Error::Cancelled(_))));
+    assert!(!mpv
+        .cancel_storyboard(slow.to_str().unwrap(), "main")
+        .unwrap());
+    assert_eq!(std::fs::read_dir(dir.join("cache")).unwrap().count(), 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn probes_files_without_playing_them() {
+    let fake = FakeBackend::new();
+    fake.set_default_property("duration", 120.0);
+    fake.set_default_property("file-format", "matroska,webm");
+    fake.set_default_property("file-size", 1234);
+    fake.set_default_property("metadata", json!({ "title": "Clip", "track": 3 }));
+    fake.set_default_property(
+        "track-list",
+        json!([
+            {
+                "id": 1, "type": "video", "codec": "h264", "default": true,
+                "demux-w": 1920, "demux-h": 1080, "demux-fps": 24.0,
+            },
+            {
+                "id": 1, "type": "audio", "lang": "en", "codec": "opus",
+                "demux-channel-count": 2, "demux-samplerate": 48000,
+            },
+            { "id": 2, "type": "unknown" },
+        ]),
+    );
+    fake.set_default_property(
+        "chapter-list",
+        json!([{ "title": "Intro", "time": 0.0 }, { "title": "Main", "time": 30.0 }]),
+    );
+    {
+        let fake = fake.clone();
+        fake.clone().on_command("loadfile", move |command| {
+            let event = match command.args[0].as_str().unwrap() {
+                "hang.mkv" => return Ok(serde_json::Value::Null),
+                "broken.mkv" => json!({ "event": "end-file", "reason": "error", "error": -13 }),
+                _ => json!({ "event": "file-loaded" }),
+            };
+            fake.emit(&command.window_label, event);
+            Ok(serde_json::Value::Null)
+        });
+    }
+
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({ "probe": { "workers": 2, "timeout": 200 } }),
+    );
+    let app = mock_builder()
+        .plugin(Builder::new().backend(fake.clone()).build())
+        .build(context)
+        .expect("failed to build the app");
+
+    let mpv = app.mpv();
+    let info = mpv.probe("clip.mkv", "main").unwrap();
+    assert_eq!(info.duration, Some(120.0));
+    assert_eq!(info.file_format.as_deref(), Some("matroska,webm"));
+    assert_eq!(info.file_size, Some(1234));
+    assert_eq!(info.metadata["title"], "Clip");
+    assert_eq!(info.metadata["track"], "3");
+    assert_eq!(info.tracks.len(), 2);
+    assert_eq!(
+        info.tracks[0],
+        Track {
+            id: 1,
+            kind: TrackKind::Video,
+            title: None,
+            lang: None,
+            codec: Some("h264".to_string()),
+            default: true,
+            forced: false,
+            selected: false,
+            external: false,
+            external_filename: None,
+            bitrate: None,
+            channels: None,
+            samplerate: None,
+            width: Some(1920),
+            height: Some(1080),
+            fps: Some(24.0),
+        }
+    );
+    assert_eq!(info.tracks[1].channels, Some(2));
+    assert_eq!(info.chapters[0].end, Some(30.0));
+    assert_eq!(info.chapters[1].title.as_deref(), Some("Main"));
+    assert_eq!(info.chapters[1].end, Some(120.0));
+
+    let options = fake.initial_options("probe-1").unwrap();
+    assert_eq!(options["vid"], "no");
+    assert_eq!(options["aid"], "no");
+    assert_eq!(fake.commands().last().unwrap().name, "stop");
+
+    assert!(matches!(
+        mpv.probe("broken.mkv", "main"),
+        Err(Error::Command { message, .. }) if message.contains("-13")
+    ));
+    assert!(matches!(
+        mpv.probe("hang.mkv", "main"),
+        Err(Error::Timeout(_))
+    ));
+    assert_eq!(
+        fake.destroyed(),
+        vec!["probe-1".to_string(), "probe-2".to_string()]
+    );
+
+    let probes: Vec<_> = (0..4)
+        .map(|index| {
+            let app = app.handle().clone();
+            std::thread::spawn(move || app.mpv().probe(&format!("{}.mkv", index), "main"))
+        })
+        .collect();
+    for probe in probes {
+        assert_eq!(probe.join().unwrap().unwrap().duration, Some(120.0));
+    }
+    assert!(fake.instances().len() <= 2);
+}
+
+#[test]
+fn manages_tracks_and_editions() {
+    let fake = FakeBackend::new();
+    fake.set_default_property(
+        "track-list",
+        json!([
+            { "id": 1, "type": "audio", "lang": "en", "selected": true },
+            { "id": 2, "type": "sub", "title": "Signs", "forced": true },
+        ]),
+    );
+    fake.set_default_property(
+        "edition-list",
+        json!([{ "id": 0, "title": "Theatrical", "default": true }, { "id": 1, "title": "Extended" }]),
+    );
+    fake.set_default_property("current-edition", 1);
+
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let app = app(
+        &fake,
+        Builder::new().on_event(move |_app, _window_label, event| {
+            let _ = tx.lock().unwrap().send(event.clone());
+        }),
+    );
+    let mpv = app.mpv();
+    mpv.init(
+        mpv_config(json!({ "initialOptions": { "vid": "no" } })),
+        "main",
+    )
+    .unwrap();
+
+    let tracks = mpv.list_tracks("main").unwrap();
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0].kind, TrackKind::Audio);
+    assert!(tracks[0].selected);
+    assert_eq!(tracks[1].title.as_deref(), Some("Signs"));
+    assert!(tracks[1].forced);
+
+    mpv.select_track(TrackKind::Sub, Some(2), "main").unwrap();
+    mpv.select_track(TrackKind::Audio, None, "main").unwrap();
+    assert_eq!(fake.property("main", "sid").unwrap(), 2);
+    assert_eq!(fake.property("main", "aid").unwrap(), "no");
+
+    let options = ExternalTrackOptions {
+        select: false,
+        lang: Some("de".to_string()),
+        ..Default::default()
+    };
+    mpv.add_external_track(TrackKind::Sub, "subs.srt", &options, "main")
+        .unwrap();
+    mpv.remove_track(TrackKind::Sub, 3, "main").unwrap();
+    let commands = fake.commands();
+    assert_eq!(commands[0].name, "sub-add");
+    assert_eq!(
+        commands[0].args,
+        vec![json!("subs.srt"), json!("auto"), json!(""), json!("de")]
+    );
+    assert_eq!(commands[1].name, "sub-remove");
+    assert_eq!(commands[1].args, vec![json!(3)]);
+
+    let editions = mpv.list_editions("main").unwrap();
+    assert_eq!(editions.len(), 2);
+    assert!(editions[0].default && !editions[0].selected);
+    assert!(editions[1].selected);
+    mpv.select_edition(0, "main").unwrap();
+    assert_eq!(fake.property("main", "edition").unwrap(), 0);
+
+    fake.update_property(
+        "main",
+        "track-list",
+        json!([{ "id": 1, "type": "video", "demux-w": 640 }]),
+    );
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event["event"], "tracks-changed");
+    assert_eq!(event["tracks"][0]["type"], "video");
+    assert_eq!(event["tracks"][0]["width"], 640);
+    // `track-list` is only observed by the plugin, so its `property-change` isn't sent.
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+#[test]
+fn navigates_chapters() {
+    let fake = FakeBackend::new();
+    let chapters = json!([{ "title": "Intro", "time": 0.0 }, { "title": "Main", "time": 90.5 }]);
+    fake.set_default_property("chapter-list", chapters.clone());
+    fake.set_default_property("duration", 600.0);
+
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let app = app(
+        &fake,
+        Builder::new().on_event(move |_app, _window_label, event| {
+            let _ = tx.lock().unwrap().send(event.clone());
+        }),
+    );
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+
+    let list = mpv.list_chapters("main").unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].title.as_deref(), Some("Intro"));
+    assert_eq!(list[0].end, Some(90.5));
+    assert_eq!(list[1].start, 90.5);
+    assert_eq!(list[1].end, Some(600.0));
+
+    fake.update_property("main", "chapter-list", chapters);
+    fake.update_property("main", "duration", json!(600.0));
+    fake.update_property("main", "chapter", json!(1));
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event["event"], "chapter-changed");
+    assert_eq!(event["index"], 1);
+    assert_eq!(event["title"], "Main");
+    assert_eq!(event["chapter"]["end"], 600.0);
+
+    // Unchanged chapters send nothing, leaving one chapter sends `null`.
+    fake.update_property("main", "chapter", json!(1));
+    fake.update_property("main", "chapter", json!(-1));
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event["event"], "chapter-changed");
+    assert!(event["index"].is_null());
+    assert!(event["chapter"].is_null());
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    mpv.next_chapter("main").unwrap();
+    mpv.prev_chapter("main").unwrap();
+    mpv.seek_chapter(1, "main").unwrap();
+    let commands = fake.commands();
+    assert_eq!(commands[0].name, "add");
+    assert_eq!(commands[0].args, vec![json!("chapter"), json!(1)]);
+    assert_eq!(commands[1].args, vec![json!("chapter"), json!(-1)]);
+    assert_eq!(fake.property("main", "chapter").unwrap(), 1);
+}
+
+#[test]
+fn manages_the_playlist() {
+    let fake = FakeBackend::new();
+    let playlist = json!([
+        { "id": 1, "filename": "a.mkv", "current": true, "playing": true },
+        { "id": 2, "filename": "b.mkv", "title": "Second" },
+        { "id": 5, "filename": "c.mkv" },
+    ]);
+    fake.set_default_property("playlist", playlist.clone());
+
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let app = app(
+        &fake,
+        Builder::new().on_event(move |_app, _window_label, event| {
+            let _ = tx.lock().unwrap().send(event.clone());
+        }),
+    );
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+
+    let playlist_handle = mpv.playlist("main");
+    let entries = playlist_handle.entries().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert!(entries[0].current && entries[0].playing);
+    assert_eq!(entries[1].title.as_deref(), Some("Second"));
+    assert_eq!((entries[2].id, entries[2].index), (5, 2));
+
+    playlist_handle.append("d.mkv").unwrap();
+    playlist_handle.insert_at("e.mkv", 1).unwrap();
+    playlist_handle.insert_next("f.mkv").unwrap();
+    playlist_handle.move_entry(0, 2).unwrap();
+    playlist_handle.move_entry(2, 0).unwrap();
+    playlist_handle.remove(1).unwrap();
+    playlist_handle.clear().unwrap();
+    playlist_handle.play_index(1).unwrap();
+    playlist_handle.play_entry(5).unwrap();
+    assert!(matches!(
+        playlist_handle.play_entry(9),
+        Err(Error::Command { .. })
+    ));
+
+    let commands: Vec<_> = fake
+        .commands()
+        .into_iter()
+        .map(|command| (command.name, command.args))
+        .collect();
+    assert_eq!(
+        commands,
+        vec![
+            ("loadfile".into(), vec![json!("d.mkv"), json!("append")]),
+            (
+                "loadfile".into(),
+                vec![json!("e.mkv"), json!("insert-at"), json!(1)]
+            ),
+            (
+                "loadfile".into(),
+                vec![json!("f.mkv"), json!("insert-next")]
+            ),
+            ("playlist-move".into(), vec![json!(0), json!(3)]),
+            ("playlist-move".into(), vec![json!(2), json!(0)]),
+            ("playlist-remove".into(), vec![json!(1)]),
+            ("playlist-clear".into(), vec![]),
+            ("playlist-play-index".into(), vec![json!(1)]),
+            ("playlist-play-index".into(), vec![json!(2)]),
+        ]
+    );
+
+    fake.update_property("main", "playlist", playlist);
+    let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event["event"], "playlist-changed");
+    assert_eq!(event["entries"][1]["filename"], "b.mkv");
+    assert_eq!(event["entries"][2]["id"], 5);
+}
+
+#[test]
+fn imports_and_exports_playlist_files() {
+    let fake = FakeBackend::new();
+    fake.set_default_property(
+        "playlist",
+        json!([
+            { "id": 1, "filename": "/music/a.mp3", "title": "A", "current": true },
+            { "id": 2, "filename": "https://example.com/b" },
+        ]),
+    );
+    let app = app(&fake, Builder::new());
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+
+    let dir = std::env::temp_dir().join(format!("libmpv-playlist-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let exported = dir.join("queue.m3u8");
+    mpv.playlist("main").export(&exported).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&exported).unwrap(),
+        "#EXTM3U\n#EXTINF:-1,A\n/music/a.mp3\nhttps://example.com/b\n"
+    );
+
+    let imported = dir.join("import.pls");
+    std::fs::write(
+        &imported,
+        "[playlist]\nFile1=one.mp3\nTitle1=One, live\nFile2=/music/two.mp3\n",
+    )
+    .unwrap();
+    assert_eq!(mpv.playlist("main").import(&imported, true).unwrap(), 2);
+
+    let commands = fake.commands();
+    assert_eq!(commands.len(), 2);
+    assert_eq!(
+        commands[0].args,
+        vec![
+            json!(dir.join("one.mp3").to_string_lossy()),
+            json!("replace"),
+            json!(-1),
+            json!("force-media-title=%9%One, live"),
+        ]
+    );
+    assert_eq!(
+        commands[1].args,
+        vec![json!("/music/two.mp3"), json!("append")]
+    );
+
+    std::fs::write(&imported, "[playlist]\nNumberOfEntries=0\n").unwrap();
+    assert!(matches!(
+        mpv.playlist("main").import(&imported, false),
+        Err(Error::PlaylistFile(_))
+    ));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn imports_without_titles_when_mpv_rejects_per_file_options() {
+    let fake = FakeBackend::new();
+    // mpv before 0.38 takes no index, so `-1` is read as invalid options.
+    fake.on_command("loadfile", |command| match command.args.len() {
+        0..=2 => Ok(json!(null)),
+        _ => Err("invalid parameter".to_string()),
+    });
+    let app = app(&fake, Builder::new());
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+
+    let dir = std::env::temp_dir().join(format!("libmpv-old-loadfile-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let imported = dir.join("import.m3u");
+    std::fs::write(
+        &imported,
+        "#EXTM3U\n#EXTINF:-1,One\n/music/one.mp3\n#EXTINF:-1,Two\n/music/two.mp3\n",
+    )
+    .unwrap();
+    assert_eq!(mpv.playlist("main").import(&imported, false).unwrap(), 2);
+
+    let args: Vec<_> = fake
+        .commands()
+        .into_iter()
+        .map(|command| command.args)
+        .collect();
+    assert_eq!(
+        args,
+        vec![
+            vec![
+                json!("/music/one.mp3"),
+                json!("append"),
+                json!(-1),
+                json!("force-media-title=%3%One"),
+            ],
+            vec![json!("/music/one.mp3"), json!("append")],
+            vec![json!("/music/two.mp3"), json!("append")],
+        ]
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Waits for the command the queue engine sends from the event thread.
+fn wait_for_commands(fake: &FakeBackend, count: usize) -> Vec<RecordedCommand> {
+    for _ in 0..100 {
+        let commands = fake.commands();
+        if commands.len() >= count {
+            return commands;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    panic!("expected {} commands, got {:?}", count, fake.commands());
+}
+
+#[test]
+fn drives_the_queue() {
+    let fake = FakeBackend::new();
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let app = app(
+        &fake,
+        Builder::new().on_event(move |_app, _window_label, event| {
+            let _ = tx.lock().unwrap().send(event.clone());
+        }),
+    );
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+    let playlist = mpv.playlist("main");
+
+    let set_playlist = |ids: &[i64]| {
+        let entries: Vec<_> = ids
+            .iter()
+            .map(|id| json!({ "id": id, "filename": format!("{}.mkv", id) }))
+            .collect();
+        fake.update_property("main", "playlist", json!(entries));
+        while rx.recv_timeout(Duration::from_secs(5)).unwrap()["event"] != "playlist-changed" {}
+    };
+    let start = |id: i64| {
+        fake.emit(
+            "main",
+            json!({ "event": "start-file", "playlist_entry_id": id }),
+        );
+    };
+    let end = |id: i64, reason: &str| {
+        fake.emit(
+            "main",
+            json!({ "event": "end-file", "reason": reason, "playlist_entry_id": id }),
+        );
+    };
+    let play_index = |command: &RecordedCommand| {
+        assert_eq!(command.name, "playlist-play-index");
+        command.args[0].as_u64().unwrap() as usize
+    };
+
+    set_playlist(&[1, 2, 3, 4, 5]);
+    start(1);
+
+    // In playlist order without repeat, mpv advances by itself.
+    end(1, "eof");
+    start(2);
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(fake.commands().is_empty());
+
+    playlist.set_repeat(RepeatMode::One).unwrap();
+    end(2, "eof");
+    assert_eq!(play_index(&wait_for_commands(&fake, 1)[0]), 1);
+    // mpv's own advance is overridden and not recorded.
+    start(3);
+    start(2);
+
+    playlist.set_repeat(RepeatMode::All).unwrap();
+    playlist.set_shuffle(true, Some(42)).unwrap();
+    let order = playlist.queue().unwrap().order;
+    assert_eq!(order[0], 2);
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(sorted, vec![1, 2, 3, 4, 5]);
+    playlist.set_shuffle(true, Some(42)).unwrap();
+    assert_eq!(playlist.queue().unwrap().order, order);
+
+    assert!(playlist.next().unwrap());
+    assert_eq!(play_index(&fake.commands()[1]), (order[1] - 1) as usize);
+    start(order[1]);
+
+    // History survives reordering the playlist.
+    set_playlist(&[5, 4, 3, 2, 1]);
+    assert_eq!(playlist.queue().unwrap().order, order);
+    assert!(playlist.previous().unwrap());
+    assert_eq!(play_index(&fake.commands()[2]), 3);
+    start(2);
+    assert!(playlist.next().unwrap());
+    assert_eq!(play_index(&fake.commands()[3]), (5 - order[1]) as usize);
+    start(order[1]);
+    assert_eq!(playlist.queue().unwrap().history, vec![1, 2, order[1]]);
+
+    // Without repeat, the queue stops after its last entry even if mpv wouldn't.
+    playlist.set_repeat(RepeatMode::Off).unwrap();
+    let last = order[4];
+    playlist.play_entry(last).unwrap();
+    start(last);
+    end(last, "eof");
+    if last == 1 {
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(fake.commands().len(), 5);
+    } else {
+        let commands = wait_for_commands(&fake, 6);
+        assert_eq!(commands[5].name, "stop");
+        assert_eq!(commands[5].args, vec![json!("keep-playlist")]);
+    }
+    assert!(!playlist.next().unwrap());
+}
+
+#[test]
+fn loads_directories_in_natural_order() {
+    let fake = FakeBackend::new();
+    let app = app(&fake, Builder::new());
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+
+    let dir = std::env::temp_dir().join(format!("libmpv-directory-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("extras")).unwrap();
+    for name in [
+        "ep10.mkv",
+        "ep2.mkv",
+        "Ep1.mkv",
+        "Ep1.srt",
+        "Ep1.en.srt",
+        "ep2.idx",
+        "ep2.sub",
+        "lonely.srt",
+        "song.flac",
+        "photo.png",
+        "cover.jpg",
+        "notes.txt",
+        ".hidden.mkv",
+        "extras/ep3.mkv",
+    ] {
+        std::fs::write(dir.join(name), b"").unwrap();
+    }
+    let names = |files: &[tauri_plugin_libmpv::MediaFile]| -> Vec<String> {
+        files
+            .iter()
+            .map(|file| {
+                file.path
+                    .strip_prefix(&dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    };
+
+    let files = mpv
+        .playlist("main")
+        .load_directory(&dir, false, &[])
+        .unwrap();
+    assert_eq!(
+        names(&files),
+        vec!["Ep1.mkv", "ep2.mkv", "ep10.mkv", "song.flac"]
+    );
+    assert_eq!(files[0].kind, MediaKind::Video);
+    assert_eq!(
+        files[0].subtitles,
+        vec![dir.join("Ep1.en.srt"), dir.join("Ep1.srt")]
+    );
+    assert_eq!(files[1].subtitles, vec![dir.join("ep2.idx")]);
+    assert_eq!(files[3].kind, MediaKind::Audio);
+
+    let commands = fake.commands();
+    assert_eq!(commands.len(), 4);
+    let en = dir.join("Ep1.en.srt").to_string_lossy().into_owned();
+    let plain = dir.join("Ep1.srt").to_string_lossy().into_owned();
+    assert_eq!(
+        commands[0].args,
+        vec![
+            json!(dir.join("Ep1.mkv").to_string_lossy()),
+            json!("append"),
+            json!(-1),
+            json!(format!(
+                "sub-files-append=%{}%{},sub-files-append=%{}%{}",
+                en.len(),
+                en,
+                plain.len(),
+                plain
+            )),
+        ]
+    );
+    assert_eq!(
+        commands[2].args,
+        vec![
+            json!(dir.join("ep10.mkv").to_string_lossy()),
+            json!("append")
+        ]
+    );
+
+    let files = mpv
+        .playlist("main")
+        .load_directory(
+            &dir,
+            true,
+            &[MediaKind::Video, MediaKind::Image, MediaKind::Subtitle],
+        )
+        .unwrap();
+    assert_eq!(
+        names(&files),
+        vec![
+            "Ep1.mkv",
+            "ep2.mkv",
+            "ep10.mkv",
+            "extras/ep3.mkv",
+            "lonely.srt",
+            "photo.png"
+        ]
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn saves_and_clears_resume_positions() {
+    let dir = std::env::temp_dir().join(format!("libmpv-resume-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let movie = dir.join("movie.mkv");
+    std::fs::write(&movie, b"").unwrap();
+    let movie = movie.to_string_lossy().into_owned();
+
+    let fake = FakeBackend::new();
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({
+            "resume": { "enabled": true, "file": dir.join("resume.json"), "saveInterval": 60_000 }
+        }),
+    );
+    let app = mock_builder()
+        .plugin(Builder::new().backend(fake.clone()).build())
+        .build(context)
+        .expect("failed to build the app");
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+
+    let play = |position: f64| {
+        fake.emit(
+            "main",
+            json!({ "event": "start-file", "playlist_entry_id": 1 }),
+        );
+        fake.update_property("main", "path", movie.as_str());
+        fake.update_property("main", "duration", 100.0);
+        fake.update_property("main", "time-pos", position);
+    };
+    let end = |reason: &str| {
+        fake.emit(
+            "main",
+            json!({ "event": "end-file", "reason": reason, "playlist_entry_id": 1 }),
+        );
+    };
+    let position = |path: &str| {
+        mpv.resume_point(path, "main")
+            .unwrap()
+            .map(|point| point.position)
+    };
+    let wait_for_position = |expected: Option<f64>| {
+        for _ in 0..100 {
+            if position(&movie) == expected {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("expected {:?}, got {:?}", expected, position(&movie));
+    };
+
+    // Saved when the file ends, not before the interval.
+    play(30.0);
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(position(&movie), None);
+    fake.update_property("main", "time-pos", 50.0);
+    fake.update_property("main", "time-pos", serde_json::Value::Null);
+    end("stop");
+    wait_for_position(Some(50.0));
+    let point = mpv.resume_points("main").unwrap().remove(0);
+    assert_eq!(point.duration, 100.0);
+    assert!(point.last_played > 0);
+    let other_spelling = dir.join(".").join("movie.mkv");
+    assert_eq!(position(&other_spelling.to_string_lossy()), Some(50.0));
+
+    // Leaving right away keeps the position from before.
+    play(3.0);
+    end("stop");
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(position(&movie), Some(50.0));
+
+    // Destroying saves synchronously.
+    play(42.0);
+    mpv.destroy("main").unwrap();
+    assert_eq!(position(&movie), Some(42.0));
+    let saved = std::fs::read_to_string(dir.join("resume.json")).unwrap();
+    assert!(saved.contains("\"lastPlayed\""));
+
+    // Playing to the end clears it.
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+    play(60.0);
+    end("eof");
+    wait_for_position(None);
+
+    play(20.0);
+    end("quit");
+    wait_for_position(Some(20.0));
+    assert!(mpv.clear_resume_point(&movie, "main").unwrap());
+    assert!(!mpv.clear_resume_point(&movie, "main").unwrap());
+    assert!(mpv.resume_points("main").unwrap().is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn stores_resume_positions_in_order() {
+    let dir = std::env::temp_dir().join(format!("libmpv-resume-order-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let movie = dir.join("movie.mkv");
+    std::fs::write(&movie, b"").unwrap();
+    let movie = movie.to_string_lossy().into_owned();
+
+    let fake = FakeBackend::new();
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({
+            "resume": { "enabled": true, "file": dir.join("resume.json"), "saveInterval": 0 }
+        }),
+    );
+    let app = mock_builder()
+        .plugin(Builder::new().backend(fake.clone()).build())
+        .build(context)
+        .expect("failed to build the app");
+    let mpv = app.mpv();
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+    let position = || {
+        mpv.resume_point(&movie, "main")
+            .unwrap()
+            .map(|point| point.position)
+    };
+
+    // Every position is saved, the last one wins.
+    fake.emit(
+        "main",
+        json!({ "event": "start-file", "playlist_entry_id": 1 }),
+    );
+    fake.update_property("main", "path", movie.as_str());
+    fake.update_property("main", "duration", 1000.0);
+    for position in 10..=200 {
+        fake.update_property("main", "time-pos", f64::from(position));
+    }
+    mpv.destroy("main").unwrap();
+    assert_eq!(position(), Some(200.0));
+
+    // Clearing waits for the positions still being written, which can't bring it back.
+    mpv.init(mpv_config(json!({})), "main").unwrap();
+    fake.emit(
+        "main",
+        json!({ "event": "start-file", "playlist_entry_id": 1 }),
+    );
+    fake.update_property("main", "path", movie.as_str());
+    fake.update_property("main", "duration", 1000.0);
+    for position in 10..=100 {
+        fake.update_property("main", "time-pos", f64::from(position));
+    }
+    assert!(mpv.clear_resume_point(&movie, "main").unwrap());
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(position(), None);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn saves_resume_positions_of_properties_observed_as_strings() {
+    let dir = std::env::temp_dir().join(format!("libmpv-resume-strings-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let movie = dir.join("movie.mkv");
+    std::fs::write(&movie, b"").unwrap();
+    let movie = movie.to_string_lossy().into_owned();
+
+    let fake = FakeBackend::new();
+    let mut context = mock_context(noop_assets());
+    context.config_mut().plugins.0.insert(
+        "libmpv".to_string(),
+        json!({
+            "resume": { "enabled": true, "file": dir.join("resume.json") }
+        }),
+    );
+    let app = mock_builder()
+        .plugin(Builder::new().backend(fake.clone()).build())
+        .build(context)
+        .expect("failed to build the app");
+    let mpv = app.mpv();
+    mpv.init(
+        mpv_config(json!({
+            "observedProperties": { "time-pos": "string", "duration": "string" },
+        })),
+        "main",
+    )
+    .unwrap();
+
+    fake.emit(
+        "main",
+        json!({ "event": "start-file", "playlist_entry_id": 1 }),
+    );
+    fake.update_property("main", "path", movie.as_str());
+    fake.update_property("main", "duration", "100.000000");
+    fake.update_property("main", "time-pos", "42.500000");
+    mpv.destroy("main").unwrap();
+
+    let point = mpv.resume_point(&movie, "main").unwrap().unwrap();
+    assert_eq!(point.position, 42.5);
+    assert_eq!(point.duration, 100.0);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
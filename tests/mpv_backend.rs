@@ -0,0 +1,338 @@
+//! Tests the `mpv` backend against a fake `mpv` that speaks the JSON IPC protocol.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use tauri_plugin_libmpv::{BackendInstance, Error, IpcBackend, MpvBackend};
+
+use common::serial;
+
+/// Set by the fake `mpv` script to the arguments mpv was started with.
+const FAKE_MPV_ARGS: &str = "FAKE_MPV_ARGS";
+
+/// Pretends to be mpv when started through [`fake_mpv_path`]. Does nothing in a normal run.
+#[test]
+fn fake_mpv() {
+    if let Ok(args) = std::env::var(FAKE_MPV_ARGS) {
+        std::process::exit(serve_fake_mpv(&args));
+    }
+}
+
+/// A script that runs this test binary as [`fake_mpv`].
+fn fake_mpv_path() -> &'static PathBuf {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let exe = std::env::current_exe().unwrap();
+        let path = exe.with_file_name(format!("fake-mpv-{}.sh", std::process::id()));
+        let script = format!(
+            "#!/bin/sh\n{}=\"$*\" exec '{}' fake_mpv --exact --nocapture --test-threads=1\n",
+            FAKE_MPV_ARGS,
+            exe.display()
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    })
+}
+
+/// Answers IPC requests like mpv, with initial options as properties.
+///
+/// `--fake-fail` makes startup fail, the `crash` command aborts and any other
+/// command responds with its own arguments.
+fn serve_fake_mpv(args: &str) -> i32 {
+    let mut properties = serde_json::Map::new();
+    properties.insert("options".to_string(), json!(["volume", "pause"]));
+    properties.insert(
+        "option-info/volume".to_string(),
+        json!({ "type": "Float", "min": 0, "max": 1000 }),
+    );
+    let mut socket_path = None;
+
+    for arg in args.split(' ') {
+        if arg == "--version" {
+            // libtest has already printed `test fake_mpv ... ` on this line.
+            println!("\nmpv 0.99.0 (fake)");
+            return 0;
+        }
+        let Some((name, value)) = arg.trim_start_matches("--").split_once('=') else {
+            continue;
+        };
+        match name {
+            "input-ipc-server" => socket_path = Some(value.to_string()),
+            "fake-fail" => {
+                eprintln!("Error parsing option fake-fail (option not found)");
+                return 1;
+            }
+            _ => {
+                properties.insert(name.to_string(), json!(value));
+            }
+        }
+    }
+
+    let listener = UnixListener::bind(socket_path.expect("no IPC socket")).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    // ID, name and whether mpv formats the value as a string.
+    let mut observed: Vec<(u64, String, bool)> = Vec::new();
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let request: Value = serde_json::from_str(&line).unwrap();
+        let command = request["command"].as_array().cloned().unwrap_or_default();
+        let name = command[0].as_str().unwrap_or_default();
+        let mut events = Vec::new();
+
+        let result = match name {
+            "get_property" | "get_property_string" => properties
+                .get(command[1].as_str().unwrap())
+                .cloned()
+                .ok_or("property unavailable"),
+            "set_property" => {
+                let property = command[1].as_str().unwrap().to_string();
+                properties.insert(property.clone(), command[2].clone());
+                for (id, _, string) in observed.iter().filter(|(_, name, _)| *name == property) {
+                    events.push(json!({
+                        "event": "property-change",
+                        "id": id,
+                        "name": property,
+                        "data": observed_value(&command[2], *string),
+                    }));
+                }
+                Ok(Value::Null)
+            }
+            "observe_property" | "observe_property_string" => {
+                let property = command[2].as_str().unwrap().to_string();
+                let string = name == "observe_property_string";
+                let value = properties.get(&property).cloned().unwrap_or_default();
+                events.push(json!({
+                    "event": "property-change",
+                    "id": command[1],
+                    "name": property,
+                    "data": observed_value(&value, string),
+                }));
+                observed.push((command[1].as_u64().unwrap(), property, string));
+                Ok(Value::Null)
+            }
+            "unobserve_property" => {
+                observed.retain(|(id, _, _)| Some(*id) != command[1].as_u64());
+                Ok(Value::Null)
+            }
+            "fail" => Err("invalid parameter"),
+            "quit" => return 0,
+            "crash" => std::process::abort(),
+            _ => Ok(json!(command)),
+        };
+
+        let response = match result {
+            Ok(data) => {
+                json!({ "request_id": request["request_id"], "error": "success", "data": data })
+            }
+            Err(error) => json!({ "request_id": request["request_id"], "error": error }),
+        };
+        for message in std::iter::once(response).chain(events) {
+            writeln!(writer, "{}", message).unwrap();
+        }
+    }
+    0
+}
+
+/// A property value as `observe_property_string` sends it, if `string` is set.
+fn observed_value(value: &Value, string: bool) -> Value {
+    match value {
+        Value::String(_) => value.clone(),
+        value if string => json!(value.to_string()),
+        value => value.clone(),
+    }
+}
+
+fn create(
+    initial_options: Value,
+    observed_properties: Value,
+) -> (Arc<dyn BackendInstance>, mpsc::Receiver<Value>) {
+    let initial_options: IndexMap<String, Value> = serde_json::from_value(initial_options).unwrap();
+    let observed_properties: IndexMap<String, String> =
+        serde_json::from_value(observed_properties).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let instance = IpcBackend::new(fake_mpv_path())
+        .expect("failed to run the fake mpv")
+        .create(
+            "main",
+            &initial_options,
+            &observed_properties,
+            Arc::new(move |event| {
+                let _ = tx.lock().unwrap().send(event);
+            }),
+        )
+        .expect("failed to create the instance");
+    (instance, rx)
+}
+
+fn next_event(events: &mpsc::Receiver<Value>) -> Value {
+    events
+        .recv_timeout(Duration::from_secs(10))
+        .expect("no event was delivered")
+}
+
+#[test]
+fn reads_the_version() {
+    let _serial = serial();
+    let backend = IpcBackend::new(fake_mpv_path()).unwrap();
+    assert_eq!(backend.version(), "mpv 0.99.0 (fake)");
+
+    assert!(matches!(
+        IpcBackend::new("/nonexistent/mpv"),
+        Err(Error::Ipc(_))
+    ));
+}
+
+#[test]
+fn maps_calls_to_ipc_commands() {
+    let _serial = serial();
+    let (instance, events) = create(json!({ "volume": 50, "pause": true }), json!({}));
+
+    assert_eq!(instance.get_property("pause", "flag").unwrap(), "yes");
+    assert_eq!(
+        instance.command("loadfile", &[json!("a.mp4")]).unwrap(),
+        json!(["loadfile", "a.mp4"])
+    );
+
+    instance.observe_property(3, "volume", "int64").unwrap();
+    let event = next_event(&events);
+    assert_eq!(event["id"], 3);
+    assert_eq!(event["data"], "50");
+
+    instance.set_property("volume", &json!(80)).unwrap();
+    assert_eq!(next_event(&events)["data"], 80);
+    assert_eq!(instance.get_property("volume", "int64").unwrap(), 80);
+
+    instance
+        .command_async(9, "seek", &[json!(10), json!("absolute")])
+        .unwrap();
+    let reply = next_event(&events);
+    assert_eq!(reply["event"], "command-reply");
+    assert_eq!(reply["id"], 9);
+    assert_eq!(reply["result"], json!(["seek", 10, "absolute"]));
+
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn observes_initial_properties() {
+    let _serial = serial();
+    let (instance, events) = create(json!({ "volume": 50 }), json!({ "volume": "int64" }));
+
+    let event = next_event(&events);
+    assert_eq!(event["event"], "property-change");
+    assert_eq!(event["id"], 1);
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn observes_properties_in_their_format() {
+    let _serial = serial();
+    let (instance, events) = create(
+        json!({ "volume": 50 }),
+        json!({ "volume": "string", "mute": "flag" }),
+    );
+    assert_eq!(next_event(&events)["id"], 1);
+    assert_eq!(next_event(&events)["id"], 2);
+
+    instance.observe_property(3, "time-pos", "string").unwrap();
+    instance.observe_property(4, "time-pos", "double").unwrap();
+    assert_eq!(next_event(&events)["id"], 3);
+    assert_eq!(next_event(&events)["id"], 4);
+
+    instance.set_property("time-pos", &json!(12.5)).unwrap();
+    let mut changes = [next_event(&events), next_event(&events)];
+    changes.sort_by_key(|event| event["id"].as_u64());
+    assert_eq!(changes[0]["data"], "12.5");
+    assert_eq!(changes[1]["data"], 12.5);
+
+    instance.set_property("volume", &json!(80)).unwrap();
+    assert_eq!(next_event(&events)["data"], "80");
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn maps_ipc_errors() {
+    let _serial = serial();
+    let (instance, _events) = create(json!({}), json!({}));
+
+    let error = instance.command("fail", &[]).unwrap_err();
+    assert!(matches!(error, Error::Command { message, .. } if message == "invalid parameter"));
+
+    let error = instance.get_property("missing", "node").unwrap_err();
+    assert!(
+        matches!(error, Error::GetProperty { message, .. } if message == "property unavailable")
+    );
+
+    assert!(matches!(
+        instance.hook_add(1, "on_load", 0),
+        Err(Error::UnsupportedFeature(_))
+    ));
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn reports_startup_failures() {
+    let _serial = serial();
+    let options: IndexMap<String, Value> =
+        serde_json::from_value(json!({ "fake-fail": "yes", "volume": 5000, "pause": "yes" }))
+            .unwrap();
+
+    let result = IpcBackend::new(fake_mpv_path()).unwrap().create(
+        "main",
+        &options,
+        &IndexMap::new(),
+        Arc::new(|_| {}),
+    );
+
+    let Err(Error::CreateInstance {
+        log,
+        invalid_options,
+        ..
+    }) = result
+    else {
+        panic!("expected a creation error, got {:?}", result);
+    };
+    assert!(log[0].contains("fake-fail"), "{:?}", log);
+    let invalid: Vec<(&str, &str)> = invalid_options
+        .iter()
+        .map(|option| (option.name.as_str(), option.reason.as_str()))
+        .collect();
+    assert_eq!(
+        invalid,
+        vec![
+            ("fake-fail", "unknown option"),
+            ("volume", "must be at most 1000")
+        ]
+    );
+}
+
+#[test]
+fn reports_crashes() {
+    let _serial = serial();
+    let (instance, events) = create(json!({}), json!({}));
+
+    assert!(instance.command("crash", &[]).is_err());
+
+    let crashed = next_event(&events);
+    assert_eq!(crashed["event"], "crashed");
+    assert_eq!(crashed["signal"], libc::SIGABRT);
+    assert_eq!(crashed["restarting"], false);
+    instance.destroy().unwrap();
+}
@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use tauri_plugin_libmpv::{
+    read_playlist_file, write_playlist_file, PlaylistFileEntry, PlaylistFormat,
+};
+
+fn entry(location: &str, title: Option<&str>, duration: Option<f64>) -> PlaylistFileEntry {
+    PlaylistFileEntry {
+        location: location.to_string(),
+        title: title.map(str::to_string),
+        duration,
+    }
+}
+
+#[test]
+fn parses_m3u_with_extinf() {
+    let content = "\u{feff}#EXTM3U\n\
+        #EXTINF:123,Artist - Song, Live\n\
+        song.mp3\n\
+        \n\
+        #EXTINF:-1 tvg-id=\"news\",News\n\
+        http://example.com/stream\n\
+        # A comment\n\
+        /music/other.flac\n\
+        file:///music/with%20space.ogg\n";
+    let entries = PlaylistFormat::M3u
+        .parse(content, Path::new("/playlists"))
+        .unwrap();
+
+    assert_eq!(
+        entries,
+        vec![
+            entry(
+                "/playlists/song.mp3",
+                Some("Artist - Song, Live"),
+                Some(123.0)
+            ),
+            entry("http://example.com/stream", Some("News"), None),
+            entry("/music/other.flac", None, None),
+            entry("/music/with space.ogg", None, None),
+        ]
+    );
+}
+
+#[test]
+fn parses_pls_in_any_order() {
+    let content = "[playlist]\n\
+        Title2=Second\n\
+        File2=https://example.com/radio\n\
+        Length2=-1\n\
+        file1=sub/first.mp3\n\
+        Length1=61\n\
+        Title3=No file\n\
+        NumberOfEntries=2\n\
+        Version=2\n";
+    let entries = PlaylistFormat::Pls
+        .parse(content, Path::new("/playlists"))
+        .unwrap();
+
+    assert_eq!(
+        entries,
+        vec![
+            entry("/playlists/sub/first.mp3", None, Some(61.0)),
+            entry("https://example.com/radio", Some("Second"), None),
+        ]
+    );
+}
+
+#[test]
+fn parses_xspf() {
+    let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Ignored</title>
+  <trackList>
+    <track>
+      <location>file:///music/a%20b.mp3</location>
+      <location>file:///music/fallback.mp3</location>
+      <title>Rock &amp; Roll</title>
+      <duration>90500</duration>
+      <extension application="http://example.com"><title>Nested</title></extension>
+    </track>
+    <track>
+      <location>relative/c.ogg</location>
+    </track>
+    <track>
+      <title>No location</title>
+    </track>
+  </trackList>
+</playlist>"#;
+    let entries = PlaylistFormat::Xspf
+        .parse(content, Path::new("/playlists"))
+        .unwrap();
+
+    assert_eq!(
+        entries,
+        vec![
+            entry("/music/a b.mp3", Some("Rock & Roll"), Some(90.5)),
+            entry("/playlists/relative/c.ogg", None, None),
+        ]
+    );
+    assert!(PlaylistFormat::Xspf
+        .parse("<playlist><track></playlist>", Path::new("/"))
+        .is_err());
+}
+
+#[test]
+fn reads_files_in_windows_1252() {
+    let dir = std::env::temp_dir().join(format!("libmpv-legacy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("legacy.m3u");
+    std::fs::write(
+        &path,
+        b"#EXTM3U\n#EXTINF:60,Caf\xe9 \x93live\x94\nM\xfcsik.mp3\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        read_playlist_file(&path).unwrap(),
+        vec![entry(
+            &dir.join("Müsik.mp3").to_string_lossy(),
+            Some("Café “live”"),
+            Some(60.0)
+        )]
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn round_trips_every_format() {
+    let entries = vec![
+        entry(
+            "/music/a & b.mp3",
+            Some("A, \"quoted\" <title>"),
+            Some(12.0),
+        ),
+        entry("https://example.com/stream?x=1&y=2", None, None),
+        entry("/music/c.ogg", None, Some(300.0)),
+    ];
+    let dir = std::env::temp_dir().join(format!("libmpv-playlists-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for name in ["list.m3u", "list.M3U8", "list.pls", "list.xspf"] {
+        let path = dir.join(name);
+        write_playlist_file(&path, &entries).unwrap();
+        assert_eq!(read_playlist_file(&path).unwrap(), entries, "{}", name);
+    }
+    assert!(write_playlist_file(&dir.join("list.txt"), &entries).is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
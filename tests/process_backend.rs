@@ -0,0 +1,152 @@
+//! Tests the `process` backend with this test binary as the host and the stub wrapper.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use tauri_plugin_libmpv::{BackendInstance, Error, MpvBackend, ProcessBackend};
+
+use common::stub_path;
+
+/// Entry point of the host processes the other tests start. Does nothing in a normal run.
+#[test]
+fn host() {
+    tauri_plugin_libmpv::host::run_if_requested();
+}
+
+fn backend() -> ProcessBackend {
+    let args = ["host", "--exact", "--nocapture", "--test-threads=1"]
+        .map(String::from)
+        .to_vec();
+    let program = std::env::current_exe().unwrap();
+    unsafe { ProcessBackend::load(program, args, stub_path()) }.expect("failed to load the stub")
+}
+
+fn create(
+    initial_options: Value,
+    observed_properties: Value,
+) -> (Arc<dyn BackendInstance>, mpsc::Receiver<Value>) {
+    let initial_options: IndexMap<String, Value> = serde_json::from_value(initial_options).unwrap();
+    let observed_properties: IndexMap<String, String> =
+        serde_json::from_value(observed_properties).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let instance = backend()
+        .create(
+            "main",
+            &initial_options,
+            &observed_properties,
+            Arc::new(move |event| {
+                let _ = tx.lock().unwrap().send(event);
+            }),
+        )
+        .expect("failed to create the instance");
+    (instance, rx)
+}
+
+/// Skips events until one named `name` arrives.
+fn wait_for_event(events: &mpsc::Receiver<Value>, name: &str) -> Value {
+    loop {
+        let event = events
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap_or_else(|_| panic!("no '{}' event was delivered", name));
+        if event["event"] == name {
+            return event;
+        }
+    }
+}
+
+#[test]
+fn forwards_calls_to_the_host() {
+    let (instance, events) = create(json!({ "volume": 50 }), json!({ "volume": "int64" }));
+
+    assert_eq!(wait_for_event(&events, "property-change")["data"], 50);
+
+    let response = instance.command("loadfile", &[json!("video.mp4")]).unwrap();
+    assert_eq!(response["args"], json!(["video.mp4"]));
+
+    instance.set_property("volume", &json!(80)).unwrap();
+    assert_eq!(instance.get_property("volume", "int64").unwrap(), 80);
+    assert_eq!(wait_for_event(&events, "property-change")["data"], 80);
+
+    instance.destroy().unwrap();
+    assert!(matches!(instance.command("stop", &[]), Err(Error::Ipc(_))));
+}
+
+#[test]
+fn maps_host_errors() {
+    let (instance, _events) = create(json!({}), json!({}));
+
+    let error = instance
+        .command("stub-error", &[json!("invalid parameter")])
+        .unwrap_err();
+    assert!(matches!(error, Error::Command { message, .. } if message == "invalid parameter"));
+
+    let error = instance.get_property("missing", "node").unwrap_err();
+    assert!(matches!(error, Error::GetProperty { window_label, .. } if window_label == "main"));
+
+    assert!(matches!(
+        instance.hook_add(1, "on_load", 0),
+        Err(Error::UnsupportedFeature(_))
+    ));
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn reports_creation_failures() {
+    let options: IndexMap<String, Value> =
+        serde_json::from_value(json!({ "stub-fail-create": true })).unwrap();
+
+    let result = backend().create("main", &options, &IndexMap::new(), Arc::new(|_| {}));
+
+    assert!(matches!(
+        result,
+        Err(Error::CreateInstance { window_label, .. }) if window_label == "main"
+    ));
+}
+
+#[test]
+fn restarts_crashed_hosts() {
+    let (instance, events) = create(json!({ "volume": 50 }), json!({}));
+    instance.observe_property(7, "volume", "int64").unwrap();
+    wait_for_event(&events, "property-change");
+
+    assert!(instance.command("stub-crash", &[]).is_err());
+
+    let crashed = wait_for_event(&events, "crashed");
+    assert_eq!(crashed["restarting"], true);
+    assert_eq!(crashed["signal"], libc::SIGABRT);
+
+    // The observation is restored with the initial value of the new host.
+    let restored = wait_for_event(&events, "property-change");
+    assert_eq!(restored["id"], 7);
+    assert_eq!(restored["data"], 50);
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while instance.get_property("volume", "int64").is_err() {
+        assert!(Instant::now() < deadline, "the host was not restarted");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn reports_the_capabilities_of_the_host() {
+    let capabilities = backend().capabilities();
+    assert!(capabilities.abi_version.is_some());
+    assert!(capabilities.observe_properties);
+    assert!(!capabilities.render_api);
+
+    let program = std::env::current_exe().unwrap();
+    let args = ["host", "--exact", "--nocapture", "--test-threads=1"]
+        .map(String::from)
+        .to_vec();
+    let result = unsafe { ProcessBackend::load(program, args, "/nonexistent/libmpv-wrapper.so") };
+    assert!(matches!(result, Err(Error::FFI(message)) if message.contains("/nonexistent")));
+}
@@ -0,0 +1,228 @@
+//! Tests the `libmpv-wrapper` FFI layer against the stub library in `stub-wrapper`.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use tauri_plugin_libmpv::{
+    BackendCapabilities, BackendInstance, Error, LibmpvWrapper, MpvBackend, WrapperBackend,
+};
+
+use common::{serial, stub_path};
+
+fn backend() -> WrapperBackend {
+    unsafe { WrapperBackend::load(stub_path()) }.expect("failed to load the stub")
+}
+
+fn live_strings() -> i64 {
+    let library = unsafe { libloading::Library::new(stub_path()) }.unwrap();
+    let live_strings: libloading::Symbol<extern "C" fn() -> i64> =
+        unsafe { library.get(b"mpv_wrapper_stub_live_strings\0") }.unwrap();
+    live_strings()
+}
+
+fn create(
+    initial_options: Value,
+    observed_properties: Value,
+) -> (Arc<dyn BackendInstance>, mpsc::Receiver<Value>) {
+    let initial_options: IndexMap<String, Value> = serde_json::from_value(initial_options).unwrap();
+    let observed_properties: IndexMap<String, String> =
+        serde_json::from_value(observed_properties).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let instance = backend()
+        .create(
+            "main",
+            &initial_options,
+            &observed_properties,
+            Arc::new(move |event| {
+                let _ = tx.lock().unwrap().send(event);
+            }),
+        )
+        .expect("failed to create the instance");
+    (instance, rx)
+}
+
+fn next_event(events: &mpsc::Receiver<Value>) -> Value {
+    events
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no event was delivered")
+}
+
+#[test]
+fn encodes_command_arguments_as_json() {
+    let _serial = serial();
+    let (instance, _events) = create(json!({}), json!({}));
+
+    let args = [
+        json!("a \"quoted\" path.mp4"),
+        json!({ "start": 5 }),
+        json!(1.5),
+    ];
+    let response = instance.command("loadfile", &args).unwrap();
+
+    assert_eq!(response["name"], "loadfile");
+    assert_eq!(response["args"], json!(args));
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn round_trips_properties() {
+    let _serial = serial();
+    let (instance, _events) = create(json!({ "volume": 50 }), json!({}));
+
+    assert_eq!(instance.get_property("volume", "int64").unwrap(), 50);
+    instance.set_property("sub-delay", &json!(-0.25)).unwrap();
+    assert_eq!(instance.get_property("sub-delay", "double").unwrap(), -0.25);
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn maps_error_responses() {
+    let _serial = serial();
+    let (instance, _events) = create(json!({}), json!({}));
+
+    let error = instance
+        .command("stub-error", &[json!("invalid parameter")])
+        .unwrap_err();
+    assert!(matches!(error, Error::Command { message, .. } if message == "invalid parameter"));
+
+    let error = instance.get_property("missing", "node").unwrap_err();
+    assert!(matches!(error, Error::GetProperty { message, .. } if message == "property not found"));
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn handles_null_and_malformed_responses() {
+    let _serial = serial();
+    let (instance, _events) = create(json!({}), json!({}));
+
+    assert!(matches!(
+        instance.command("stub-null", &[]),
+        Err(Error::FFI(_))
+    ));
+    assert!(matches!(
+        instance.command("stub-invalid-json", &[]),
+        Err(Error::SerdeJson(_))
+    ));
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn delivers_events_from_foreign_threads() {
+    let _serial = serial();
+    let (instance, events) = create(json!({ "pause": true }), json!({ "pause": "flag" }));
+
+    let event = next_event(&events);
+    assert_eq!(event["event"], "property-change");
+    assert_eq!(event["name"], "pause");
+    assert_eq!(event["data"], true);
+
+    let custom = json!({ "event": "client-message", "args": ["hello"] });
+    instance
+        .command("stub-event", &[json!(custom.to_string())])
+        .unwrap();
+    assert_eq!(next_event(&events), custom);
+
+    instance.destroy().unwrap();
+}
+
+#[test]
+fn frees_every_string() {
+    let _serial = serial();
+    let before = live_strings();
+
+    let (instance, events) = create(json!({ "pause": false }), json!({ "pause": "flag" }));
+    next_event(&events);
+    instance.command("stop", &[]).unwrap();
+    instance.set_property("pause", &json!(true)).unwrap();
+    instance.get_property("pause", "flag").unwrap();
+    let _ = instance.command("stub-error", &[json!("failed")]);
+    let _ = instance.command("stub-invalid-json", &[]);
+    instance.destroy().unwrap();
+
+    assert_eq!(live_strings(), before);
+}
+
+#[test]
+fn reports_creation_failures() {
+    let _serial = serial();
+    let options: IndexMap<String, Value> =
+        serde_json::from_value(json!({ "stub-fail-create": true })).unwrap();
+
+    let result = backend().create("main", &options, &IndexMap::new(), Arc::new(|_| {}));
+
+    assert!(matches!(
+        result,
+        Err(Error::CreateInstance { window_label, .. }) if window_label == "main"
+    ));
+}
+
+#[test]
+fn detects_optional_symbols() {
+    let _serial = serial();
+
+    assert_eq!(
+        backend().capabilities(),
+        BackendCapabilities {
+            abi_version: Some("1.1".to_string()),
+            async_commands: true,
+            observe_properties: true,
+            hooks: false,
+            render_api: false,
+        }
+    );
+
+    let wrapper = unsafe { LibmpvWrapper::new(stub_path()) }.unwrap();
+    assert_eq!(
+        WrapperBackend::new(wrapper).capabilities(),
+        BackendCapabilities::default()
+    );
+}
+
+#[test]
+fn rejects_unsupported_interface_versions() {
+    let _serial = serial();
+
+    std::env::set_var("STUB_WRAPPER_VERSION", "2.0");
+    let result = unsafe { WrapperBackend::load(stub_path()) };
+    std::env::remove_var("STUB_WRAPPER_VERSION");
+
+    assert!(matches!(result, Err(Error::FFI(message)) if message.contains("2.0")));
+}
+
+#[test]
+fn uses_optional_symbols() {
+    let _serial = serial();
+    let (instance, events) = create(json!({}), json!({}));
+
+    instance.observe_property(42, "volume", "int64").unwrap();
+    instance.set_property("volume", &json!(80)).unwrap();
+    // The initial value and the change, in either order.
+    let changes = [next_event(&events), next_event(&events)];
+    assert!(changes
+        .iter()
+        .all(|event| event["event"] == "property-change" && event["id"] == 42));
+    assert!(changes.iter().any(|event| event["data"] == 80));
+
+    instance.unobserve_property(42).unwrap();
+    instance
+        .command_async(7, "seek", &[json!(10), json!("absolute")])
+        .unwrap();
+    let event = next_event(&events);
+    assert_eq!(event["event"], "command-reply");
+    assert_eq!(event["id"], 7);
+    assert_eq!(event["result"]["args"], json!([10, "absolute"]));
+
+    assert!(matches!(
+        instance.hook_add(1, "on_load", 0),
+        Err(Error::UnsupportedFeature(_))
+    ));
+    instance.destroy().unwrap();
+}